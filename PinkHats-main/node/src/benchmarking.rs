@@ -23,10 +23,15 @@
 use crate::service::FullClient;
 
 use node_template_runtime as runtime;
-use runtime::{AccountId, Balance, BalancesCall, SystemCall};
+use runtime::{
+	pallet_medical_record::{self, Content, RecordCategory, UserType},
+	pallet_record_sharing::{self, SealedEnvelope},
+	AccountId, Balance, BalancesCall, MaxCiphertextLength, MaxRecordContentLength, MaxRecordLength,
+	MaxSharedRecords, MaxTitleLength, MedicalRecordConfig, RecordSharingConfig, SystemCall,
+};
 use sc_cli::Result;
 use sc_client_api::BlockBackend;
-use sp_core::{Encode, Pair};
+use sp_core::{bounded::BoundedVec, sr25519, Encode, Get, Pair};
 use sp_inherents::{InherentData, InherentDataProvider};
 use sp_keyring::Sr25519Keyring;
 use sp_runtime::{OpaqueExtrinsic, SaturatedConversion};
@@ -110,6 +115,244 @@ impl frame_benchmarking_cli::ExtrinsicBuilder for TransferKeepAliveBuilder {
 	}
 }
 
+/// How many patients the benchmark chain registers for the medical record builders.
+pub const BENCHMARK_PATIENTS: u32 = 100;
+
+/// The encryption key the benchmark doctor publishes at genesis.
+const BENCHMARK_DOCTOR_ENCRYPTION_KEY: [u8; 32] = [1; 32];
+
+/// The key pair of the `index`th benchmark patient.
+pub fn benchmark_patient(index: u32) -> sr25519::Pair {
+	sr25519::Pair::from_string(&format!("//Patient//{}", index), None)
+		.expect("static values are valid; qed")
+}
+
+/// The doctor who adds records for, and receives sharings from, the benchmark patients.
+fn benchmark_doctor() -> sr25519::Pair {
+	Sr25519Keyring::Alice.pair()
+}
+
+/// The genesis state the medical record builders rely on, as the accounts to endow and the
+/// configs of the medical record and record sharing pallets.
+///
+/// Every benchmark patient is registered with one record, and the benchmark doctor is
+/// registered with a published encryption key.
+pub fn medical_record_benchmark_genesis(
+) -> (Vec<AccountId>, MedicalRecordConfig, RecordSharingConfig) {
+	let doctor: AccountId = benchmark_doctor().public().into();
+	let patients: Vec<AccountId> =
+		(0..BENCHMARK_PATIENTS).map(|i| benchmark_patient(i).public().into()).collect();
+
+	let mut accounts = vec![(doctor.clone(), UserType::Doctor)];
+	accounts.extend(patients.iter().cloned().map(|patient| (patient, UserType::Patient)));
+	let medical_record = MedicalRecordConfig {
+		accounts,
+		records: patients
+			.iter()
+			.cloned()
+			.map(|patient| (patient, RecordCategory::Note, b"genesis".to_vec()))
+			.collect(),
+		..Default::default()
+	};
+	let record_sharing =
+		RecordSharingConfig { encryption_keys: vec![(doctor, BENCHMARK_DOCTOR_ENCRYPTION_KEY)] };
+
+	(patients, medical_record, record_sharing)
+}
+
+// Extrinsics of a block are spread over the benchmark patients, `per_patient` for each.
+// Returns the patient the `nonce`th extrinsic belongs to and its position among theirs.
+fn benchmark_patient_for(
+	nonce: u32,
+	per_patient: u32,
+) -> std::result::Result<(u32, u32), &'static str> {
+	let index = nonce / per_patient;
+	if index >= BENCHMARK_PATIENTS {
+		return Err("Not enough benchmark patients for this many extrinsics")
+	}
+	Ok((index, nonce % per_patient))
+}
+
+// A record content of the maximum length.
+fn benchmark_record_content() -> BoundedVec<u8, MaxRecordContentLength> {
+	vec![0; MaxRecordContentLength::get() as usize]
+		.try_into()
+		.expect("the content has the maximum length; qed")
+}
+
+// A record title of the maximum length.
+fn benchmark_record_title() -> Option<BoundedVec<u8, MaxTitleLength>> {
+	let title = vec![0; MaxTitleLength::get() as usize];
+	Some(title.try_into().expect("the title has the maximum length; qed"))
+}
+
+/// Generates `MedicalRecord::PatientAddsRecord` extrinsics for the benchmarks.
+///
+/// Every extrinsic adds a record with content and title of the maximum length. They are sent by the
+/// benchmark patients of [`medical_record_benchmark_genesis`], so it only works on the benchmark
+/// chain (`--chain benchmark`).
+///
+/// Note: Should only be used for benchmarking.
+pub struct PatientAddsRecordBuilder {
+	client: Arc<FullClient>,
+}
+
+impl PatientAddsRecordBuilder {
+	/// Creates a new [`Self`] from the given client.
+	pub fn new(client: Arc<FullClient>) -> Self {
+		Self { client }
+	}
+}
+
+impl frame_benchmarking_cli::ExtrinsicBuilder for PatientAddsRecordBuilder {
+	fn pallet(&self) -> &str {
+		"medical_record"
+	}
+
+	fn extrinsic(&self) -> &str {
+		"patient_adds_record"
+	}
+
+	fn build(&self, nonce: u32) -> std::result::Result<OpaqueExtrinsic, &'static str> {
+		// Each patient already has their genesis record.
+		let (patient, nonce) = benchmark_patient_for(nonce, MaxRecordLength::get() - 1)?;
+		let record_content = benchmark_record_content();
+		let extrinsic: OpaqueExtrinsic = create_benchmark_extrinsic(
+			self.client.as_ref(),
+			benchmark_patient(patient),
+			pallet_medical_record::Call::<runtime::Runtime>::patient_adds_record {
+				category: RecordCategory::Note,
+				title: benchmark_record_title(),
+				record_content,
+			}
+			.into(),
+			nonce,
+		)
+		.into();
+
+		Ok(extrinsic)
+	}
+}
+
+/// Generates `MedicalRecord::DoctorAddsRecord` extrinsics for the benchmarks.
+///
+/// Every extrinsic adds a signed record with content and title of the maximum length for one of the
+/// benchmark patients of [`medical_record_benchmark_genesis`], so it only works on the benchmark
+/// chain (`--chain benchmark`).
+///
+/// Note: Should only be used for benchmarking.
+pub struct DoctorAddsRecordBuilder {
+	client: Arc<FullClient>,
+}
+
+impl DoctorAddsRecordBuilder {
+	/// Creates a new [`Self`] from the given client.
+	pub fn new(client: Arc<FullClient>) -> Self {
+		Self { client }
+	}
+}
+
+impl frame_benchmarking_cli::ExtrinsicBuilder for DoctorAddsRecordBuilder {
+	fn pallet(&self) -> &str {
+		"medical_record"
+	}
+
+	fn extrinsic(&self) -> &str {
+		"doctor_adds_record"
+	}
+
+	fn build(&self, nonce: u32) -> std::result::Result<OpaqueExtrinsic, &'static str> {
+		let doctor = benchmark_doctor();
+		// Each patient already has their genesis record, so ids start at 2.
+		let (patient, position) = benchmark_patient_for(nonce, MaxRecordLength::get() - 1)?;
+		let patient_id: AccountId = benchmark_patient(patient).public().into();
+		let record_id = position + 2;
+		let record_content = benchmark_record_content();
+
+		let payload = pallet_medical_record::Pallet::<runtime::Runtime>::record_signing_payload(
+			&patient_id,
+			record_id,
+			1,
+			&Content::OnChain(record_content.clone()),
+		);
+		let signature = runtime::Signature::Sr25519(doctor.sign(payload.as_ref()));
+
+		let extrinsic: OpaqueExtrinsic = create_benchmark_extrinsic(
+			self.client.as_ref(),
+			doctor,
+			pallet_medical_record::Call::<runtime::Runtime>::doctor_adds_record {
+				patient_id,
+				category: RecordCategory::Note,
+				title: benchmark_record_title(),
+				record_content,
+				signature,
+			}
+			.into(),
+			nonce,
+		)
+		.into();
+
+		Ok(extrinsic)
+	}
+}
+
+/// Generates `MedicalRecord::ShareRecordWith` extrinsics for the benchmarks.
+///
+/// Every extrinsic shares the genesis record of one of the benchmark patients of
+/// [`medical_record_benchmark_genesis`] with the benchmark doctor, so it only works on the
+/// benchmark chain (`--chain benchmark`).
+///
+/// Note: Should only be used for benchmarking.
+pub struct ShareRecordWithBuilder {
+	client: Arc<FullClient>,
+}
+
+impl ShareRecordWithBuilder {
+	/// Creates a new [`Self`] from the given client.
+	pub fn new(client: Arc<FullClient>) -> Self {
+		Self { client }
+	}
+}
+
+impl frame_benchmarking_cli::ExtrinsicBuilder for ShareRecordWithBuilder {
+	fn pallet(&self) -> &str {
+		"medical_record"
+	}
+
+	fn extrinsic(&self) -> &str {
+		"share_record_with"
+	}
+
+	fn build(&self, nonce: u32) -> std::result::Result<OpaqueExtrinsic, &'static str> {
+		let (patient, nonce) = benchmark_patient_for(nonce, MaxSharedRecords::get())?;
+		let encrypted_key = SealedEnvelope {
+			version: pallet_record_sharing::ENVELOPE_VERSION,
+			algorithm: pallet_record_sharing::ALGORITHM_X25519_XSALSA20_POLY1305,
+			ephemeral_public_key: [2; 32],
+			nonce: [3; 24],
+			ciphertext: vec![4; MaxCiphertextLength::get() as usize]
+				.try_into()
+				.expect("the ciphertext has the maximum length; qed"),
+		};
+		let extrinsic: OpaqueExtrinsic = create_benchmark_extrinsic(
+			self.client.as_ref(),
+			benchmark_patient(patient),
+			pallet_medical_record::Call::<runtime::Runtime>::share_record_with {
+				recipient_id: benchmark_doctor().public().into(),
+				encrypted_key,
+				recipient_key_id: 1,
+				record_id: 1,
+				validity: None,
+			}
+			.into(),
+			nonce,
+		)
+		.into();
+
+		Ok(extrinsic)
+	}
+}
+
 /// Create a transaction using the given `call`.
 ///
 /// Note: Should only be used for benchmarking.
@@ -0,0 +1,453 @@
+//! RPC methods of the medical record pallet.
+
+use std::sync::Arc;
+
+use jsonrpsee::{
+	core::RpcResult,
+	proc_macros::rpc,
+	types::error::{CallError, ErrorObject},
+};
+use node_template_runtime::{opaque::Block, AccountId, BlockNumber, Hash};
+use pallet_medical_record_runtime_api::{
+	MedicalRecordApi as MedicalRecordRuntimeApi, RecordCategory, RecordContent, RecordId,
+	RecordInfo, RecordStatus,
+};
+use pallet_record_sharing_runtime_api::{GrantInfo, RecordSharingApi as RecordSharingRuntimeApi};
+use serde::{Deserialize, Serialize};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::{
+	offchain::{OffchainStorage, STORAGE_PREFIX},
+	Bytes,
+};
+use sp_runtime::{
+	generic::BlockId,
+	traits::{BlakeTwo256, Hash as HashT},
+};
+
+/// The offchain database is disabled on this node.
+const OFFCHAIN_STORAGE_DISABLED: i32 = 1;
+/// The stored payload does not match its anchored hash.
+const PAYLOAD_HASH_MISMATCH: i32 = 2;
+/// The runtime API call failed.
+const RUNTIME_ERROR: i32 = 3;
+
+/// How many items a page holds if the caller does not ask for fewer.
+const MAX_PAGE_SIZE: u32 = 100;
+
+/// A page of a list, starting at the requested offset.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Page<T> {
+	pub items: Vec<T>,
+	/// How many items there are in total.
+	pub total: u32,
+	/// The offset of the next page, if there is one.
+	pub next_offset: Option<u32>,
+}
+
+impl<T> Page<T> {
+	// A page of `total` items holding the ones from `offset` on.
+	fn new(items: Vec<T>, total: u32, offset: u32) -> Self {
+		let end = offset.saturating_add(items.len() as u32);
+		Page { items, total, next_offset: (end < total).then_some(end) }
+	}
+}
+
+// The offset and the number of items to ask the runtime for.
+fn page_bounds(offset: Option<u32>, limit: Option<u32>) -> (u32, u32) {
+	(offset.unwrap_or(0), limit.unwrap_or(MAX_PAGE_SIZE).min(MAX_PAGE_SIZE))
+}
+
+// Read a payload from the offchain database, making sure it still matches its hash.
+fn read_payload<S: OffchainStorage>(storage: Option<&S>, hash: Hash) -> RpcResult<Option<Bytes>> {
+	let storage = storage.ok_or_else(|| {
+		CallError::Custom(ErrorObject::owned(
+			OFFCHAIN_STORAGE_DISABLED,
+			"Offchain storage is disabled on this node",
+			None::<()>,
+		))
+	})?;
+
+	let key = pallet_medical_record::offchain_payload_key(hash.as_ref());
+	match storage.get(STORAGE_PREFIX, &key) {
+		None => Ok(None),
+		Some(payload) if BlakeTwo256::hash(&payload) == hash => Ok(Some(payload.into())),
+		Some(_) => Err(CallError::Custom(ErrorObject::owned(
+			PAYLOAD_HASH_MISMATCH,
+			"Stored payload does not match its hash",
+			None::<()>,
+		))
+		.into()),
+	}
+}
+
+/// A record as returned by the RPC.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Record {
+	pub id: RecordId,
+	pub patient: AccountId,
+	pub category: Category,
+	/// Encrypted with the record key. `None` if the record has no title or is erased.
+	pub title: Option<Bytes>,
+	pub created_at: BlockNumber,
+	/// Milliseconds since the Unix epoch.
+	pub created_on: u64,
+	pub status: Status,
+	/// The doctors who endorsed the current version, oldest first.
+	pub endorsements: Vec<Endorsement>,
+	/// `None` once the record is erased.
+	pub content: Option<Content>,
+	/// The version a doctor has to sign to verify the record.
+	pub version: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Category {
+	Note,
+	Diagnosis,
+	Prescription,
+	Lab,
+	Imaging,
+	Immunization,
+	Allergy,
+}
+
+impl From<RecordCategory> for Category {
+	fn from(category: RecordCategory) -> Self {
+		match category {
+			RecordCategory::Note => Category::Note,
+			RecordCategory::Diagnosis => Category::Diagnosis,
+			RecordCategory::Prescription => Category::Prescription,
+			RecordCategory::Lab => Category::Lab,
+			RecordCategory::Imaging => Category::Imaging,
+			RecordCategory::Immunization => Category::Immunization,
+			RecordCategory::Allergy => Category::Allergy,
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum Status {
+	Unverified,
+	Verified,
+	Erased { at: BlockNumber },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Endorsement {
+	pub doctor: AccountId,
+	pub at: BlockNumber,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum Content {
+	#[serde(rename_all = "camelCase")]
+	OnChain { data: Bytes },
+	/// Fetch the payload with `medicalRecord_getPayload`.
+	#[serde(rename_all = "camelCase")]
+	OffChain { hash: Hash, size: u32, media_type: String },
+}
+
+impl From<RecordInfo<AccountId, Hash, BlockNumber>> for Record {
+	fn from(record: RecordInfo<AccountId, Hash, BlockNumber>) -> Self {
+		Record {
+			id: record.id,
+			patient: record.patient,
+			category: record.category.into(),
+			title: record.title.map(Into::into),
+			created_at: record.created_at,
+			created_on: record.created_on,
+			status: match record.status {
+				RecordStatus::Unverified => Status::Unverified,
+				RecordStatus::Verified => Status::Verified,
+				RecordStatus::Erased { at } => Status::Erased { at },
+			},
+			endorsements: record
+				.endorsements
+				.into_iter()
+				.map(|endorsement| Endorsement { doctor: endorsement.doctor, at: endorsement.at })
+				.collect(),
+			content: record.content.map(|content| match content {
+				RecordContent::OnChain(data) => Content::OnChain { data: data.into() },
+				RecordContent::OffChain { hash, size, media_type } => Content::OffChain {
+					hash,
+					size,
+					media_type: String::from_utf8_lossy(&media_type).into_owned(),
+				},
+			}),
+			version: record.version,
+		}
+	}
+}
+
+/// A sharing of a record as returned by the RPC.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Share {
+	pub sender: AccountId,
+	pub recipient: AccountId,
+	pub record_id: RecordId,
+	pub envelope: Envelope,
+	/// The recipient's encryption key the envelope was sealed for.
+	pub recipient_key_id: u32,
+	/// `None` if the sharing never expires.
+	pub validity: Option<Validity>,
+}
+
+/// The record key sealed for the recipient.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Envelope {
+	pub version: u8,
+	pub algorithm: u8,
+	pub ephemeral_public_key: Bytes,
+	pub nonce: Bytes,
+	pub ciphertext: Bytes,
+}
+
+/// The blocks a sharing can be used in, from `starts_at` up to, but excluding, `ends_at`.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Validity {
+	pub starts_at: BlockNumber,
+	pub ends_at: BlockNumber,
+}
+
+impl From<GrantInfo<AccountId, BlockNumber>> for Share {
+	fn from(grant: GrantInfo<AccountId, BlockNumber>) -> Self {
+		Share {
+			sender: grant.sender,
+			recipient: grant.recipient,
+			record_id: grant.record_id,
+			envelope: Envelope {
+				version: grant.sealed_key.version,
+				algorithm: grant.sealed_key.algorithm,
+				ephemeral_public_key: grant.sealed_key.ephemeral_public_key.to_vec().into(),
+				nonce: grant.sealed_key.nonce.to_vec().into(),
+				ciphertext: grant.sealed_key.ciphertext.into(),
+			},
+			recipient_key_id: grant.recipient_key_id,
+			validity: grant.validity.map(|(starts_at, ends_at)| Validity { starts_at, ends_at }),
+		}
+	}
+}
+
+/// Medical record RPC methods.
+///
+/// Methods reading chain state take an optional block hash and read the best block without one.
+/// Lists hold up to 100 items per page, starting at `offset`.
+#[rpc(client, server)]
+pub trait MedicalRecordApi {
+	/// Return the off-chain payload of a record by the hash anchored on chain.
+	///
+	/// Only nodes running with `--enable-offchain-indexing true` keep payloads.
+	#[method(name = "medicalRecord_getPayload")]
+	fn get_payload(&self, hash: Hash) -> RpcResult<Option<Bytes>>;
+
+	/// List the records of a patient, including erased ones, ordered by id.
+	#[method(name = "medicalRecord_listRecords")]
+	fn list_records(
+		&self,
+		patient: AccountId,
+		offset: Option<u32>,
+		limit: Option<u32>,
+		at: Option<Hash>,
+	) -> RpcResult<Page<Record>>;
+
+	/// Return a record of a patient.
+	#[method(name = "medicalRecord_getRecord")]
+	fn get_record(
+		&self,
+		patient: AccountId,
+		record_id: RecordId,
+		at: Option<Hash>,
+	) -> RpcResult<Option<Record>>;
+
+	/// List the sharings an account received that have not expired.
+	#[method(name = "medicalRecord_listIncomingShares")]
+	fn list_incoming_shares(
+		&self,
+		recipient: AccountId,
+		offset: Option<u32>,
+		limit: Option<u32>,
+		at: Option<Hash>,
+	) -> RpcResult<Page<Share>>;
+
+	/// List the sharings an account gave that have not expired.
+	#[method(name = "medicalRecord_listOutgoingShares")]
+	fn list_outgoing_shares(
+		&self,
+		sender: AccountId,
+		offset: Option<u32>,
+		limit: Option<u32>,
+		at: Option<Hash>,
+	) -> RpcResult<Page<Share>>;
+}
+
+/// Serves records and sharings through the runtime API, and record payloads from the node's
+/// offchain database.
+pub struct MedicalRecord<C, S> {
+	client: Arc<C>,
+	offchain_storage: Option<S>,
+}
+
+impl<C, S> MedicalRecord<C, S> {
+	/// Create new `MedicalRecord` with the given reference to the client and the offchain storage.
+	pub fn new(client: Arc<C>, offchain_storage: Option<S>) -> Self {
+		Self { client, offchain_storage }
+	}
+}
+
+impl<C, S> MedicalRecord<C, S>
+where
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	C::Api: MedicalRecordRuntimeApi<Block, AccountId, Hash, BlockNumber>
+		+ RecordSharingRuntimeApi<Block, AccountId, BlockNumber>,
+{
+	// Call the runtime API at the given block, or at the best block.
+	fn call_at<R>(
+		&self,
+		at: Option<Hash>,
+		call: impl FnOnce(&C::Api, &BlockId<Block>) -> Result<R, sp_api::ApiError>,
+	) -> RpcResult<R> {
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		call(&self.client.runtime_api(), &at).map_err(|e| {
+			CallError::Custom(ErrorObject::owned(
+				RUNTIME_ERROR,
+				"Unable to query medical records",
+				Some(e.to_string()),
+			))
+			.into()
+		})
+	}
+}
+
+impl<C, S> MedicalRecordApiServer for MedicalRecord<C, S>
+where
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: MedicalRecordRuntimeApi<Block, AccountId, Hash, BlockNumber>
+		+ RecordSharingRuntimeApi<Block, AccountId, BlockNumber>,
+	S: OffchainStorage + 'static,
+{
+	fn get_payload(&self, hash: Hash) -> RpcResult<Option<Bytes>> {
+		read_payload(self.offchain_storage.as_ref(), hash)
+	}
+
+	fn list_records(
+		&self,
+		patient: AccountId,
+		offset: Option<u32>,
+		limit: Option<u32>,
+		at: Option<Hash>,
+	) -> RpcResult<Page<Record>> {
+		let (offset, limit) = page_bounds(offset, limit);
+		let page = self.call_at(at, |api, at| api.records_of(at, patient, offset, limit))?;
+		Ok(Page::new(page.items.into_iter().map(Into::into).collect(), page.total, offset))
+	}
+
+	fn get_record(
+		&self,
+		patient: AccountId,
+		record_id: RecordId,
+		at: Option<Hash>,
+	) -> RpcResult<Option<Record>> {
+		let record = self.call_at(at, |api, at| api.record(at, patient, record_id))?;
+		Ok(record.map(Into::into))
+	}
+
+	fn list_incoming_shares(
+		&self,
+		recipient: AccountId,
+		offset: Option<u32>,
+		limit: Option<u32>,
+		at: Option<Hash>,
+	) -> RpcResult<Page<Share>> {
+		let (offset, limit) = page_bounds(offset, limit);
+		let page = self.call_at(at, |api, at| api.grants_to(at, recipient, offset, limit))?;
+		Ok(Page::new(page.items.into_iter().map(Into::into).collect(), page.total, offset))
+	}
+
+	fn list_outgoing_shares(
+		&self,
+		sender: AccountId,
+		offset: Option<u32>,
+		limit: Option<u32>,
+		at: Option<Hash>,
+	) -> RpcResult<Page<Share>> {
+		let (offset, limit) = page_bounds(offset, limit);
+		let page = self.call_at(at, |api, at| api.grants_by(at, sender, offset, limit))?;
+		Ok(Page::new(page.items.into_iter().map(Into::into).collect(), page.total, offset))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use jsonrpsee::core::Error as RpcError;
+	use sp_core::offchain::storage::InMemOffchainStorage;
+
+	fn error_code(error: RpcError) -> i32 {
+		match error {
+			RpcError::Call(CallError::Custom(error)) => error.code(),
+			error => panic!("unexpected error: {:?}", error),
+		}
+	}
+
+	#[test]
+	fn pages_are_bounded() {
+		assert_eq!(page_bounds(None, None), (0, MAX_PAGE_SIZE));
+		assert_eq!(page_bounds(Some(5), Some(10)), (5, 10));
+		assert_eq!(page_bounds(Some(5), Some(MAX_PAGE_SIZE + 1)), (5, MAX_PAGE_SIZE));
+	}
+
+	#[test]
+	fn pages_point_to_the_next_one() {
+		// The first page
+		assert_eq!(
+			Page::new(vec![1, 2], 5, 0),
+			Page { items: vec![1, 2], total: 5, next_offset: Some(2) }
+		);
+		// A page ending right before the last item
+		assert_eq!(Page::new(vec![2, 3, 4], 5, 1).next_offset, Some(4));
+		// The last page, full or not
+		assert_eq!(Page::new(vec![4, 5], 5, 3).next_offset, None);
+		assert_eq!(Page::new(vec![5], 5, 4).next_offset, None);
+		// Past the end
+		assert_eq!(
+			Page::new(Vec::<u32>::new(), 5, 7),
+			Page { items: vec![], total: 5, next_offset: None }
+		);
+		assert_eq!(Page::new(Vec::<u32>::new(), 0, 0).next_offset, None);
+	}
+
+	#[test]
+	fn payloads_are_checked_against_their_hash() {
+		let payload = b"lab report".to_vec();
+		let hash = BlakeTwo256::hash(&payload);
+		let mut storage = InMemOffchainStorage::default();
+
+		assert_eq!(read_payload(Some(&storage), hash).unwrap(), None);
+
+		let key = pallet_medical_record::offchain_payload_key(hash.as_ref());
+		storage.set(STORAGE_PREFIX, &key, &payload);
+		assert_eq!(read_payload(Some(&storage), hash).unwrap(), Some(payload.into()));
+
+		// A payload that was changed in the database is not served
+		storage.set(STORAGE_PREFIX, &key, b"tampered");
+		let error = read_payload(Some(&storage), hash).unwrap_err();
+		assert_eq!(error_code(error), PAYLOAD_HASH_MISMATCH);
+	}
+
+	#[test]
+	fn payloads_need_offchain_storage() {
+		let hash = BlakeTwo256::hash(b"lab report");
+		let error = read_payload::<InMemOffchainStorage>(None, hash).unwrap_err();
+		assert_eq!(error_code(error), OFFCHAIN_STORAGE_DISABLED);
+	}
+}
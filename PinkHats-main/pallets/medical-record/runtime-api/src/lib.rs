@@ -0,0 +1,172 @@
+//! Runtime API to query medical records.
+//!
+//! The types returned here are kept independent of the pallet's storage layout, so clients can
+//! depend on them across storage migrations.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Codec, Decode, Encode};
+use scale_info::TypeInfo;
+use sp_std::vec::Vec;
+
+pub type RecordId = u32;
+/// Milliseconds since the Unix epoch, as kept by `pallet_timestamp`.
+pub type Moment = u64;
+
+/// A role an account holds.
+#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, TypeInfo)]
+pub enum Role {
+	Patient,
+	Doctor,
+	Nurse,
+	Pharmacist,
+	Laboratory,
+	Insurer,
+	Researcher,
+	EmergencyResponder,
+}
+
+/// What a record is about.
+#[derive(Decode, Encode, Clone, Copy, Debug, Eq, PartialEq, TypeInfo)]
+pub enum RecordCategory {
+	Note,
+	Diagnosis,
+	Prescription,
+	Lab,
+	Imaging,
+	Immunization,
+	Allergy,
+}
+
+/// What a record holds, or where to find it.
+#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, TypeInfo)]
+pub enum RecordContent<Hash> {
+	OnChain(Vec<u8>),
+	/// A payload kept in the offchain database of indexing nodes.
+	OffChain {
+		hash: Hash,
+		size: u32,
+		media_type: Vec<u8>,
+	},
+}
+
+#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, TypeInfo)]
+pub enum RecordStatus<BlockNumber> {
+	/// Endorsed by fewer doctors than the record's category asks for.
+	Unverified,
+	Verified,
+	Erased {
+		at: BlockNumber,
+	},
+}
+
+/// A doctor's endorsement of the current version of a record.
+#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, TypeInfo)]
+pub struct Endorsement<AccountId, BlockNumber> {
+	pub doctor: AccountId,
+	pub at: BlockNumber,
+}
+
+#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, TypeInfo)]
+pub struct RecordInfo<AccountId, Hash, BlockNumber> {
+	pub id: RecordId,
+	pub patient: AccountId,
+	pub category: RecordCategory,
+	/// Encrypted with the record key. `None` if the record has no title or is erased.
+	pub title: Option<Vec<u8>>,
+	pub created_at: BlockNumber,
+	pub created_on: Moment,
+	pub status: RecordStatus<BlockNumber>,
+	/// Oldest first. Empty once the record is erased.
+	pub endorsements: Vec<Endorsement<AccountId, BlockNumber>>,
+	/// `None` once the record is erased.
+	pub content: Option<RecordContent<Hash>>,
+	/// The version a doctor has to sign to verify the record.
+	pub version: u32,
+}
+
+/// Part of a list, starting at the requested offset.
+#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, TypeInfo)]
+pub struct Page<T> {
+	pub items: Vec<T>,
+	/// How many items the whole list holds.
+	pub total: u32,
+}
+
+/// Which records `records_matching` returns. Every field left empty matches any record.
+#[derive(Decode, Encode, Clone, Debug, Default, Eq, PartialEq, TypeInfo)]
+pub struct RecordFilter<BlockNumber> {
+	pub categories: Vec<RecordCategory>,
+	/// Records created in the blocks `[from, to)`.
+	pub created_at: Option<(BlockNumber, BlockNumber)>,
+	/// Records created in the time span `[from, to)`.
+	pub created_on: Option<(Moment, Moment)>,
+	/// Whether the record has a title.
+	pub has_title: Option<bool>,
+}
+
+impl<BlockNumber: PartialOrd> RecordFilter<BlockNumber> {
+	pub fn matches<AccountId, Hash>(
+		&self,
+		record: &RecordInfo<AccountId, Hash, BlockNumber>,
+	) -> bool {
+		(self.categories.is_empty() || self.categories.contains(&record.category)) &&
+			self.created_at
+				.as_ref()
+				.map_or(true, |(from, to)| from <= &record.created_at && &record.created_at < to) &&
+			self.created_on
+				.map_or(true, |(from, to)| from <= record.created_on && record.created_on < to) &&
+			self.has_title.map_or(true, |has_title| has_title == record.title.is_some())
+	}
+}
+
+/// How an actor came to act on a patient's records.
+#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, TypeInfo)]
+pub enum AccessPath {
+	Direct,
+	Guardian,
+	Emergency,
+	Policy,
+	Upgrade,
+}
+
+#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, TypeInfo)]
+pub enum AuditAction<AccountId> {
+	Grant { recipient: AccountId },
+	Revoke { recipient: AccountId },
+	Verify,
+	BreakGlass,
+	SetEmergencyProfile,
+	ClearEmergencyProfile,
+}
+
+#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, TypeInfo)]
+pub struct AuditEntry<AccountId, BlockNumber> {
+	pub actor: AccountId,
+	pub action: AuditAction<AccountId>,
+	/// `None` if the action is not about a single record.
+	pub record_id: Option<RecordId>,
+	pub path: AccessPath,
+	pub purpose: Vec<u8>,
+	pub at: BlockNumber,
+}
+
+sp_api::decl_runtime_apis! {
+	pub trait MedicalRecordApi<AccountId, Hash, BlockNumber>
+	where
+		AccountId: Codec,
+		Hash: Codec,
+		BlockNumber: Codec,
+	{
+		/// The roles `account` holds.
+		fn account_roles(account: AccountId) -> Vec<Role>;
+		/// Up to `limit` records of `patient` from `offset` on, including erased ones, ordered
+		/// by id.
+		fn records_of(patient: AccountId, offset: u32, limit: u32) -> Page<RecordInfo<AccountId, Hash, BlockNumber>>;
+		fn record(patient: AccountId, id: RecordId) -> Option<RecordInfo<AccountId, Hash, BlockNumber>>;
+		/// The records of `patient` that match `filter`, including erased ones, ordered by id.
+		fn records_matching(patient: AccountId, filter: RecordFilter<BlockNumber>) -> Vec<RecordInfo<AccountId, Hash, BlockNumber>>;
+		/// The audit log of `patient`, oldest entry first.
+		fn audit_log(patient: AccountId) -> Vec<AuditEntry<AccountId, BlockNumber>>;
+	}
+}
@@ -1,20 +1,745 @@
-//! Benchmarking setup for pallet-template
+//! Benchmarking setup for pallet-medical-record
 
 use super::*;
 
-#[allow(unused)]
-use crate::Pallet as Template;
-use frame_benchmarking::{benchmarks, whitelisted_caller};
+use crate::Pallet as MedicalRecord;
+use frame_benchmarking::{account, benchmarks, whitelisted_caller};
+use frame_support::{
+	traits::{Currency, EnsureOrigin, Get, ReservableCurrency},
+	BoundedVec,
+};
 use frame_system::RawOrigin;
+use pallet_record_sharing::{AccessPath, AuditAction, KeyId};
+use sp_runtime::traits::{Bounded, Hash, Zero};
+use sp_std::{vec, vec::Vec};
+
+const SEED: u32 = 0;
+// The most accounts an erased record is shared with in the benchmark. Nothing bounds them;
+// `erase_record` is weighed by how many the caller says there are.
+const MAX_RECIPIENTS: u32 = 100;
+
+// Register the account and give it enough to pay for the deposits.
+fn register<T: Config>(account_id: &T::AccountId, user_type: UserType) {
+	MedicalRecord::<T>::grant_role(account_id, user_type).expect("the account is new");
+	T::Currency::make_free_balance_be(account_id, BalanceOf::<T>::max_value() / 2u32.into());
+}
+
+// Add `count` records for the patient, so the benchmarked one is not the first.
+fn add_records<T: Config>(patient_id: &T::AccountId, count: u32) {
+	for _ in 0..count {
+		MedicalRecord::<T>::patient_adds_record(
+			RawOrigin::Signed(patient_id.clone()).into(),
+			RecordCategory::Note,
+			None,
+			Default::default(),
+		)
+		.expect("the patient has room for more records");
+	}
+}
+
+// Add an offchain record with a title for the patient and amend it until it keeps `versions`
+// versions. Every version holds a payload of its own and as many endorsements as fit, so
+// dropping one releases its payload and returns every deposit it can.
+fn add_offchain_versions<T: Config>(patient_id: &T::AccountId, versions: u32) {
+	MedicalRecord::<T>::patient_adds_offchain_record(
+		RawOrigin::Signed(patient_id.clone()).into(),
+		RecordCategory::Note,
+		record_title::<T>(),
+		payload::<T>(0),
+		media_type::<T>(),
+	)
+	.expect("the patient has room for a record");
+	add_endorsements::<T>(patient_id, 1, T::MaxEndorsements::get());
+	for version in 1..versions {
+		MedicalRecord::<T>::amend_offchain_record(
+			RawOrigin::Signed(patient_id.clone()).into(),
+			patient_id.clone(),
+			1,
+			version.to_le_bytes().to_vec().try_into().expect("MaxPayloadLength fits a u32"),
+			media_type::<T>(),
+		)
+		.expect("the patient can amend their record");
+		add_endorsements::<T>(patient_id, 1, T::MaxEndorsements::get());
+	}
+}
+
+fn record_content<T: Config>(len: u32) -> BoundedVec<u8, T::MaxRecordContentLength> {
+	vec![0; len as usize].try_into().expect("len is at most MaxRecordContentLength")
+}
+
+fn payload<T: Config>(len: u32) -> BoundedVec<u8, T::MaxPayloadLength> {
+	vec![0; len as usize].try_into().expect("len is at most MaxPayloadLength")
+}
+
+// A media type of the maximum length.
+fn media_type<T: Config>() -> BoundedVec<u8, T::MaxMediaTypeLength> {
+	let media_type = vec![0; T::MaxMediaTypeLength::get() as usize];
+	media_type.try_into().expect("the media type has the maximum length")
+}
+
+// A title of the maximum length, so the record takes up as much storage as it can.
+fn record_title<T: Config>() -> Option<BoundedVec<u8, T::MaxTitleLength>> {
+	let title = vec![0; T::MaxTitleLength::get() as usize];
+	Some(title.try_into().expect("the title has the maximum length"))
+}
+
+// A reason of the maximum length, so audit entries take up as much storage as they can.
+fn purpose<T: Config>() -> BoundedVec<u8, T::MaxPurposeLength> {
+	let purpose = vec![0; T::MaxPurposeLength::get() as usize];
+	purpose.try_into().expect("the purpose has the maximum length")
+}
+
+// Fill the patient's audit log, so the next entry has to drop the oldest one.
+fn fill_audit_log<T: Config>(patient_id: &T::AccountId) {
+	let entry = AuditEntry::<T> {
+		actor: patient_id.clone(),
+		action: AuditAction::Grant { recipient: patient_id.clone() },
+		record_id: Some(1),
+		path: AccessPath::Direct,
+		purpose: purpose::<T>(),
+		at: Zero::zero(),
+	};
+	let log = vec![entry; T::MaxAuditEntries::get() as usize];
+	let log =
+		BoundedVec::<_, T::MaxAuditEntries>::try_from(log).expect("the log is full, not over");
+	AuditLog::<T>::insert(patient_id, log);
+}
+
+// Have `count` other doctors endorse the current version of the record, each reserving the
+// deposit for their endorsement.
+fn add_endorsements<T: Config>(patient_id: &T::AccountId, record_id: RecordId, count: u32) {
+	let signer = T::BenchmarkHelper::signer();
+	let signature = T::BenchmarkHelper::sign(&signer, &[]);
+	let endorsements: Vec<Endorsement<T>> = (0..count)
+		.map(|i| {
+			let doctor: T::AccountId = account("doctor", i, SEED);
+			let mut endorsement = Endorsement {
+				doctor: doctor.clone(),
+				signature: signature.clone(),
+				at: Zero::zero(),
+				deposit: Zero::zero(),
+			};
+			endorsement.deposit = MedicalRecord::<T>::endorsement_deposit(&endorsement);
+			T::Currency::make_free_balance_be(&doctor, BalanceOf::<T>::max_value() / 2u32.into());
+			T::Currency::reserve(&doctor, endorsement.deposit)
+				.expect("the doctor was given enough for the deposit");
+			endorsement
+		})
+		.collect();
+	let endorsements: BoundedVec<_, T::MaxEndorsements> =
+		endorsements.try_into().expect("count is below MaxEndorsements");
+	PatientRecords::<T>::mutate(patient_id, record_id, |record| {
+		if let Some(record) = record {
+			let content = record.get_content().cloned().expect("the record is not erased");
+			record.status = RecordStatus::Unverified(content, endorsements.clone());
+		}
+	});
+	RecordVersions::<T>::mutate(patient_id, record_id, |versions| {
+		if let Some(current) = versions.last_mut() {
+			current.endorsements = endorsements;
+		}
+	});
+}
+
+// Share the record with `recipient`, who is registered as a doctor for it.
+fn share<T: Config>(patient_id: &T::AccountId, recipient: &T::AccountId, record_id: RecordId) {
+	register::<T>(recipient, UserType::Doctor);
+	let (encrypted_key, key_id) = T::BenchmarkHelper::sealed_key(recipient);
+	MedicalRecord::<T>::share_record_with(
+		RawOrigin::Signed(patient_id.clone()).into(),
+		recipient.clone(),
+		encrypted_key,
+		key_id,
+		record_id,
+		None,
+	)
+	.expect("the recipient may receive the record");
+}
+
+// Publish a new emergency key and return its id.
+fn rotate_emergency_key<T: Config>() -> KeyId {
+	MedicalRecord::<T>::set_emergency_key(T::LicensingOrigin::successful_origin(), [0; 32])
+		.expect("the licensing authority sets the emergency key");
+	MedicalRecord::<T>::emergency_key()
+		.map(|(key_id, _)| key_id)
+		.expect("the key was just set")
+}
+
+// Register `guardian_id` and appoint them with every scope.
+fn appoint<T: Config>(patient_id: &T::AccountId, guardian_id: &T::AccountId) {
+	register::<T>(guardian_id, UserType::Doctor);
+	MedicalRecord::<T>::appoint_guardian(
+		RawOrigin::Signed(patient_id.clone()).into(),
+		guardian_id.clone(),
+		GuardianScope { add_records: true, share: true, revoke_shares: true },
+		None,
+	)
+	.expect("the guardian is registered");
+}
+
+// The records `1..=count`, or the patient's notes if `count` is 0.
+fn requested_records<T: Config>(count: u32) -> RequestedRecords<T> {
+	if count == 0 {
+		return RequestedRecords::Category(RecordCategory::Note)
+	}
+	let record_ids: Vec<RecordId> = (1..=count).collect();
+	RequestedRecords::Records(record_ids.try_into().expect("count is MaxRequestedRecords at most"))
+}
+
+// Have `requester` ask the patient for `requested_records(count)`.
+fn request<T: Config>(patient_id: &T::AccountId, requester: &T::AccountId, count: u32) {
+	MedicalRecord::<T>::request_access(
+		RawOrigin::Signed(requester.clone()).into(),
+		patient_id.clone(),
+		requested_records::<T>(count),
+		purpose::<T>(),
+		10u32.into(),
+	)
+	.expect("the requester may receive the records");
+}
 
 benchmarks! {
-	do_something {
-		let s in 0 .. 100;
+	create_account {
 		let caller: T::AccountId = whitelisted_caller();
-	}: _(RawOrigin::Signed(caller), s)
+	}: _(RawOrigin::Signed(caller.clone()), UserType::Patient)
+	verify {
+		assert!(MedicalRecord::<T>::has_role(&caller, UserType::Patient));
+	}
+
+	add_role {
+		let caller: T::AccountId = whitelisted_caller();
+		register::<T>(&caller, UserType::Doctor);
+	}: _(RawOrigin::Signed(caller.clone()), UserType::Patient)
+	verify {
+		assert!(MedicalRecord::<T>::has_role(&caller, UserType::Patient));
+	}
+
+	remove_role {
+		let caller: T::AccountId = whitelisted_caller();
+		register::<T>(&caller, UserType::Doctor);
+		MedicalRecord::<T>::grant_role(&caller, UserType::Patient)?;
+	}: _(RawOrigin::Signed(caller.clone()), UserType::Patient)
+	verify {
+		assert!(!MedicalRecord::<T>::has_role(&caller, UserType::Patient));
+	}
+
+	set_profile {
+		let p in 0 .. T::MaxProfileLength::get();
+		let caller: T::AccountId = whitelisted_caller();
+		register::<T>(&caller, UserType::Patient);
+		let profile: BoundedVec<u8, T::MaxProfileLength> =
+			vec![0; p as usize].try_into().expect("p is at most MaxProfileLength");
+	}: _(RawOrigin::Signed(caller.clone()), profile.clone())
+	verify {
+		let account = MedicalRecord::<T>::account(&caller).expect("the caller is registered");
+		assert_eq!(account.profile, profile);
+	}
+
+	apply_for_doctor_license {
+		let caller: T::AccountId = whitelisted_caller();
+		register::<T>(&caller, UserType::Patient);
+	}: _(RawOrigin::Signed(caller.clone()))
+	verify {
+		assert!(DoctorApplications::<T>::contains_key(&caller));
+	}
+
+	approve_doctor_license {
+		let applicant: T::AccountId = account("applicant", 0, SEED);
+		// The doctor role is added to an existing account.
+		register::<T>(&applicant, UserType::Patient);
+		MedicalRecord::<T>::apply_for_doctor_license(RawOrigin::Signed(applicant.clone()).into())?;
+		let origin = T::LicensingOrigin::successful_origin();
+	}: _<T::RuntimeOrigin>(origin, applicant.clone())
+	verify {
+		assert!(MedicalRecord::<T>::has_role(&applicant, UserType::Doctor));
+	}
+
+	reject_doctor_license {
+		let applicant: T::AccountId = account("applicant", 0, SEED);
+		MedicalRecord::<T>::apply_for_doctor_license(RawOrigin::Signed(applicant.clone()).into())?;
+		let origin = T::LicensingOrigin::successful_origin();
+	}: _<T::RuntimeOrigin>(origin, applicant.clone())
+	verify {
+		assert!(!DoctorApplications::<T>::contains_key(&applicant));
+	}
+
+	grant_license {
+		let nurse: T::AccountId = account("nurse", 0, SEED);
+		register::<T>(&nurse, UserType::Patient);
+		let origin = T::LicensingOrigin::successful_origin();
+	}: _<T::RuntimeOrigin>(origin, nurse.clone(), UserType::Nurse)
+	verify {
+		assert!(MedicalRecord::<T>::has_role(&nurse, UserType::Nurse));
+	}
+
+	revoke_license {
+		let nurse: T::AccountId = account("nurse", 0, SEED);
+		register::<T>(&nurse, UserType::Patient);
+		MedicalRecord::<T>::grant_role(&nurse, UserType::Nurse)?;
+		let origin = T::LicensingOrigin::successful_origin();
+	}: _<T::RuntimeOrigin>(origin, nurse.clone(), UserType::Nurse)
+	verify {
+		assert!(!MedicalRecord::<T>::has_role(&nurse, UserType::Nurse));
+	}
+
+	set_permissions {
+		let mut categories = CategorySet::new();
+		for category in [
+			RecordCategory::Note,
+			RecordCategory::Diagnosis,
+			RecordCategory::Prescription,
+			RecordCategory::Lab,
+			RecordCategory::Imaging,
+			RecordCategory::Immunization,
+			RecordCategory::Allergy,
+		] {
+			categories.try_insert(category).expect("there is room for every category");
+		}
+		let origin = T::PermissionsOrigin::successful_origin();
+	}: _<T::RuntimeOrigin>(origin, UserType::Nurse, RecordAction::Verify, categories)
+	verify {
+		let categories = MedicalRecord::<T>::permissions(UserType::Nurse, RecordAction::Verify);
+		assert!(categories.contains(&RecordCategory::Allergy));
+	}
+
+	set_endorsement_threshold {
+		let origin = T::PermissionsOrigin::successful_origin();
+	}: _<T::RuntimeOrigin>(origin, RecordCategory::Note, T::MaxEndorsements::get())
+	verify {
+		assert_eq!(
+			MedicalRecord::<T>::endorsement_threshold(RecordCategory::Note),
+			T::MaxEndorsements::get()
+		);
+	}
+
+	patient_adds_record {
+		let c in 0 .. T::MaxRecordContentLength::get();
+		let r in 0 .. T::MaxRecordLength::get() - 1;
+		let patient: T::AccountId = whitelisted_caller();
+		register::<T>(&patient, UserType::Patient);
+		add_records::<T>(&patient, r);
+	}: _(
+		RawOrigin::Signed(patient.clone()),
+		RecordCategory::Note,
+		record_title::<T>(),
+		record_content::<T>(c)
+	)
+	verify {
+		assert!(MedicalRecord::<T>::record(&patient, r + 1).is_some());
+	}
+
+	doctor_adds_record {
+		let c in 0 .. T::MaxRecordContentLength::get();
+		let r in 0 .. T::MaxRecordLength::get() - 1;
+		let patient: T::AccountId = account("patient", 0, SEED);
+		register::<T>(&patient, UserType::Patient);
+		add_records::<T>(&patient, r);
+		let doctor = T::BenchmarkHelper::signer();
+		register::<T>(&doctor, UserType::Doctor);
+		fill_audit_log::<T>(&patient);
+
+		let record_content = record_content::<T>(c);
+		let payload = MedicalRecord::<T>::record_signing_payload(
+			&patient,
+			r + 1,
+			1,
+			&Content::OnChain(record_content.clone()),
+		);
+		let signature = T::BenchmarkHelper::sign(&doctor, payload.as_ref());
+	}: _(
+		RawOrigin::Signed(doctor),
+		patient.clone(),
+		RecordCategory::Note,
+		record_title::<T>(),
+		record_content,
+		signature
+	)
+	verify {
+		assert!(MedicalRecord::<T>::record(&patient, r + 1).map_or(false, |r| r.is_verified()));
+	}
+
+	patient_adds_offchain_record {
+		let p in 0 .. T::MaxPayloadLength::get();
+		let r in 0 .. T::MaxRecordLength::get() - 1;
+		let patient: T::AccountId = whitelisted_caller();
+		register::<T>(&patient, UserType::Patient);
+		add_records::<T>(&patient, r);
+	}: _(
+		RawOrigin::Signed(patient.clone()),
+		RecordCategory::Note,
+		record_title::<T>(),
+		payload::<T>(p),
+		media_type::<T>()
+	)
+	verify {
+		assert!(MedicalRecord::<T>::record(&patient, r + 1).is_some());
+	}
+
+	doctor_adds_offchain_record {
+		let p in 0 .. T::MaxPayloadLength::get();
+		let r in 0 .. T::MaxRecordLength::get() - 1;
+		let patient: T::AccountId = account("patient", 0, SEED);
+		register::<T>(&patient, UserType::Patient);
+		add_records::<T>(&patient, r);
+		let doctor = T::BenchmarkHelper::signer();
+		register::<T>(&doctor, UserType::Doctor);
+		fill_audit_log::<T>(&patient);
+
+		let payload = payload::<T>(p);
+		let content = Content::OffChain(PayloadAnchor {
+			hash: T::Hashing::hash(&payload),
+			size: p,
+			media_type: media_type::<T>(),
+		});
+		let signing_payload = MedicalRecord::<T>::record_signing_payload(&patient, r + 1, 1, &content);
+		let signature = T::BenchmarkHelper::sign(&doctor, signing_payload.as_ref());
+	}: _(
+		RawOrigin::Signed(doctor),
+		patient.clone(),
+		RecordCategory::Note,
+		record_title::<T>(),
+		payload,
+		media_type::<T>(),
+		signature
+	)
+	verify {
+		assert!(MedicalRecord::<T>::record(&patient, r + 1).map_or(false, |r| r.is_verified()));
+	}
+
+	doctor_verifies_record {
+		// The endorsements of the record once the doctor endorsed it.
+		let e in 1 .. T::MaxEndorsements::get();
+		let patient: T::AccountId = account("patient", 0, SEED);
+		register::<T>(&patient, UserType::Patient);
+		add_records::<T>(&patient, 1);
+		let doctor = T::BenchmarkHelper::signer();
+		register::<T>(&doctor, UserType::Doctor);
+		// Every other doctor is compared with the endorsing one, whose endorsement verifies
+		// the record.
+		add_endorsements::<T>(&patient, 1, e - 1);
+		EndorsementThresholds::<T>::insert(RecordCategory::Note, e);
+		fill_audit_log::<T>(&patient);
+
+		let payload = MedicalRecord::<T>::record_signing_payload(
+			&patient,
+			1,
+			1,
+			&Content::OnChain(Default::default()),
+		);
+		let signature = T::BenchmarkHelper::sign(&doctor, payload.as_ref());
+	}: _(RawOrigin::Signed(doctor), patient.clone(), 1, signature)
+	verify {
+		assert!(MedicalRecord::<T>::record(&patient, 1).map_or(false, |r| r.is_verified()));
+	}
+
+	amend_record {
+		let c in 0 .. T::MaxRecordContentLength::get();
+		// 1 if the history is full, so the oldest version is dropped: its deposit is returned
+		// and its payload released and cleared from the offchain database.
+		let d in 0 .. 1;
+		let patient: T::AccountId = whitelisted_caller();
+		register::<T>(&patient, UserType::Patient);
+		let versions = if d == 1 { T::MaxRecordVersions::get() } else { 1 };
+		add_offchain_versions::<T>(&patient, versions);
+	}: _(RawOrigin::Signed(patient.clone()), patient.clone(), 1, record_content::<T>(c))
+	verify {
+		assert_eq!(MedicalRecord::<T>::current_version(&patient, 1), versions + 1);
+	}
+
+	amend_offchain_record {
+		let p in 0 .. T::MaxPayloadLength::get();
+		let d in 0 .. 1;
+		let patient: T::AccountId = whitelisted_caller();
+		register::<T>(&patient, UserType::Patient);
+		let versions = if d == 1 { T::MaxRecordVersions::get() } else { 1 };
+		add_offchain_versions::<T>(&patient, versions);
+	}: _(
+		RawOrigin::Signed(patient.clone()),
+		patient.clone(),
+		1,
+		payload::<T>(p),
+		media_type::<T>()
+	)
+	verify {
+		assert_eq!(MedicalRecord::<T>::current_version(&patient, 1), versions + 1);
+	}
+
+	erase_record {
+		let s in 0 .. MAX_RECIPIENTS;
+		let patient: T::AccountId = whitelisted_caller();
+		register::<T>(&patient, UserType::Patient);
+		// Every kept version holds a payload of its own, which is released.
+		add_offchain_versions::<T>(&patient, T::MaxRecordVersions::get());
+		for i in 0..s {
+			share::<T>(&patient, &account("recipient", i, SEED), 1);
+		}
+		let key_id = rotate_emergency_key::<T>();
+		let (encrypted_key, _) = T::BenchmarkHelper::sealed_key(&patient);
+		MedicalRecord::<T>::set_emergency_record(
+			RawOrigin::Signed(patient.clone()).into(),
+			1,
+			encrypted_key,
+			key_id,
+		)?;
+		fill_audit_log::<T>(&patient);
+	}: _(RawOrigin::Signed(patient.clone()), 1, s)
+	verify {
+		assert!(MedicalRecord::<T>::record(&patient, 1).map_or(false, |r| r.is_erased()));
+	}
+
+	share_record_with {
+		let patient: T::AccountId = whitelisted_caller();
+		register::<T>(&patient, UserType::Patient);
+		add_records::<T>(&patient, 1);
+		let recipient: T::AccountId = account("recipient", 0, SEED);
+		register::<T>(&recipient, UserType::Doctor);
+		let (encrypted_key, key_id) = T::BenchmarkHelper::sealed_key(&recipient);
+		fill_audit_log::<T>(&patient);
+	}: _(RawOrigin::Signed(patient), recipient, encrypted_key, key_id, 1, None)
+
+	revoke_share_with {
+		let patient: T::AccountId = whitelisted_caller();
+		register::<T>(&patient, UserType::Patient);
+		add_records::<T>(&patient, 1);
+		let recipient: T::AccountId = account("recipient", 0, SEED);
+		share::<T>(&patient, &recipient, 1);
+		fill_audit_log::<T>(&patient);
+	}: _(RawOrigin::Signed(patient), recipient, 1)
+
+	set_emergency_key {
+		let origin = T::LicensingOrigin::successful_origin();
+	}: _<T::RuntimeOrigin>(origin, [0; 32])
+	verify {
+		assert!(MedicalRecord::<T>::emergency_key().is_some());
+	}
+
+	set_emergency_record {
+		let patient: T::AccountId = whitelisted_caller();
+		register::<T>(&patient, UserType::Patient);
+		add_records::<T>(&patient, 1);
+		let key_id = rotate_emergency_key::<T>();
+		let (encrypted_key, _) = T::BenchmarkHelper::sealed_key(&patient);
+		fill_audit_log::<T>(&patient);
+	}: _(RawOrigin::Signed(patient.clone()), 1, encrypted_key, key_id)
+	verify {
+		assert!(EmergencyProfile::<T>::contains_key(&patient, 1));
+	}
+
+	clear_emergency_record {
+		let patient: T::AccountId = whitelisted_caller();
+		register::<T>(&patient, UserType::Patient);
+		add_records::<T>(&patient, 1);
+		let key_id = rotate_emergency_key::<T>();
+		let (encrypted_key, _) = T::BenchmarkHelper::sealed_key(&patient);
+		MedicalRecord::<T>::set_emergency_record(
+			RawOrigin::Signed(patient.clone()).into(),
+			1,
+			encrypted_key,
+			key_id,
+		)?;
+		fill_audit_log::<T>(&patient);
+	}: _(RawOrigin::Signed(patient.clone()), 1)
+	verify {
+		assert!(!EmergencyProfile::<T>::contains_key(&patient, 1));
+	}
+
+	break_glass {
+		let r in 1 .. T::MaxRecordLength::get();
+		let patient: T::AccountId = account("patient", 0, SEED);
+		register::<T>(&patient, UserType::Patient);
+		add_records::<T>(&patient, r);
+		// Only the last record is wrapped for the current key, so the others are looked at
+		// first.
+		let (encrypted_key, _) = T::BenchmarkHelper::sealed_key(&patient);
+		let stale_key_id = rotate_emergency_key::<T>();
+		for record_id in 1..r {
+			MedicalRecord::<T>::set_emergency_record(
+				RawOrigin::Signed(patient.clone()).into(),
+				record_id,
+				encrypted_key.clone(),
+				stale_key_id,
+			)?;
+		}
+		let key_id = rotate_emergency_key::<T>();
+		MedicalRecord::<T>::set_emergency_record(
+			RawOrigin::Signed(patient.clone()).into(),
+			r,
+			encrypted_key,
+			key_id,
+		)?;
+		let responder: T::AccountId = account("responder", 0, SEED);
+		register::<T>(&responder, UserType::EmergencyResponder);
+		fill_audit_log::<T>(&patient);
+	}: _(RawOrigin::Signed(responder), patient.clone(), purpose::<T>())
+	verify {
+		let log = MedicalRecord::<T>::audit_log(&patient);
+		assert_eq!(log.last().map(|entry| entry.path), Some(AccessPath::Emergency));
+	}
+
+	appoint_guardian {
+		let patient: T::AccountId = whitelisted_caller();
+		register::<T>(&patient, UserType::Patient);
+		let guardian: T::AccountId = account("guardian", 0, SEED);
+		register::<T>(&guardian, UserType::Doctor);
+		let scope = GuardianScope { add_records: true, share: true, revoke_shares: true };
+	}: _(RawOrigin::Signed(patient.clone()), guardian.clone(), scope, None)
+	verify {
+		assert!(Guardians::<T>::contains_key(&patient, &guardian));
+	}
+
+	appoint_guardian_for {
+		let patient: T::AccountId = account("patient", 0, SEED);
+		register::<T>(&patient, UserType::Patient);
+		let guardian: T::AccountId = account("guardian", 0, SEED);
+		register::<T>(&guardian, UserType::Doctor);
+		let scope = GuardianScope { add_records: true, share: true, revoke_shares: true };
+		let origin = T::LicensingOrigin::successful_origin();
+	}: _<T::RuntimeOrigin>(origin, patient.clone(), guardian.clone(), scope, None)
+	verify {
+		assert!(Guardians::<T>::contains_key(&patient, &guardian));
+	}
+
+	revoke_guardian {
+		let patient: T::AccountId = whitelisted_caller();
+		register::<T>(&patient, UserType::Patient);
+		let guardian: T::AccountId = account("guardian", 0, SEED);
+		appoint::<T>(&patient, &guardian);
+	}: _(RawOrigin::Signed(patient.clone()), patient.clone(), guardian.clone())
+	verify {
+		assert!(!Guardians::<T>::contains_key(&patient, &guardian));
+	}
+
+	revoke_guardian_for {
+		let patient: T::AccountId = account("patient", 0, SEED);
+		register::<T>(&patient, UserType::Patient);
+		let guardian: T::AccountId = account("guardian", 0, SEED);
+		appoint::<T>(&patient, &guardian);
+		let origin = T::LicensingOrigin::successful_origin();
+	}: _<T::RuntimeOrigin>(origin, patient.clone(), guardian.clone())
+	verify {
+		assert!(!Guardians::<T>::contains_key(&patient, &guardian));
+	}
+
+	guardian_adds_record {
+		let c in 0 .. T::MaxRecordContentLength::get();
+		let r in 0 .. T::MaxRecordLength::get() - 1;
+		let patient: T::AccountId = account("patient", 0, SEED);
+		register::<T>(&patient, UserType::Patient);
+		add_records::<T>(&patient, r);
+		let guardian: T::AccountId = whitelisted_caller();
+		appoint::<T>(&patient, &guardian);
+	}: _(
+		RawOrigin::Signed(guardian),
+		patient.clone(),
+		RecordCategory::Note,
+		record_title::<T>(),
+		record_content::<T>(c)
+	)
+	verify {
+		assert!(MedicalRecord::<T>::record(&patient, r + 1).is_some());
+	}
+
+	guardian_shares_record {
+		let patient: T::AccountId = account("patient", 0, SEED);
+		register::<T>(&patient, UserType::Patient);
+		add_records::<T>(&patient, 1);
+		let guardian: T::AccountId = whitelisted_caller();
+		appoint::<T>(&patient, &guardian);
+		let recipient: T::AccountId = account("recipient", 0, SEED);
+		register::<T>(&recipient, UserType::Doctor);
+		let (encrypted_key, key_id) = T::BenchmarkHelper::sealed_key(&recipient);
+		fill_audit_log::<T>(&patient);
+	}: _(RawOrigin::Signed(guardian), patient, recipient, encrypted_key, key_id, 1, None)
+
+	guardian_revokes_share {
+		let patient: T::AccountId = account("patient", 0, SEED);
+		register::<T>(&patient, UserType::Patient);
+		add_records::<T>(&patient, 1);
+		let guardian: T::AccountId = whitelisted_caller();
+		appoint::<T>(&patient, &guardian);
+		let recipient: T::AccountId = account("recipient", 0, SEED);
+		share::<T>(&patient, &recipient, 1);
+		fill_audit_log::<T>(&patient);
+	}: _(RawOrigin::Signed(guardian), patient, recipient, 1)
+
+	request_access {
+		// The records requested; none for a category.
+		let k in 0 .. T::MaxRequestedRecords::get();
+		let patient: T::AccountId = account("patient", 0, SEED);
+		register::<T>(&patient, UserType::Patient);
+		add_records::<T>(&patient, k);
+		let requester: T::AccountId = whitelisted_caller();
+		register::<T>(&requester, UserType::Doctor);
+		// Every block before the last one the request may expire at is full.
+		let now = frame_system::Pallet::<T>::block_number();
+		let expires_at = now + T::AccessRequestLifetime::get();
+		let full = vec![(patient.clone(), 0); T::MaxRequestExpiriesPerBlock::get() as usize];
+		for delay in 0..MAX_REQUEST_EXPIRY_DELAY {
+			RequestExpiries::<T>::insert(
+				expires_at + delay.into(),
+				BoundedVec::<_, T::MaxRequestExpiriesPerBlock>::try_from(full.clone())
+					.expect("the queue is full, not over"),
+			);
+		}
+		let records = requested_records::<T>(k);
+	}: _(RawOrigin::Signed(requester), patient.clone(), records, purpose::<T>(), 10u32.into())
+	verify {
+		assert!(AccessRequests::<T>::contains_key(&patient, 1));
+	}
+
+	approve_access_request {
+		// The records shared.
+		let k in 1 .. T::MaxRequestedRecords::get();
+		let patient: T::AccountId = whitelisted_caller();
+		register::<T>(&patient, UserType::Patient);
+		add_records::<T>(&patient, k);
+		let requester: T::AccountId = account("requester", 0, SEED);
+		register::<T>(&requester, UserType::Doctor);
+		request::<T>(&patient, &requester, k);
+		let (encrypted_key, key_id) = T::BenchmarkHelper::sealed_key(&requester);
+		let keys: Vec<_> = (1..=k).map(|record_id| (record_id, encrypted_key.clone())).collect();
+		let keys: BoundedVec<_, T::MaxRequestedRecords> =
+			keys.try_into().expect("k is MaxRequestedRecords at most");
+		fill_audit_log::<T>(&patient);
+	}: _(RawOrigin::Signed(patient.clone()), 1, keys, key_id)
+	verify {
+		assert!(!AccessRequests::<T>::contains_key(&patient, 1));
+	}
+
+	deny_access_request {
+		let patient: T::AccountId = whitelisted_caller();
+		register::<T>(&patient, UserType::Patient);
+		add_records::<T>(&patient, 1);
+		let requester: T::AccountId = account("requester", 0, SEED);
+		register::<T>(&requester, UserType::Doctor);
+		request::<T>(&patient, &requester, 1);
+	}: _(RawOrigin::Signed(patient.clone()), 1)
+	verify {
+		assert!(!AccessRequests::<T>::contains_key(&patient, 1));
+	}
+
+	author_adds_record {
+		let c in 0 .. T::MaxRecordContentLength::get();
+		let r in 0 .. T::MaxRecordLength::get() - 1;
+		let patient: T::AccountId = account("patient", 0, SEED);
+		register::<T>(&patient, UserType::Patient);
+		add_records::<T>(&patient, r);
+		let nurse: T::AccountId = whitelisted_caller();
+		register::<T>(&nurse, UserType::Nurse);
+	}: _(
+		RawOrigin::Signed(nurse),
+		patient.clone(),
+		RecordCategory::Note,
+		record_title::<T>(),
+		record_content::<T>(c)
+	)
 	verify {
-		assert_eq!(Something::<T>::get(), Some(s));
+		assert!(MedicalRecord::<T>::record(&patient, r + 1).is_some());
 	}
 
-	impl_benchmark_test_suite!(Template, crate::mock::new_test_ext(), crate::mock::Test);
+	impl_benchmark_test_suite!(
+		MedicalRecord,
+		crate::mock::ExternalitiesBuilder::default().build(),
+		crate::mock::Test
+	);
 }
@@ -2,6 +2,16 @@
 
 pub use pallet::*;
 
+use sp_std::vec::Vec;
+
+/// Prefix of the offchain database keys record payloads are indexed under.
+pub const OFFCHAIN_PAYLOAD_PREFIX: &[u8] = b"medical_record::payload::";
+
+/// The offchain database key of the record payload with the given hash.
+pub fn offchain_payload_key(payload_hash: &[u8]) -> Vec<u8> {
+	[OFFCHAIN_PAYLOAD_PREFIX, payload_hash].concat()
+}
+
 #[cfg(test)]
 mod mock;
 
@@ -11,98 +21,603 @@ mod tests;
 #[cfg(feature = "runtime-benchmarks")]
 mod benchmarking;
 
+pub mod migrations;
+pub mod weights;
+pub use weights::WeightInfo;
+
 #[frame_support::pallet]
 pub mod pallet {
-	use frame_support::pallet_prelude::*;
+	use crate::WeightInfo;
+	use frame_support::{
+		pallet_prelude::*,
+		traits::{Currency, ReservableCurrency},
+		BoundedBTreeSet,
+	};
 	use frame_system::pallet_prelude::*;
-	use pallet_record_sharing::EncryptedKey;
+	use pallet_medical_record_runtime_api as api;
+	use pallet_record_sharing::{
+		AccessPath, AuditAction, EncryptionPublicKey, KeyId, RecordProvider, SharingManager,
+		ValidityWindow,
+	};
 	use scale_info::TypeInfo;
 	use serde::{Deserialize, Serialize};
+	use sp_runtime::traits::{
+		Hash, IdentifyAccount, One, Saturating, UniqueSaturatedInto, Verify, Zero,
+	};
+	use sp_std::{collections::btree_set::BTreeSet, vec::Vec};
+
+	/// The current storage version.
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
+	/// How many blocks past its lifetime an access request may expire at, when the expiry
+	/// queues of the blocks before are full.
+	pub const MAX_REQUEST_EXPIRY_DELAY: u32 = 16;
 
 	#[pallet::pallet]
 	#[pallet::generate_store(pub(super) trait Store)]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	#[pallet::config]
-	pub trait Config: frame_system::Config + pallet_record_sharing::Config {
+	pub trait Config: frame_system::Config + pallet_timestamp::Config {
 		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
 		type MaxRecordContentLength: Get<u32>;
-		type SignatureLength: Get<u32>;
+		/// Signature a doctor produces over a record's signing payload.
+		type Signature: Verify<Signer = Self::Signer> + Parameter + MaxEncodedLen;
+		/// Public key of a doctor, identified by their `AccountId`.
+		type Signer: IdentifyAccount<AccountId = Self::AccountId>;
 		type MaxRecordLength: Get<u32>;
+		/// Origin allowed to approve or reject doctor license applications, e.g. a medical board.
+		type LicensingOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+		/// Origin allowed to change which roles may add, verify or receive records, e.g.
+		/// governance.
+		type PermissionsOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+		/// The pallet that stores which records are shared with whom.
+		type RecordSharing: SharingManager<Self::AccountId, Self::BlockNumber>;
+		/// Maximum size in bytes of a payload kept in the offchain database.
+		type MaxPayloadLength: Get<u32>;
+		type MaxMediaTypeLength: Get<u32>;
+		/// How many versions of a record are kept, including the current one.
+		type MaxRecordVersions: Get<u32>;
+		/// How many doctors can endorse one version of a record.
+		#[pallet::constant]
+		type MaxEndorsements: Get<u32>;
+		/// Maximum size in bytes of an account's profile.
+		type MaxProfileLength: Get<u32>;
+		/// Maximum size in bytes of a record's encrypted title.
+		type MaxTitleLength: Get<u32>;
+		/// Maximum size in bytes of the purpose given for an access, e.g. a responder's reason
+		/// for breaking the glass.
+		type MaxPurposeLength: Get<u32>;
+		/// How many audit log entries are kept per patient. The oldest are dropped first.
+		type MaxAuditEntries: Get<u32>;
+		/// How many records a single access request can ask for.
+		type MaxRequestedRecords: Get<u32>;
+		/// How many blocks a patient has to answer an access request before it expires.
+		#[pallet::constant]
+		type AccessRequestLifetime: Get<Self::BlockNumber>;
+		/// How many access requests can expire in the same block.
+		type MaxRequestExpiriesPerBlock: Get<u32>;
+		/// Deposit reserved from the requester while an access request is open.
+		#[pallet::constant]
+		type AccessRequestDeposit: Get<BalanceOf<Self>>;
+		/// How many open access requests an account can have with the same patient.
+		#[pallet::constant]
+		type MaxOpenRequests: Get<u32>;
+		/// The currency record deposits are reserved in.
+		type Currency: ReservableCurrency<Self::AccountId>;
+		/// Deposit reserved for each byte a record or one of its versions takes up. The author of
+		/// the current version pays for the record, and each version's author for the version.
+		#[pallet::constant]
+		type DepositPerByte: Get<BalanceOf<Self>>;
+		/// Weight information for extrinsics in this pallet.
+		type WeightInfo: WeightInfo;
+		#[cfg(feature = "runtime-benchmarks")]
+		type BenchmarkHelper: BenchmarkHelper<Self>;
+	}
+
+	/// Creates what the benchmarks cannot create from the pallet's types alone.
+	#[cfg(feature = "runtime-benchmarks")]
+	pub trait BenchmarkHelper<T: Config> {
+		/// An account `sign` can sign for.
+		fn signer() -> T::AccountId;
+		fn sign(signer: &T::AccountId, message: &[u8]) -> T::Signature;
+		/// Make `recipient` ready to be shared with and return a record key sealed for it,
+		/// together with the id of the recipient's key it was sealed for.
+		fn sealed_key(recipient: &T::AccountId) -> (EncryptedKey<T>, KeyId);
 	}
 
 	#[derive(
-		Decode, Encode, Deserialize, Serialize, MaxEncodedLen, Clone, PartialEq, Eq, Debug, TypeInfo,
+		Decode,
+		Encode,
+		Deserialize,
+		Serialize,
+		MaxEncodedLen,
+		Clone,
+		PartialEq,
+		Eq,
+		PartialOrd,
+		Ord,
+		Debug,
+		TypeInfo,
 	)]
 	pub enum UserType {
 		Patient,
 		Doctor,
+		Nurse,
+		Pharmacist,
+		Laboratory,
+		Insurer,
+		Researcher,
+		/// ER staff who may invoke break-glass access to emergency profiles.
+		EmergencyResponder,
+	}
+
+	/// What a record is about. The permissions matrix grants actions per category.
+	#[derive(
+		Decode,
+		Encode,
+		Deserialize,
+		Serialize,
+		MaxEncodedLen,
+		Clone,
+		Copy,
+		PartialEq,
+		Eq,
+		PartialOrd,
+		Ord,
+		Debug,
+		TypeInfo,
+	)]
+	pub enum RecordCategory {
+		/// Anything else, e.g. a visit summary. Records added before categories existed are
+		/// notes.
+		Note,
+		Diagnosis,
+		Prescription,
+		Lab,
+		Imaging,
+		Immunization,
+		Allergy,
+	}
+
+	/// What a role may do with a category of records.
+	#[derive(
+		Decode,
+		Encode,
+		Deserialize,
+		Serialize,
+		MaxEncodedLen,
+		Clone,
+		Copy,
+		PartialEq,
+		Eq,
+		Debug,
+		TypeInfo,
+	)]
+	pub enum RecordAction {
+		/// Add records for patients or amend them.
+		Add,
+		/// Verify records by signing them.
+		Verify,
+		/// Have records shared with them.
+		Receive,
+	}
+
+	/// The permissions a new chain starts with: doctors may do anything, the other clinical
+	/// roles what their work needs, and patients may receive every record.
+	pub fn default_permissions() -> Vec<(UserType, RecordAction, Vec<RecordCategory>)> {
+		use RecordAction::*;
+		use RecordCategory::*;
+		let all =
+			|| sp_std::vec![Note, Diagnosis, Prescription, Lab, Imaging, Immunization, Allergy];
+		sp_std::vec![
+			(UserType::Patient, Receive, all()),
+			(UserType::Doctor, Add, all()),
+			(UserType::Doctor, Verify, all()),
+			(UserType::Doctor, Receive, all()),
+			(UserType::Nurse, Add, sp_std::vec![Note, Immunization]),
+			(UserType::Nurse, Receive, sp_std::vec![Note, Prescription, Immunization, Allergy]),
+			(UserType::Pharmacist, Receive, sp_std::vec![Prescription, Allergy]),
+			(UserType::Laboratory, Add, sp_std::vec![Lab, Imaging]),
+			(UserType::Laboratory, Verify, sp_std::vec![Lab, Imaging]),
+			(UserType::Laboratory, Receive, sp_std::vec![Lab, Imaging]),
+			(UserType::Insurer, Receive, sp_std::vec![Diagnosis, Prescription]),
+		]
 	}
 
-	type RecordId = u32;
+	pub type RecordId = u32;
+	pub type RequestId = u32;
+	type VersionNumber = u32;
 	type RecordContent<T> = BoundedVec<u8, <T as Config>::MaxRecordContentLength>;
-	type Signature<T> = BoundedVec<u8, <T as Config>::SignatureLength>;
+	type MediaType<T> = BoundedVec<u8, <T as Config>::MaxMediaTypeLength>;
+	type Payload<T> = BoundedVec<u8, <T as Config>::MaxPayloadLength>;
+	type Profile<T> = BoundedVec<u8, <T as Config>::MaxProfileLength>;
+	type Purpose<T> = BoundedVec<u8, <T as Config>::MaxPurposeLength>;
+	type Title<T> = BoundedVec<u8, <T as Config>::MaxTitleLength>;
+	type Endorsements<T> = BoundedVec<Endorsement<T>, <T as Config>::MaxEndorsements>;
+	type AuditInfo<T> = api::AuditEntry<
+		<T as frame_system::Config>::AccountId,
+		<T as frame_system::Config>::BlockNumber,
+	>;
+	/// The roles an account holds, with room for every `UserType`.
+	pub type RoleSet = BoundedBTreeSet<UserType, ConstU32<8>>;
+	/// Record categories, with room for every `RecordCategory`.
+	pub type CategorySet = BoundedBTreeSet<RecordCategory, ConstU32<16>>;
+	type Signature<T> = <T as Config>::Signature;
 	type PatientAccountId<T> = <T as frame_system::Config>::AccountId;
+	pub type BalanceOf<T> =
+		<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
 	type DoctorAccountId<T> = <T as frame_system::Config>::AccountId;
+	type RecordInfo<T> = api::RecordInfo<
+		<T as frame_system::Config>::AccountId,
+		<T as frame_system::Config>::Hash,
+		<T as frame_system::Config>::BlockNumber,
+	>;
+	type EncryptedKey<T> = <<T as Config>::RecordSharing as SharingManager<
+		<T as frame_system::Config>::AccountId,
+		<T as frame_system::Config>::BlockNumber,
+	>>::EncryptedKey;
 
+	/// An account: the roles it holds, its profile and the block it was created at.
 	#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, MaxEncodedLen, TypeInfo)]
 	#[scale_info(skip_type_params(T))]
-	pub enum Record<T: Config> {
-		VerifiedRecord(
-			RecordId,
-			PatientAccountId<T>,
-			DoctorAccountId<T>,
-			RecordContent<T>,
-			Signature<T>,
-		),
-		UnverifiedRecord(RecordId, PatientAccountId<T>, RecordContent<T>),
+	pub struct Account<T: Config> {
+		pub roles: RoleSet,
+		/// Opaque to the pallet, e.g. an encrypted name or the hash of a document kept off chain.
+		pub profile: Profile<T>,
+		pub created_at: T::BlockNumber,
 	}
 
-	impl<T: Config> Record<T> {
-		pub fn transform_unverified_record(
-			record: Record<T>,
-			doctor_id: DoctorAccountId<T>,
-			signature: Signature<T>,
-		) -> Record<T> {
-			match record {
-				Record::VerifiedRecord(_, _, _, _, _) => record,
-				Record::UnverifiedRecord(record_id, patient_id, record_content) =>
-					Record::VerifiedRecord(
-						record_id,
-						patient_id,
-						doctor_id,
-						record_content,
-						signature,
-					),
+	/// What a guardian may do on a patient's behalf.
+	#[derive(
+		Decode, Encode, Clone, Copy, Debug, Default, Eq, PartialEq, MaxEncodedLen, TypeInfo,
+	)]
+	pub struct GuardianScope {
+		pub add_records: bool,
+		pub share: bool,
+		pub revoke_shares: bool,
+	}
+
+	/// A guardian's mandate for a patient, e.g. a parent's for a minor.
+	#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, MaxEncodedLen, TypeInfo)]
+	#[scale_info(skip_type_params(T))]
+	pub struct Guardianship<T: Config> {
+		pub scope: GuardianScope,
+		/// The first block the guardian can no longer act in. `None` if it never expires.
+		pub expires_at: Option<T::BlockNumber>,
+		pub appointed_at: T::BlockNumber,
+	}
+
+	/// The records an access request asks for.
+	#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, MaxEncodedLen, TypeInfo)]
+	#[scale_info(skip_type_params(T))]
+	pub enum RequestedRecords<T: Config> {
+		Records(BoundedVec<RecordId, T::MaxRequestedRecords>),
+		/// Every record of the category the patient chooses to share.
+		Category(RecordCategory),
+	}
+
+	impl<T: Config> RequestedRecords<T> {
+		/// How many records the request names; none if it asks for a category.
+		pub fn record_count(&self) -> u32 {
+			match self {
+				RequestedRecords::Records(record_ids) => record_ids.len() as u32,
+				RequestedRecords::Category(_) => 0,
 			}
 		}
+	}
+
+	/// A request to have records of a patient shared, waiting for the patient to answer.
+	#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, MaxEncodedLen, TypeInfo)]
+	#[scale_info(skip_type_params(T))]
+	pub struct AccessRequest<T: Config> {
+		pub requester: T::AccountId,
+		pub records: RequestedRecords<T>,
+		pub reason: Purpose<T>,
+		/// How many blocks the sharings last once the patient approves.
+		pub duration: T::BlockNumber,
+		pub requested_at: T::BlockNumber,
+		/// The block the request is removed at if the patient has not answered it.
+		pub expires_at: T::BlockNumber,
+		/// Reserved from the requester until the request is answered or expires.
+		pub deposit: BalanceOf<T>,
+	}
+
+	/// An entry of a patient's audit log: who did what to which record, how and why.
+	#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, MaxEncodedLen, TypeInfo)]
+	#[scale_info(skip_type_params(T))]
+	pub struct AuditEntry<T: Config> {
+		pub actor: T::AccountId,
+		pub action: AuditAction<T::AccountId>,
+		/// `None` if the action is not about a single record, e.g. breaking the glass.
+		pub record_id: Option<RecordId>,
+		pub path: AccessPath,
+		pub purpose: Purpose<T>,
+		pub at: T::BlockNumber,
+	}
+
+	/// A payload kept in the offchain database of indexing nodes, anchored by its hash.
+	#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, MaxEncodedLen, TypeInfo)]
+	#[scale_info(skip_type_params(T))]
+	pub struct PayloadAnchor<T: Config> {
+		pub hash: T::Hash,
+		pub size: u32,
+		/// E.g. `application/pdf`.
+		pub media_type: MediaType<T>,
+	}
+
+	#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, MaxEncodedLen, TypeInfo)]
+	#[scale_info(skip_type_params(T))]
+	pub enum Content<T: Config> {
+		OnChain(RecordContent<T>),
+		OffChain(PayloadAnchor<T>),
+	}
+
+	/// A doctor's signature over a version of a record, and the block it was given in.
+	#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, MaxEncodedLen, TypeInfo)]
+	#[scale_info(skip_type_params(T))]
+	pub struct Endorsement<T: Config> {
+		pub doctor: DoctorAccountId<T>,
+		pub signature: Signature<T>,
+		pub at: T::BlockNumber,
+		/// Reserved from the doctor until the version is dropped or the record erased.
+		pub deposit: BalanceOf<T>,
+	}
+
+	#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, MaxEncodedLen, TypeInfo)]
+	#[scale_info(skip_type_params(T))]
+	pub enum RecordStatus<T: Config> {
+		/// Endorsed by fewer doctors than the record's category asks for.
+		Unverified(Content<T>, Endorsements<T>),
+		Verified(Content<T>, Endorsements<T>),
+		/// What is left of a record after the patient erased it: the block it was erased at.
+		Erased(T::BlockNumber),
+	}
+
+	#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, MaxEncodedLen, TypeInfo)]
+	#[scale_info(skip_type_params(T))]
+	pub struct Record<T: Config> {
+		pub id: RecordId,
+		pub patient: PatientAccountId<T>,
+		/// Who added the record: the patient, one of their guardians or a licensed author.
+		pub author: T::AccountId,
+		pub category: RecordCategory,
+		/// Encrypted with the record key, like the content.
+		pub title: Option<Title<T>>,
+		pub created_at: T::BlockNumber,
+		/// The time of the block the record was created in.
+		pub created_on: T::Moment,
+		pub status: RecordStatus<T>,
+	}
 
+	impl<T: Config> Record<T> {
 		pub fn get_id(&self) -> u32 {
-			match self {
-				Record::UnverifiedRecord(id, _, _) => *id,
-				Record::VerifiedRecord(id, _, _, _, _) => *id,
+			self.id
+		}
+
+		pub fn get_content(&self) -> Option<&Content<T>> {
+			match &self.status {
+				RecordStatus::Unverified(content, _) | RecordStatus::Verified(content, _) =>
+					Some(content),
+				RecordStatus::Erased(_) => None,
 			}
 		}
 
-		pub fn is_verified(&self) -> bool {
-			match self {
-				Record::UnverifiedRecord(_, _, _) => false,
-				Record::VerifiedRecord(_, _, _, _, _) => true,
+		/// The endorsements of the current version, oldest first.
+		pub fn endorsements(&self) -> &[Endorsement<T>] {
+			match &self.status {
+				RecordStatus::Unverified(_, endorsements) |
+				RecordStatus::Verified(_, endorsements) => endorsements,
+				RecordStatus::Erased(_) => &[],
 			}
 		}
+
+		pub fn is_verified(&self) -> bool {
+			matches!(self.status, RecordStatus::Verified(_, _))
+		}
+
+		pub fn is_erased(&self) -> bool {
+			matches!(self.status, RecordStatus::Erased(_))
+		}
 	}
 
+	/// One version of a record: who wrote it, when, and the doctors who endorsed it.
+	#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, MaxEncodedLen, TypeInfo)]
+	#[scale_info(skip_type_params(T))]
+	pub struct RecordVersion<T: Config> {
+		pub number: VersionNumber,
+		pub author: T::AccountId,
+		pub created_at: T::BlockNumber,
+		pub content: Content<T>,
+		pub endorsements: Endorsements<T>,
+		/// Reserved from the author until the version is dropped or the record erased.
+		pub deposit: BalanceOf<T>,
+	}
+
+	// Every registered account. An account can hold several roles, e.g. a doctor can also be
+	// a patient.
+	#[pallet::storage]
+	#[pallet::getter(fn account)]
+	pub type Accounts<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, Account<T>>;
+
+	// Which categories of records each role may add, verify or receive.
+	#[pallet::storage]
+	#[pallet::getter(fn permissions)]
+	pub type Permissions<T: Config> = StorageDoubleMap<
+		_,
+		Twox64Concat,
+		UserType,
+		Twox64Concat,
+		RecordAction,
+		CategorySet,
+		ValueQuery,
+	>;
+
+	#[pallet::type_value]
+	pub fn DefaultEndorsementThreshold() -> u32 {
+		1
+	}
+
+	// How many doctors have to endorse a record of each category before it counts as verified.
+	#[pallet::storage]
+	#[pallet::getter(fn endorsement_threshold)]
+	pub type EndorsementThresholds<T: Config> =
+		StorageMap<_, Twox64Concat, RecordCategory, u32, ValueQuery, DefaultEndorsementThreshold>;
+
+	// Every record, keyed by (patient, record id). Erased records stay as tombstones.
+	#[pallet::storage]
+	#[pallet::getter(fn record)]
+	pub type PatientRecords<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		PatientAccountId<T>,
+		Twox64Concat,
+		RecordId,
+		Record<T>,
+	>;
+
+	// The id of the last record added for each patient. Ids of erased records are not handed
+	// out again.
+	#[pallet::storage]
+	#[pallet::getter(fn last_record_id)]
+	pub type LastRecordId<T: Config> =
+		StorageMap<_, Blake2_128Concat, PatientAccountId<T>, RecordId, ValueQuery>;
+
+	// How many records of each patient are not erased. A patient can have at most
+	// `MaxRecordLength` of them.
+	#[pallet::storage]
+	#[pallet::getter(fn record_count)]
+	pub type RecordCount<T: Config> =
+		StorageMap<_, Blake2_128Concat, PatientAccountId<T>, u32, ValueQuery>;
+
+	// The latest `MaxRecordVersions` versions of a record, oldest first, keyed by (patient,
+	// record id). The last one is the version kept in `PatientRecords`. Records added before
+	// versions were kept only have the versions created since.
+	#[pallet::storage]
+	#[pallet::getter(fn record_versions)]
+	pub type RecordVersions<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		PatientAccountId<T>,
+		Twox64Concat,
+		RecordId,
+		BoundedVec<RecordVersion<T>, T::MaxRecordVersions>,
+		ValueQuery,
+	>;
+
+	// How many records hold each payload kept in the offchain database, keyed by the payload's
+	// hash. A record holds each distinct payload of its content and kept versions once. The
+	// payload is cleared from the offchain database when the last record holding it lets go.
+	#[pallet::storage]
+	#[pallet::getter(fn payload_references)]
+	pub type PayloadReferences<T: Config> = StorageMap<_, Identity, T::Hash, u32, ValueQuery>;
+
+	// Who reserved a deposit for a record and how much, keyed by (patient, record id).
+	// Records added before deposits were taken have none.
+	#[pallet::storage]
+	#[pallet::getter(fn record_deposit)]
+	pub type RecordDeposits<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		PatientAccountId<T>,
+		Twox64Concat,
+		RecordId,
+		(T::AccountId, BalanceOf<T>),
+	>;
+
+	// The encryption key of the emergency responder role, with its key id. Emergency profiles
+	// are wrapped for it.
+	#[pallet::storage]
+	#[pallet::getter(fn emergency_key)]
+	pub type EmergencyKey<T: Config> = StorageValue<_, (KeyId, EncryptionPublicKey)>;
+
+	// The records of each patient's emergency profile, keyed by (patient, record id), with the
+	// record key wrapped for the emergency key of the given id.
+	#[pallet::storage]
+	#[pallet::getter(fn emergency_record)]
+	pub type EmergencyProfile<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		PatientAccountId<T>,
+		Twox64Concat,
+		RecordId,
+		(EncryptedKey<T>, KeyId),
+	>;
+
+	// Who was given access to each patient's records and how, oldest first. Entries are only
+	// appended; the oldest are dropped once `MaxAuditEntries` is reached.
+	#[pallet::storage]
+	#[pallet::getter(fn audit_log)]
+	pub type AuditLog<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		PatientAccountId<T>,
+		BoundedVec<AuditEntry<T>, T::MaxAuditEntries>,
+		ValueQuery,
+	>;
+
+	// The guardians of each patient, keyed by (patient, guardian).
 	#[pallet::storage]
-	#[pallet::getter(fn records)]
-	pub type Records<T: Config> = StorageDoubleMap<
+	#[pallet::getter(fn guardianship)]
+	pub type Guardians<T: Config> = StorageDoubleMap<
 		_,
 		Blake2_128Concat,
+		PatientAccountId<T>,
+		Blake2_128Concat,
 		T::AccountId,
+		Guardianship<T>,
+	>;
+
+	// Pending access requests, keyed by (patient, request id).
+	#[pallet::storage]
+	#[pallet::getter(fn access_request)]
+	pub type AccessRequests<T: Config> = StorageDoubleMap<
+		_,
 		Blake2_128Concat,
-		UserType,
-		BoundedVec<Record<T>, T::MaxRecordLength>,
+		PatientAccountId<T>,
+		Twox64Concat,
+		RequestId,
+		AccessRequest<T>,
+	>;
+
+	// The id of the last access request made to each patient.
+	#[pallet::storage]
+	pub type LastRequestId<T: Config> =
+		StorageMap<_, Blake2_128Concat, PatientAccountId<T>, RequestId, ValueQuery>;
+
+	// How many access requests each account has open with each patient, keyed by
+	// (patient, requester).
+	#[pallet::storage]
+	#[pallet::getter(fn open_requests)]
+	pub type OpenRequests<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		PatientAccountId<T>,
+		Blake2_128Concat,
+		T::AccountId,
+		u32,
+		ValueQuery,
+	>;
+
+	// Access requests indexed by the block they expire at, as (patient, request id). Requests
+	// answered before then are skipped.
+	#[pallet::storage]
+	pub type RequestExpiries<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		T::BlockNumber,
+		BoundedVec<(PatientAccountId<T>, RequestId), T::MaxRequestExpiriesPerBlock>,
+		ValueQuery,
 	>;
 
+	// Accounts waiting for the licensing authority to approve them as doctors,
+	// with the block they applied at.
+	#[pallet::storage]
+	#[pallet::getter(fn doctor_applications)]
+	pub type DoctorApplications<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, T::BlockNumber>;
+
 	// Pallets use events to inform users when important changes are made.
 	// https://docs.substrate.io/main-docs/build/events-errors/
 	#[pallet::event]
@@ -111,7 +626,62 @@ pub mod pallet {
 		AccountCreated(T::AccountId, UserType),
 		PatientAddsRecord(PatientAccountId<T>, RecordId),
 		DoctorAddsRecordForPatient(PatientAccountId<T>, DoctorAccountId<T>, RecordId),
+		/// A record got as many endorsements as its category asks for. [patient, the doctor
+		/// who gave the last one, record]
 		DoctorVerifiesRecordForPatient(PatientAccountId<T>, DoctorAccountId<T>, RecordId),
+		DoctorLicenseApplied(T::AccountId),
+		DoctorLicenseApproved(T::AccountId),
+		DoctorLicenseRejected(T::AccountId),
+		RecordErased(PatientAccountId<T>, RecordId),
+		/// A record got a new version that awaits verification. [patient, author, record, version]
+		RecordAmended(PatientAccountId<T>, T::AccountId, RecordId, VersionNumber),
+		/// An account took up a role. [account, role]
+		RoleAdded(T::AccountId, UserType),
+		/// An account gave up a role. [account, role]
+		RoleRemoved(T::AccountId, UserType),
+		ProfileUpdated(T::AccountId),
+		/// The categories of records a role may act on changed. [role, action, categories]
+		PermissionsSet(UserType, RecordAction, CategorySet),
+		/// [category, threshold]
+		EndorsementThresholdSet(RecordCategory, u32),
+		/// A doctor endorsed the current version of a record. [patient, doctor, record,
+		/// endorsements of the version]
+		RecordEndorsed(PatientAccountId<T>, DoctorAccountId<T>, RecordId, u32),
+		/// A deposit was reserved for a new or amended record, replacing the one reserved before,
+		/// or for a new version or endorsement of a record. [depositor, patient, record, amount]
+		RecordDepositReserved(T::AccountId, PatientAccountId<T>, RecordId, BalanceOf<T>),
+		/// A deposit of a record, or of a dropped version or endorsement of it, was returned.
+		/// [depositor, patient, record, amount]
+		RecordDepositReturned(T::AccountId, PatientAccountId<T>, RecordId, BalanceOf<T>),
+		/// [patient, guardian, scope, expires_at]
+		GuardianAppointed(PatientAccountId<T>, T::AccountId, GuardianScope, Option<T::BlockNumber>),
+		/// [patient, guardian]
+		GuardianRevoked(PatientAccountId<T>, T::AccountId),
+		/// [patient, guardian, record]
+		GuardianAddsRecordForPatient(PatientAccountId<T>, T::AccountId, RecordId),
+		/// A licensed author added an unverified record for the patient. [patient, author, record]
+		AuthorAddsRecordForPatient(PatientAccountId<T>, T::AccountId, RecordId),
+		/// [patient, guardian, recipient, record]
+		GuardianSharesRecord(PatientAccountId<T>, T::AccountId, T::AccountId, RecordId),
+		/// [patient, guardian, recipient, record]
+		GuardianRevokesShare(PatientAccountId<T>, T::AccountId, T::AccountId, RecordId),
+		/// The emergency responder role got a new encryption key. [key_id]
+		EmergencyKeySet(KeyId),
+		/// [patient, record]
+		EmergencyRecordSet(PatientAccountId<T>, RecordId),
+		/// [patient, record]
+		EmergencyRecordCleared(PatientAccountId<T>, RecordId),
+		/// A responder broke the glass on a patient's emergency profile.
+		/// [patient, responder, reason]
+		EmergencyAccessed(PatientAccountId<T>, T::AccountId, Purpose<T>),
+		/// [patient, requester, request]
+		AccessRequested(PatientAccountId<T>, T::AccountId, RequestId),
+		/// The requested records were shared with the requester. [patient, requester, request]
+		AccessRequestApproved(PatientAccountId<T>, T::AccountId, RequestId),
+		/// [patient, requester, request]
+		AccessRequestDenied(PatientAccountId<T>, T::AccountId, RequestId),
+		/// The patient did not answer the request in time. [patient, requester, request]
+		AccessRequestExpired(PatientAccountId<T>, T::AccountId, RequestId),
 	}
 
 	// Errors inform users that something went wrong.
@@ -121,212 +691,1832 @@ pub mod pallet {
 		AccountAlreadyExist,
 		InvalidArgument,
 		ExceedsMaxRecordLength,
-		RecordAlreadyVerified,
+		/// The doctor already endorsed the current version of the record.
+		AlreadyEndorsed,
+		RecordAlreadyErased,
 		NonExistentRecord,
+		InvalidSignature,
+		AmendmentNotAllowed,
+		/// `MaxRecordVersions` is 0, so no version can be kept.
+		TooManyVersions,
+		DoctorLicenseRequired,
+		ApplicationAlreadyPending,
+		ApplicationNotFound,
+		RoleAlreadyHeld,
+		RoleNotHeld,
+		TooManyRoles,
+		/// Every account has to keep at least one role.
+		LastRoleCannotBeRemoved,
+		/// A patient can only give up the role once all their records are erased.
+		RecordsNotErased,
+		/// The role is only granted by the licensing authority.
+		LicenseRequired,
+		/// None of the account's roles may do this with this category of records.
+		NotPermitted,
+		NotAGuardian,
+		GuardianshipExpired,
+		/// The guardian's scope does not cover this call.
+		OutsideGuardianScope,
+		/// No emergency key is set, or the record key was wrapped for an older one.
+		StaleEmergencyKey,
+		NoEmergencyProfile,
+		AccessRequestNotFound,
+		/// Too many access requests already expire in every block a new one could expire at.
+		TooManyRequestExpiries,
+		/// The requester has `MaxOpenRequests` open access requests with the patient already.
+		TooManyOpenRequests,
+		/// The record keys do not match the records the request asks for.
+		KeysDoNotMatchRequest,
+		TooManyEndorsements,
+		/// The record is shared with more accounts than `erase_record` was given.
+		TooManySharings,
 	}
 
 	#[pallet::genesis_config]
 	pub struct GenesisConfig<T: Config> {
 		pub accounts: Vec<(T::AccountId, UserType)>,
+		/// Unverified records added for patients registered in `accounts`.
+		pub records: Vec<(T::AccountId, RecordCategory, Vec<u8>)>,
+		pub permissions: Vec<(UserType, RecordAction, Vec<RecordCategory>)>,
 	}
 
 	#[cfg(feature = "std")]
 	impl<T: Config> Default for GenesisConfig<T> {
 		fn default() -> Self {
-			Self { accounts: Default::default() }
+			Self {
+				accounts: Default::default(),
+				records: Default::default(),
+				permissions: default_permissions(),
+			}
 		}
 	}
 
 	#[pallet::genesis_build]
 	impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
 		fn build(&self) {
+			for (user_type, action, categories) in self.permissions.iter() {
+				<Permissions<T>>::insert(user_type, action, Pallet::<T>::category_set(categories));
+			}
 			for (account_id, user_type) in self.accounts.iter() {
-				<Records<T>>::insert(
-					account_id.clone(),
-					user_type.clone(),
-					BoundedVec::with_bounded_capacity(T::MaxRecordLength::get() as usize),
-				);
+				Pallet::<T>::grant_role(account_id, user_type.clone())
+					.expect("genesis accounts must not hold a role twice");
 			}
+			for (patient_id, category, content) in self.records.iter() {
+				let content =
+					content.clone().try_into().expect("genesis records must fit a record");
+				Pallet::<T>::add_unverified_record(
+					patient_id.clone(),
+					patient_id.clone(),
+					*category,
+					None,
+					Content::OnChain(content),
+				)
+				.expect("genesis records must belong to patients with room for them");
+			}
+		}
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(now: T::BlockNumber) -> Weight {
+			Self::remove_expired_requests(now)
 		}
 	}
 
 	#[pallet::call]
 	impl<T: Config> Pallet<T> {
-		// Create an account for a patient or a doctor.
-		// A single AccountId can only have one account for each UserType
-		#[pallet::weight(10_000)]
+		// Create an account for a patient.
+		// A single AccountId can only have one account; further roles are added with `add_role`.
+		// Doctor accounts are only created once the licensing authority approves an application.
+		#[pallet::weight(T::WeightInfo::create_account())]
 		pub fn create_account(origin: OriginFor<T>, user_type: UserType) -> DispatchResult {
 			let who = ensure_signed(origin)?;
+			Self::ensure_self_service(&user_type)?;
+			ensure!(!<Accounts<T>>::contains_key(&who), Error::<T>::AccountAlreadyExist);
 
-			match Self::records(&who, &user_type) {
-				Some(_) => Err(Error::<T>::AccountAlreadyExist.into()),
-				None => {
-					<Records<T>>::insert(
-						who.clone(),
-						user_type.clone(),
-						BoundedVec::with_bounded_capacity(T::MaxRecordLength::get() as usize),
-					);
-					Self::deposit_event(Event::AccountCreated(who, user_type));
-					Ok(())
-				},
+			Self::grant_role(&who, user_type.clone())?;
+			Self::deposit_event(Event::AccountCreated(who, user_type));
+			Ok(())
+		}
+
+		// Take up another role with an existing account.
+		// Clinical roles are only granted by the licensing authority.
+		#[pallet::weight(T::WeightInfo::add_role())]
+		pub fn add_role(origin: OriginFor<T>, user_type: UserType) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::ensure_self_service(&user_type)?;
+			ensure!(<Accounts<T>>::contains_key(&who), Error::<T>::AccountNotFound);
+
+			Self::grant_role(&who, user_type.clone())?;
+			Self::deposit_event(Event::RoleAdded(who, user_type));
+			Ok(())
+		}
+
+		// Give up a role. The account keeps its other roles, its profile and its records.
+		// A patient has to erase their records first.
+		#[pallet::weight(T::WeightInfo::remove_role())]
+		pub fn remove_role(origin: OriginFor<T>, user_type: UserType) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			// `RecordCount` leaves erased records out.
+			if user_type == UserType::Patient {
+				ensure!(Self::record_count(&who) == 0, Error::<T>::RecordsNotErased);
 			}
+
+			<Accounts<T>>::try_mutate(&who, |account| -> DispatchResult {
+				let account = account.as_mut().ok_or(Error::<T>::AccountNotFound)?;
+				ensure!(account.roles.contains(&user_type), Error::<T>::RoleNotHeld);
+				ensure!(account.roles.len() > 1, Error::<T>::LastRoleCannotBeRemoved);
+				account.roles.remove(&user_type);
+				Ok(())
+			})?;
+
+			Self::deposit_event(Event::RoleRemoved(who, user_type));
+			Ok(())
 		}
 
-		// Let a patient to add an 'unverified' record which can later be verified by a doctor
-		#[pallet::weight(10_000)]
-		pub fn patient_adds_record(
+		// Replace the profile of the caller's account.
+		#[pallet::weight(T::WeightInfo::set_profile(profile.len() as u32))]
+		pub fn set_profile(origin: OriginFor<T>, profile: Profile<T>) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			<Accounts<T>>::try_mutate(&who, |account| -> DispatchResult {
+				account.as_mut().ok_or(Error::<T>::AccountNotFound)?.profile = profile;
+				Ok(())
+			})?;
+
+			Self::deposit_event(Event::ProfileUpdated(who));
+			Ok(())
+		}
+
+		// Apply to become a doctor. The application stays pending until the licensing
+		// authority approves or rejects it.
+		#[pallet::weight(T::WeightInfo::apply_for_doctor_license())]
+		pub fn apply_for_doctor_license(origin: OriginFor<T>) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(!Self::has_role(&who, UserType::Doctor), Error::<T>::AccountAlreadyExist);
+			ensure!(
+				!<DoctorApplications<T>>::contains_key(&who),
+				Error::<T>::ApplicationAlreadyPending
+			);
+
+			<DoctorApplications<T>>::insert(&who, <frame_system::Pallet<T>>::block_number());
+			Self::deposit_event(Event::DoctorLicenseApplied(who));
+			Ok(())
+		}
+
+		// Approve a pending application and create the applicant's doctor account.
+		#[pallet::weight(T::WeightInfo::approve_doctor_license())]
+		pub fn approve_doctor_license(
 			origin: OriginFor<T>,
-			record_content: RecordContent<T>,
+			applicant: T::AccountId,
 		) -> DispatchResult {
-			let patient_id = ensure_signed(origin)?;
-			let add_record = |mb_record: &mut Option<BoundedVec<Record<_>, _>>| match mb_record {
-				None => Err(Error::<T>::AccountNotFound),
-				Some(patient_records) => {
-					let new_record_id = patient_records.len() as u32 + 1;
-					patient_records
-						.try_push(Record::<T>::UnverifiedRecord(
-							new_record_id,
-							patient_id.clone(),
-							record_content,
-						))
-						.map_err(|_| Error::<T>::ExceedsMaxRecordLength)
-				},
-			};
+			T::LicensingOrigin::ensure_origin(origin)?;
+			<DoctorApplications<T>>::take(&applicant).ok_or(Error::<T>::ApplicationNotFound)?;
 
-			<Records<T>>::mutate(&patient_id, &UserType::Patient, add_record)?;
-			let new_record_id = Self::records(&patient_id, &UserType::Patient)
-				.expect("records should exist")
-				.len() as u32;
-			Self::deposit_event(Event::PatientAddsRecord(patient_id, new_record_id));
+			Self::grant_role(&applicant, UserType::Doctor)?;
+			Self::deposit_event(Event::DoctorLicenseApproved(applicant));
 			Ok(())
 		}
 
-		// Let a doctor to add a verified record for a patient.
-		#[pallet::weight(10_000)]
-		pub fn doctor_adds_record(
+		// Reject a pending application.
+		#[pallet::weight(T::WeightInfo::reject_doctor_license())]
+		pub fn reject_doctor_license(
 			origin: OriginFor<T>,
-			patient_id: PatientAccountId<T>,
-			record_content: RecordContent<T>,
-			signature: Signature<T>,
+			applicant: T::AccountId,
 		) -> DispatchResult {
-			let doctor_id = ensure_signed(origin)?;
-			ensure!(
-				<Records<T>>::contains_key(&doctor_id, &UserType::Doctor),
-				Error::<T>::AccountNotFound
-			);
-			let add_record = |mb_record: &mut Option<BoundedVec<Record<_>, _>>| match mb_record {
-				None => Err(Error::<T>::AccountNotFound),
-				Some(patient_records) => {
-					let new_record_id = patient_records.len() as u32 + 1;
-					patient_records
-						.try_push(Record::<T>::VerifiedRecord(
-							new_record_id,
-							patient_id.clone(),
-							doctor_id.clone(),
-							record_content,
-							signature,
-						))
-						.map_err(|_| Error::<T>::ExceedsMaxRecordLength)
-				},
-			};
+			T::LicensingOrigin::ensure_origin(origin)?;
+			<DoctorApplications<T>>::take(&applicant).ok_or(Error::<T>::ApplicationNotFound)?;
 
-			<Records<T>>::mutate(&patient_id, &UserType::Patient, add_record)?;
-			let new_record_id = Self::records(&patient_id, &UserType::Patient)
-				.expect("records should exist")
-				.len() as u32;
-			Self::deposit_event(Event::DoctorAddsRecordForPatient(
-				patient_id,
-				doctor_id,
-				new_record_id,
-			));
+			Self::deposit_event(Event::DoctorLicenseRejected(applicant));
 			Ok(())
 		}
 
-		// Let a doctor to verify a given unverified record for a patient.
-		#[pallet::weight(10_000)]
-		pub fn doctor_verifies_record(
+		// Grant a clinical role other than doctor, e.g. to a licensed nurse or laboratory.
+		// Creates the account if it does not exist yet.
+		#[pallet::weight(T::WeightInfo::grant_license())]
+		pub fn grant_license(
 			origin: OriginFor<T>,
-			patient_id: PatientAccountId<T>,
-			record_id: u32,
-			signature: Signature<T>,
+			account: T::AccountId,
+			user_type: UserType,
 		) -> DispatchResult {
-			let doctor_id = ensure_signed(origin.clone())?;
+			T::LicensingOrigin::ensure_origin(origin)?;
 			ensure!(
-				<Records<T>>::contains_key(&doctor_id, &UserType::Doctor),
-				Error::<T>::AccountNotFound
+				!matches!(user_type, UserType::Patient | UserType::Doctor),
+				Error::<T>::InvalidArgument
 			);
-			let mut patient_records = <Records<T>>::get(&patient_id, &UserType::Patient)
-				.ok_or(Error::<T>::AccountNotFound)?;
-
-			let record_index_to_verify = (record_id - 1) as usize;
 
-			ensure!(record_index_to_verify < patient_records.len(), Error::<T>::InvalidArgument);
+			Self::grant_role(&account, user_type.clone())?;
+			Self::deposit_event(Event::RoleAdded(account, user_type));
+			Ok(())
+		}
 
-			let record_to_be_verified =
-				patient_records.get_mut(record_index_to_verify).expect("record should exist");
+		// Take a license back, e.g. when a doctor is struck off. The account keeps its other
+		// roles, and is removed if the license was its only one.
+		#[pallet::weight(T::WeightInfo::revoke_license())]
+		pub fn revoke_license(
+			origin: OriginFor<T>,
+			account: T::AccountId,
+			user_type: UserType,
+		) -> DispatchResult {
+			T::LicensingOrigin::ensure_origin(origin)?;
+			ensure!(user_type != UserType::Patient, Error::<T>::InvalidArgument);
 
-			let verified_record = Record::transform_unverified_record(
-				record_to_be_verified.clone(),
-				doctor_id.clone(),
-				signature,
-			);
+			<Accounts<T>>::try_mutate_exists(&account, |maybe_account| -> DispatchResult {
+				let roles = &mut maybe_account.as_mut().ok_or(Error::<T>::AccountNotFound)?.roles;
+				ensure!(roles.remove(&user_type), Error::<T>::RoleNotHeld);
+				if roles.is_empty() {
+					*maybe_account = None;
+				}
+				Ok(())
+			})?;
 
-			ensure!(!record_to_be_verified.is_verified(), Error::<T>::RecordAlreadyVerified);
+			Self::deposit_event(Event::RoleRemoved(account, user_type));
+			Ok(())
+		}
 
-			*record_to_be_verified = verified_record;
-			<Records<T>>::set(patient_id.clone(), UserType::Patient, Some(patient_records));
+		// Set which categories of records a role may add, verify or receive.
+		#[pallet::weight(T::WeightInfo::set_permissions())]
+		pub fn set_permissions(
+			origin: OriginFor<T>,
+			user_type: UserType,
+			action: RecordAction,
+			categories: CategorySet,
+		) -> DispatchResult {
+			T::PermissionsOrigin::ensure_origin(origin)?;
 
-			Self::deposit_event(Event::DoctorVerifiesRecordForPatient(
-				patient_id, doctor_id, record_id,
-			));
+			<Permissions<T>>::insert(&user_type, action, &categories);
+			Self::deposit_event(Event::PermissionsSet(user_type, action, categories));
 			Ok(())
 		}
 
-		#[pallet::weight(10_000)]
-		pub fn share_record_with(
+		// Set how many doctors have to endorse a record of the category before it counts as
+		// verified. Records that are verified already stay verified.
+		#[pallet::weight(T::WeightInfo::set_endorsement_threshold())]
+		pub fn set_endorsement_threshold(
 			origin: OriginFor<T>,
-			recipient_id: T::AccountId,
-			encrypted_key: EncryptedKey<T>,
-			record_id: RecordId,
+			category: RecordCategory,
+			threshold: u32,
 		) -> DispatchResult {
-			let sender_id = ensure_signed(origin.clone())?;
+			T::PermissionsOrigin::ensure_origin(origin)?;
 			ensure!(
-				Self::account_exists(&sender_id) && Self::account_exists(&recipient_id),
-				Error::<T>::AccountNotFound
+				(1..=T::MaxEndorsements::get()).contains(&threshold),
+				Error::<T>::InvalidArgument
 			);
 
-			ensure!(
-				Self::get_record_by_id(sender_id, UserType::Patient, record_id).is_some(),
-				Error::<T>::NonExistentRecord
-			);
+			<EndorsementThresholds<T>>::insert(category, threshold);
+			Self::deposit_event(Event::EndorsementThresholdSet(category, threshold));
+			Ok(())
+		}
 
-			pallet_record_sharing::Pallet::<T>::share_record(
-				origin,
-				recipient_id,
-				encrypted_key,
-				record_id,
-			)
+		// Let a patient to add an 'unverified' record which can later be verified by a doctor
+		#[pallet::weight(T::WeightInfo::patient_adds_record(
+			record_content.len() as u32,
+			T::MaxRecordLength::get(),
+		))]
+		pub fn patient_adds_record(
+			origin: OriginFor<T>,
+			category: RecordCategory,
+			title: Option<Title<T>>,
+			record_content: RecordContent<T>,
+		) -> DispatchResult {
+			let patient_id = ensure_signed(origin)?;
+			let record_id = Self::add_unverified_record(
+				patient_id.clone(),
+				patient_id.clone(),
+				category,
+				title,
+				Content::OnChain(record_content),
+			)?;
+			Self::deposit_event(Event::PatientAddsRecord(patient_id, record_id));
+			Ok(())
 		}
-	}
 
-	// helper to read
-	impl<T: Config> Pallet<T> {
-		pub fn get_record_by_id(
-			account_id: T::AccountId,
-			user_type: UserType,
-			record_id: u32,
-		) -> Option<Record<T>> {
-			Self::records(&account_id, user_type).and_then(|records| {
-				if (records.len() as u32) < record_id {
-					return None
-				}
-				records.into_iter().find(|r| r.get_id() == record_id)
-			})
+		// Like `patient_adds_record`, but only the payload's hash, size and media type are kept
+		// on chain. The payload itself goes to the offchain database of indexing nodes.
+		#[pallet::weight(T::WeightInfo::patient_adds_offchain_record(
+			payload.len() as u32,
+			T::MaxRecordLength::get(),
+		))]
+		pub fn patient_adds_offchain_record(
+			origin: OriginFor<T>,
+			category: RecordCategory,
+			title: Option<Title<T>>,
+			payload: Payload<T>,
+			media_type: MediaType<T>,
+		) -> DispatchResult {
+			let patient_id = ensure_signed(origin)?;
+			let anchor = Self::anchor_payload(&payload, media_type);
+			let record_id = Self::add_unverified_record(
+				patient_id.clone(),
+				patient_id.clone(),
+				category,
+				title,
+				Content::OffChain(anchor.clone()),
+			)?;
+			Self::hold_payload(&anchor, &payload);
+			Self::deposit_event(Event::PatientAddsRecord(patient_id, record_id));
+			Ok(())
 		}
 
-		fn account_exists(account: &T::AccountId) -> bool {
-			Self::records(account, &UserType::Patient).is_some() ||
-				Self::records(account, &UserType::Doctor).is_some()
+		// Let a doctor to add a record for a patient, endorsed by the doctor. It is verified
+		// right away unless its category asks for more endorsements.
+		// The signature must cover the id the record is about to be stored under.
+		#[pallet::weight(T::WeightInfo::doctor_adds_record(
+			record_content.len() as u32,
+			T::MaxRecordLength::get(),
+		))]
+		pub fn doctor_adds_record(
+			origin: OriginFor<T>,
+			patient_id: PatientAccountId<T>,
+			category: RecordCategory,
+			title: Option<Title<T>>,
+			record_content: RecordContent<T>,
+			signature: Signature<T>,
+		) -> DispatchResult {
+			let doctor_id = ensure_signed(origin)?;
+			Self::add_verified_record(
+				doctor_id,
+				patient_id,
+				category,
+				title,
+				Content::OnChain(record_content),
+				signature,
+			)
+		}
+
+		// Like `doctor_adds_record`, with the payload kept in the offchain database.
+		// The signature covers the payload's anchor.
+		#[pallet::weight(T::WeightInfo::doctor_adds_offchain_record(
+			payload.len() as u32,
+			T::MaxRecordLength::get(),
+		))]
+		pub fn doctor_adds_offchain_record(
+			origin: OriginFor<T>,
+			patient_id: PatientAccountId<T>,
+			category: RecordCategory,
+			title: Option<Title<T>>,
+			payload: Payload<T>,
+			media_type: MediaType<T>,
+			signature: Signature<T>,
+		) -> DispatchResult {
+			let doctor_id = ensure_signed(origin)?;
+			let anchor = Self::anchor_payload(&payload, media_type);
+			Self::add_verified_record(
+				doctor_id,
+				patient_id,
+				category,
+				title,
+				Content::OffChain(anchor.clone()),
+				signature,
+			)?;
+			Self::hold_payload(&anchor, &payload);
+			Ok(())
+		}
+
+		// Let a doctor to endorse the current version of a patient's record. The record is
+		// verified once as many doctors endorsed it as its category asks for; doctors can still
+		// countersign it after that.
+		#[pallet::weight(T::WeightInfo::doctor_verifies_record(T::MaxEndorsements::get()))]
+		pub fn doctor_verifies_record(
+			origin: OriginFor<T>,
+			patient_id: PatientAccountId<T>,
+			record_id: u32,
+			signature: Signature<T>,
+		) -> DispatchResult {
+			let doctor_id = ensure_signed(origin.clone())?;
+			ensure!(<Accounts<T>>::contains_key(&doctor_id), Error::<T>::AccountNotFound);
+			ensure!(Self::has_role(&patient_id, UserType::Patient), Error::<T>::AccountNotFound);
+			let record_to_be_verified =
+				Self::record(&patient_id, record_id).ok_or(Error::<T>::InvalidArgument)?;
+
+			ensure!(
+				Self::is_permitted(
+					&doctor_id,
+					RecordAction::Verify,
+					record_to_be_verified.category
+				),
+				Error::<T>::NotPermitted
+			);
+			let record_content =
+				record_to_be_verified.get_content().ok_or(Error::<T>::RecordAlreadyErased)?;
+			let version = Self::current_version(&patient_id, record_id);
+			ensure!(
+				Self::is_valid_signature(
+					&signature,
+					&doctor_id,
+					&patient_id,
+					record_id,
+					version,
+					record_content,
+				),
+				Error::<T>::InvalidSignature
+			);
+			let was_verified = record_to_be_verified.is_verified();
+			let endorsed_record =
+				Self::endorse(record_to_be_verified, &patient_id, &doctor_id, signature)?;
+			let endorsements = endorsed_record.endorsements().len() as u32;
+			let is_verified = endorsed_record.is_verified();
+			<PatientRecords<T>>::insert(&patient_id, record_id, endorsed_record);
+			Self::audit(
+				&patient_id,
+				&doctor_id,
+				AuditAction::Verify,
+				Some(record_id),
+				AccessPath::Policy,
+				Default::default(),
+			);
+
+			Self::deposit_event(Event::RecordEndorsed(
+				patient_id.clone(),
+				doctor_id.clone(),
+				record_id,
+				endorsements,
+			));
+			if is_verified && !was_verified {
+				Self::deposit_event(Event::DoctorVerifiesRecordForPatient(
+					patient_id, doctor_id, record_id,
+				));
+			}
+			Ok(())
+		}
+
+		// Let the patient, or the author or an endorser of the record, correct it. The correction
+		// becomes a new version linked to the previous ones and has to be endorsed again. Once
+		// `MaxRecordVersions` versions are kept, the oldest one is dropped. The call is weighed
+		// as if it is.
+		#[pallet::weight(T::WeightInfo::amend_record(record_content.len() as u32, 1))]
+		pub fn amend_record(
+			origin: OriginFor<T>,
+			patient_id: PatientAccountId<T>,
+			record_id: RecordId,
+			record_content: RecordContent<T>,
+		) -> DispatchResult {
+			let author = ensure_signed(origin)?;
+			Self::amend(author, patient_id, record_id, Content::OnChain(record_content))
+		}
+
+		// Like `amend_record`, with the payload of the new version kept in the offchain database.
+		#[pallet::weight(T::WeightInfo::amend_offchain_record(payload.len() as u32, 1))]
+		pub fn amend_offchain_record(
+			origin: OriginFor<T>,
+			patient_id: PatientAccountId<T>,
+			record_id: RecordId,
+			payload: Payload<T>,
+			media_type: MediaType<T>,
+		) -> DispatchResult {
+			let author = ensure_signed(origin)?;
+			let anchor = Self::anchor_payload(&payload, media_type);
+			Self::amend(author, patient_id, record_id, Content::OffChain(anchor.clone()))?;
+			Self::index_payload(&anchor.hash, &payload);
+			Ok(())
+		}
+
+		// Let a patient erase one of their records. A tombstone keeps the record id, its
+		// category, when it was created and the block it was erased at; the title, the content,
+		// the doctors' endorsements, the earlier versions and every sharing of the record are
+		// dropped. `shared_with` is the number of accounts the record is shared with, which the
+		// call is weighed by; it fails if the record is shared with more.
+		#[pallet::weight(T::WeightInfo::erase_record(*shared_with))]
+		pub fn erase_record(
+			origin: OriginFor<T>,
+			record_id: RecordId,
+			shared_with: u32,
+		) -> DispatchResult {
+			let patient_id = ensure_signed(origin)?;
+			ensure!(Self::has_role(&patient_id, UserType::Patient), Error::<T>::AccountNotFound);
+			let record_to_be_erased =
+				Self::record(&patient_id, record_id).ok_or(Error::<T>::NonExistentRecord)?;
+			ensure!(!record_to_be_erased.is_erased(), Error::<T>::RecordAlreadyErased);
+			for payload_hash in Self::payload_hashes(&record_to_be_erased) {
+				Self::release_payload(&payload_hash);
+			}
+
+			let tombstone = Record {
+				title: None,
+				status: RecordStatus::Erased(<frame_system::Pallet<T>>::block_number()),
+				..record_to_be_erased
+			};
+			<PatientRecords<T>>::insert(&patient_id, record_id, tombstone);
+			<RecordCount<T>>::mutate(&patient_id, |count| *count = count.saturating_sub(1));
+			for version in <RecordVersions<T>>::take(&patient_id, record_id) {
+				Self::release_version(&patient_id, record_id, &version);
+			}
+			let recipients = T::RecordSharing::revoke_record(patient_id.clone(), record_id);
+			ensure!(recipients.len() as u32 <= shared_with, Error::<T>::TooManySharings);
+			for recipient in recipients {
+				Self::audit(
+					&patient_id,
+					&patient_id,
+					AuditAction::Revoke { recipient },
+					Some(record_id),
+					AccessPath::Direct,
+					Default::default(),
+				);
+			}
+			<EmergencyProfile<T>>::remove(&patient_id, record_id);
+			if let Some((depositor, deposit)) = <RecordDeposits<T>>::take(&patient_id, record_id) {
+				Self::return_deposit(&depositor, &patient_id, record_id, deposit);
+			}
+
+			Self::deposit_event(Event::RecordErased(patient_id, record_id));
+			Ok(())
+		}
+
+		#[pallet::weight(T::WeightInfo::share_record_with())]
+		pub fn share_record_with(
+			origin: OriginFor<T>,
+			recipient_id: T::AccountId,
+			encrypted_key: EncryptedKey<T>,
+			recipient_key_id: KeyId,
+			record_id: RecordId,
+			validity: Option<ValidityWindow<T::BlockNumber>>,
+		) -> DispatchResult {
+			let sender_id = ensure_signed(origin)?;
+			T::RecordSharing::share(
+				sender_id.clone(),
+				recipient_id.clone(),
+				encrypted_key,
+				recipient_key_id,
+				record_id,
+				validity,
+			)?;
+
+			Self::audit(
+				&sender_id,
+				&sender_id,
+				AuditAction::Grant { recipient: recipient_id },
+				Some(record_id),
+				AccessPath::Direct,
+				Default::default(),
+			);
+			Ok(())
+		}
+
+		#[pallet::weight(T::WeightInfo::revoke_share_with())]
+		pub fn revoke_share_with(
+			origin: OriginFor<T>,
+			recipient_id: T::AccountId,
+			record_id: RecordId,
+		) -> DispatchResult {
+			let sender_id = ensure_signed(origin)?;
+			T::RecordSharing::revoke(sender_id.clone(), recipient_id.clone(), record_id)?;
+
+			Self::audit(
+				&sender_id,
+				&sender_id,
+				AuditAction::Revoke { recipient: recipient_id },
+				Some(record_id),
+				AccessPath::Direct,
+				Default::default(),
+			);
+			Ok(())
+		}
+
+		// Set the encryption key of the emergency responder role. Profiles wrapped for an
+		// earlier key have to be wrapped again by their patients.
+		#[pallet::weight(T::WeightInfo::set_emergency_key())]
+		pub fn set_emergency_key(
+			origin: OriginFor<T>,
+			public_key: EncryptionPublicKey,
+		) -> DispatchResult {
+			T::LicensingOrigin::ensure_origin(origin)?;
+			let key_id = Self::emergency_key().map_or(1, |(key_id, _)| key_id.saturating_add(1));
+
+			<EmergencyKey<T>>::put((key_id, public_key));
+			Self::deposit_event(Event::EmergencyKeySet(key_id));
+			Ok(())
+		}
+
+		// Let a patient add a record to their emergency profile, e.g. allergies or blood type,
+		// with its key wrapped for the current emergency key. The change is logged, since it
+		// decides what a responder can read.
+		#[pallet::weight(T::WeightInfo::set_emergency_record())]
+		pub fn set_emergency_record(
+			origin: OriginFor<T>,
+			record_id: RecordId,
+			encrypted_key: EncryptedKey<T>,
+			key_id: KeyId,
+		) -> DispatchResult {
+			let patient_id = ensure_signed(origin)?;
+			ensure!(Self::has_role(&patient_id, UserType::Patient), Error::<T>::AccountNotFound);
+			let record =
+				Self::record(&patient_id, record_id).ok_or(Error::<T>::NonExistentRecord)?;
+			ensure!(!record.is_erased(), Error::<T>::RecordAlreadyErased);
+			ensure!(
+				Self::emergency_key().map_or(false, |(current, _)| current == key_id),
+				Error::<T>::StaleEmergencyKey
+			);
+
+			<EmergencyProfile<T>>::insert(&patient_id, record_id, (encrypted_key, key_id));
+			Self::audit(
+				&patient_id,
+				&patient_id,
+				AuditAction::SetEmergencyProfile,
+				Some(record_id),
+				AccessPath::Direct,
+				Default::default(),
+			);
+			Self::deposit_event(Event::EmergencyRecordSet(patient_id, record_id));
+			Ok(())
+		}
+
+		// Let a patient take a record out of their emergency profile. The change is logged like
+		// adding one.
+		#[pallet::weight(T::WeightInfo::clear_emergency_record())]
+		pub fn clear_emergency_record(origin: OriginFor<T>, record_id: RecordId) -> DispatchResult {
+			let patient_id = ensure_signed(origin)?;
+			<EmergencyProfile<T>>::take(&patient_id, record_id)
+				.ok_or(Error::<T>::NonExistentRecord)?;
+
+			Self::audit(
+				&patient_id,
+				&patient_id,
+				AuditAction::ClearEmergencyProfile,
+				Some(record_id),
+				AccessPath::Direct,
+				Default::default(),
+			);
+			Self::deposit_event(Event::EmergencyRecordCleared(patient_id, record_id));
+			Ok(())
+		}
+
+		// Let a licensed emergency responder access a patient's emergency profile, which needs a
+		// record key wrapped for the current emergency key. The access is logged with the reason
+		// and announced to the patient. The responder's system unwraps the profile's keys with
+		// the emergency key only after this call.
+		#[pallet::weight(T::WeightInfo::break_glass(T::MaxRecordLength::get()))]
+		pub fn break_glass(
+			origin: OriginFor<T>,
+			patient_id: PatientAccountId<T>,
+			reason: Purpose<T>,
+		) -> DispatchResult {
+			let responder = ensure_signed(origin)?;
+			ensure!(
+				Self::has_role(&responder, UserType::EmergencyResponder),
+				Error::<T>::NotPermitted
+			);
+			ensure!(Self::has_role(&patient_id, UserType::Patient), Error::<T>::AccountNotFound);
+			let mut profile = <EmergencyProfile<T>>::iter_prefix_values(&patient_id).peekable();
+			ensure!(profile.peek().is_some(), Error::<T>::NoEmergencyProfile);
+			// Only record keys wrapped for the current emergency key can be unwrapped.
+			let current = Self::emergency_key().map(|(key_id, _)| key_id);
+			ensure!(
+				profile.any(|(_, key_id)| Some(key_id) == current),
+				Error::<T>::StaleEmergencyKey
+			);
+
+			Self::audit(
+				&patient_id,
+				&responder,
+				AuditAction::BreakGlass,
+				None,
+				AccessPath::Emergency,
+				reason.clone(),
+			);
+			Self::deposit_event(Event::EmergencyAccessed(patient_id, responder, reason));
+			Ok(())
+		}
+
+		// Let a patient appoint a registered account as their guardian, or change the scope or
+		// expiry of an existing guardian.
+		#[pallet::weight(T::WeightInfo::appoint_guardian())]
+		pub fn appoint_guardian(
+			origin: OriginFor<T>,
+			guardian_id: T::AccountId,
+			scope: GuardianScope,
+			expires_at: Option<T::BlockNumber>,
+		) -> DispatchResult {
+			let patient_id = ensure_signed(origin)?;
+			Self::do_appoint_guardian(patient_id, guardian_id, scope, expires_at)
+		}
+
+		// Let the licensing authority appoint a guardian for a patient who cannot do it
+		// themselves, e.g. a minor.
+		#[pallet::weight(T::WeightInfo::appoint_guardian_for())]
+		pub fn appoint_guardian_for(
+			origin: OriginFor<T>,
+			patient_id: PatientAccountId<T>,
+			guardian_id: T::AccountId,
+			scope: GuardianScope,
+			expires_at: Option<T::BlockNumber>,
+		) -> DispatchResult {
+			T::LicensingOrigin::ensure_origin(origin)?;
+			Self::do_appoint_guardian(patient_id, guardian_id, scope, expires_at)
+		}
+
+		// End a guardianship. Either the patient or the guardian can end it.
+		#[pallet::weight(T::WeightInfo::revoke_guardian())]
+		pub fn revoke_guardian(
+			origin: OriginFor<T>,
+			patient_id: PatientAccountId<T>,
+			guardian_id: T::AccountId,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(who == patient_id || who == guardian_id, DispatchError::BadOrigin);
+			Self::do_revoke_guardian(patient_id, guardian_id)
+		}
+
+		// Let the licensing authority end a guardianship, e.g. once a minor comes of age.
+		#[pallet::weight(T::WeightInfo::revoke_guardian_for())]
+		pub fn revoke_guardian_for(
+			origin: OriginFor<T>,
+			patient_id: PatientAccountId<T>,
+			guardian_id: T::AccountId,
+		) -> DispatchResult {
+			T::LicensingOrigin::ensure_origin(origin)?;
+			Self::do_revoke_guardian(patient_id, guardian_id)
+		}
+
+		// Let a guardian add an unverified record for the patient. The guardian pays the deposit.
+		#[pallet::weight(T::WeightInfo::guardian_adds_record(
+			record_content.len() as u32,
+			T::MaxRecordLength::get(),
+		))]
+		pub fn guardian_adds_record(
+			origin: OriginFor<T>,
+			patient_id: PatientAccountId<T>,
+			category: RecordCategory,
+			title: Option<Title<T>>,
+			record_content: RecordContent<T>,
+		) -> DispatchResult {
+			let guardian_id = ensure_signed(origin)?;
+			Self::ensure_guardian(&patient_id, &guardian_id, |scope| scope.add_records)?;
+			let record_id = Self::add_unverified_record(
+				guardian_id.clone(),
+				patient_id.clone(),
+				category,
+				title,
+				Content::OnChain(record_content),
+			)?;
+			Self::deposit_event(Event::GuardianAddsRecordForPatient(
+				patient_id,
+				guardian_id,
+				record_id,
+			));
+			Ok(())
+		}
+
+		// Let a guardian share a record of the patient. The sharing is the patient's, so the
+		// patient can revoke it and their deposit is reserved.
+		#[pallet::weight(T::WeightInfo::guardian_shares_record())]
+		pub fn guardian_shares_record(
+			origin: OriginFor<T>,
+			patient_id: PatientAccountId<T>,
+			recipient_id: T::AccountId,
+			encrypted_key: EncryptedKey<T>,
+			recipient_key_id: KeyId,
+			record_id: RecordId,
+			validity: Option<ValidityWindow<T::BlockNumber>>,
+		) -> DispatchResult {
+			let guardian_id = ensure_signed(origin)?;
+			Self::ensure_guardian(&patient_id, &guardian_id, |scope| scope.share)?;
+			T::RecordSharing::share(
+				patient_id.clone(),
+				recipient_id.clone(),
+				encrypted_key,
+				recipient_key_id,
+				record_id,
+				validity,
+			)?;
+
+			Self::audit(
+				&patient_id,
+				&guardian_id,
+				AuditAction::Grant { recipient: recipient_id.clone() },
+				Some(record_id),
+				AccessPath::Guardian,
+				Default::default(),
+			);
+			Self::deposit_event(Event::GuardianSharesRecord(
+				patient_id,
+				guardian_id,
+				recipient_id,
+				record_id,
+			));
+			Ok(())
+		}
+
+		// Let a guardian revoke a sharing of the patient's record.
+		#[pallet::weight(T::WeightInfo::guardian_revokes_share())]
+		pub fn guardian_revokes_share(
+			origin: OriginFor<T>,
+			patient_id: PatientAccountId<T>,
+			recipient_id: T::AccountId,
+			record_id: RecordId,
+		) -> DispatchResult {
+			let guardian_id = ensure_signed(origin)?;
+			Self::ensure_guardian(&patient_id, &guardian_id, |scope| scope.revoke_shares)?;
+			T::RecordSharing::revoke(patient_id.clone(), recipient_id.clone(), record_id)?;
+
+			Self::audit(
+				&patient_id,
+				&guardian_id,
+				AuditAction::Revoke { recipient: recipient_id.clone() },
+				Some(record_id),
+				AccessPath::Guardian,
+				Default::default(),
+			);
+			Self::deposit_event(Event::GuardianRevokesShare(
+				patient_id,
+				guardian_id,
+				recipient_id,
+				record_id,
+			));
+			Ok(())
+		}
+
+		// Ask a patient to share records, either the given ones or those of a category, for
+		// `duration` blocks. The request expires if the patient does not answer it in time.
+		#[pallet::weight(T::WeightInfo::request_access(records.record_count()))]
+		pub fn request_access(
+			origin: OriginFor<T>,
+			patient_id: PatientAccountId<T>,
+			records: RequestedRecords<T>,
+			reason: Purpose<T>,
+			duration: T::BlockNumber,
+		) -> DispatchResult {
+			let requester = ensure_signed(origin)?;
+			ensure!(Self::has_role(&patient_id, UserType::Patient), Error::<T>::AccountNotFound);
+			ensure!(<Accounts<T>>::contains_key(&requester), Error::<T>::AccountNotFound);
+			ensure!(requester != patient_id && !duration.is_zero(), Error::<T>::InvalidArgument);
+			match &records {
+				RequestedRecords::Records(record_ids) => {
+					ensure!(!record_ids.is_empty(), Error::<T>::InvalidArgument);
+					for record_id in record_ids.iter() {
+						let record = Self::get_record_by_id(&patient_id, *record_id)
+							.filter(|record| !record.is_erased())
+							.ok_or(Error::<T>::NonExistentRecord)?;
+						ensure!(
+							Self::is_permitted(&requester, RecordAction::Receive, record.category),
+							Error::<T>::NotPermitted
+						);
+					}
+				},
+				RequestedRecords::Category(category) => ensure!(
+					Self::is_permitted(&requester, RecordAction::Receive, *category),
+					Error::<T>::NotPermitted
+				),
+			}
+
+			ensure!(
+				Self::open_requests(&patient_id, &requester) < T::MaxOpenRequests::get(),
+				Error::<T>::TooManyOpenRequests
+			);
+
+			let now = <frame_system::Pallet<T>>::block_number();
+			let request_id = <LastRequestId<T>>::get(&patient_id).saturating_add(1);
+			let expires_at = Self::schedule_request_expiry(
+				now.saturating_add(T::AccessRequestLifetime::get()),
+				&patient_id,
+				request_id,
+			)?;
+			let deposit = T::AccessRequestDeposit::get();
+			T::Currency::reserve(&requester, deposit)?;
+			<OpenRequests<T>>::mutate(&patient_id, &requester, |open| *open += 1);
+			<LastRequestId<T>>::insert(&patient_id, request_id);
+			<AccessRequests<T>>::insert(
+				&patient_id,
+				request_id,
+				AccessRequest {
+					requester: requester.clone(),
+					records,
+					reason,
+					duration,
+					requested_at: now,
+					expires_at,
+					deposit,
+				},
+			);
+
+			Self::deposit_event(Event::AccessRequested(patient_id, requester, request_id));
+			Ok(())
+		}
+
+		// Answer an access request by sharing the requested records for the requested duration.
+		// `keys` holds the key of every requested record, or of every record of the requested
+		// category the patient chooses to share, sealed for the requester's key
+		// `recipient_key_id`.
+		#[pallet::weight(T::WeightInfo::approve_access_request(keys.len() as u32))]
+		pub fn approve_access_request(
+			origin: OriginFor<T>,
+			request_id: RequestId,
+			keys: BoundedVec<(RecordId, EncryptedKey<T>), T::MaxRequestedRecords>,
+			recipient_key_id: KeyId,
+		) -> DispatchResult {
+			let patient_id = ensure_signed(origin)?;
+			let request = <AccessRequests<T>>::take(&patient_id, request_id)
+				.ok_or(Error::<T>::AccessRequestNotFound)?;
+			Self::ensure_keys_match_request(&patient_id, &request.records, &keys)?;
+			Self::close_request(&patient_id, &request);
+
+			let now = <frame_system::Pallet<T>>::block_number();
+			let validity =
+				ValidityWindow { starts_at: now, ends_at: now.saturating_add(request.duration) };
+			for (record_id, encrypted_key) in keys {
+				T::RecordSharing::share(
+					patient_id.clone(),
+					request.requester.clone(),
+					encrypted_key,
+					recipient_key_id,
+					record_id,
+					Some(validity.clone()),
+				)?;
+				Self::audit(
+					&patient_id,
+					&patient_id,
+					AuditAction::Grant { recipient: request.requester.clone() },
+					Some(record_id),
+					AccessPath::Direct,
+					request.reason.clone(),
+				);
+			}
+
+			Self::deposit_event(Event::AccessRequestApproved(
+				patient_id,
+				request.requester,
+				request_id,
+			));
+			Ok(())
+		}
+
+		#[pallet::weight(T::WeightInfo::deny_access_request())]
+		pub fn deny_access_request(origin: OriginFor<T>, request_id: RequestId) -> DispatchResult {
+			let patient_id = ensure_signed(origin)?;
+			let request = <AccessRequests<T>>::take(&patient_id, request_id)
+				.ok_or(Error::<T>::AccessRequestNotFound)?;
+			Self::close_request(&patient_id, &request);
+
+			Self::deposit_event(Event::AccessRequestDenied(
+				patient_id,
+				request.requester,
+				request_id,
+			));
+			Ok(())
+		}
+
+		// Let an account whose role may add records of the category, e.g. a nurse writing a
+		// note, add one for a patient without endorsing it. The record is unverified until
+		// someone whose role may verify it endorses it. The author pays the deposit.
+		#[pallet::weight(T::WeightInfo::author_adds_record(
+			record_content.len() as u32,
+			T::MaxRecordLength::get(),
+		))]
+		pub fn author_adds_record(
+			origin: OriginFor<T>,
+			patient_id: PatientAccountId<T>,
+			category: RecordCategory,
+			title: Option<Title<T>>,
+			record_content: RecordContent<T>,
+		) -> DispatchResult {
+			let author = ensure_signed(origin)?;
+			ensure!(<Accounts<T>>::contains_key(&author), Error::<T>::AccountNotFound);
+			ensure!(
+				Self::is_permitted(&author, RecordAction::Add, category),
+				Error::<T>::NotPermitted
+			);
+			let record_id = Self::add_unverified_record(
+				author.clone(),
+				patient_id.clone(),
+				category,
+				title,
+				Content::OnChain(record_content),
+			)?;
+			Self::deposit_event(Event::AuthorAddsRecordForPatient(patient_id, author, record_id));
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		// Add an unverified record written by the patient, one of their guardians or a licensed
+		// author, who pays the deposit. Returns the id of the record.
+		fn add_unverified_record(
+			author: T::AccountId,
+			patient_id: PatientAccountId<T>,
+			category: RecordCategory,
+			title: Option<Title<T>>,
+			content: Content<T>,
+		) -> Result<RecordId, DispatchError> {
+			let new_record_id = Self::ensure_can_add_record(&patient_id)?;
+			Self::push_version(&patient_id, new_record_id, 1, author.clone(), content.clone())?;
+			Self::insert_record(
+				&author,
+				&patient_id,
+				Self::new_record(
+					new_record_id,
+					patient_id.clone(),
+					author.clone(),
+					category,
+					title,
+					RecordStatus::Unverified(content, Default::default()),
+				),
+			)?;
+			Ok(new_record_id)
+		}
+
+		fn do_appoint_guardian(
+			patient_id: PatientAccountId<T>,
+			guardian_id: T::AccountId,
+			scope: GuardianScope,
+			expires_at: Option<T::BlockNumber>,
+		) -> DispatchResult {
+			ensure!(Self::has_role(&patient_id, UserType::Patient), Error::<T>::AccountNotFound);
+			ensure!(<Accounts<T>>::contains_key(&guardian_id), Error::<T>::AccountNotFound);
+			ensure!(patient_id != guardian_id, Error::<T>::InvalidArgument);
+			let now = <frame_system::Pallet<T>>::block_number();
+			ensure!(
+				expires_at.map_or(true, |expires_at| expires_at > now),
+				Error::<T>::InvalidArgument
+			);
+
+			<Guardians<T>>::insert(
+				&patient_id,
+				&guardian_id,
+				Guardianship { scope, expires_at, appointed_at: now },
+			);
+			Self::deposit_event(Event::GuardianAppointed(
+				patient_id,
+				guardian_id,
+				scope,
+				expires_at,
+			));
+			Ok(())
+		}
+
+		// Requested records need a key each and nothing else; for a category, the patient picks
+		// which of its records to share, but at least one.
+		fn ensure_keys_match_request(
+			patient_id: &PatientAccountId<T>,
+			records: &RequestedRecords<T>,
+			keys: &[(RecordId, EncryptedKey<T>)],
+		) -> DispatchResult {
+			let mut key_ids: Vec<RecordId> = keys.iter().map(|(record_id, _)| *record_id).collect();
+			key_ids.sort_unstable();
+			let distinct = key_ids.windows(2).all(|pair| pair[0] != pair[1]);
+			ensure!(distinct && !key_ids.is_empty(), Error::<T>::KeysDoNotMatchRequest);
+			match records {
+				RequestedRecords::Records(record_ids) => {
+					let mut record_ids = record_ids.clone().into_inner();
+					record_ids.sort_unstable();
+					record_ids.dedup();
+					ensure!(record_ids == key_ids, Error::<T>::KeysDoNotMatchRequest);
+				},
+				RequestedRecords::Category(category) => ensure!(
+					key_ids.iter().all(|record_id| {
+						Self::get_record_by_id(patient_id, *record_id)
+							.map_or(false, |record| record.category == *category)
+					}),
+					Error::<T>::KeysDoNotMatchRequest
+				),
+			}
+			Ok(())
+		}
+
+		// Queue a request to expire at `expires_at`, or at the first of the next
+		// `MAX_REQUEST_EXPIRY_DELAY` blocks whose queue is not full. Returns the block it
+		// expires at.
+		fn schedule_request_expiry(
+			expires_at: T::BlockNumber,
+			patient_id: &PatientAccountId<T>,
+			request_id: RequestId,
+		) -> Result<T::BlockNumber, Error<T>> {
+			let mut block = expires_at;
+			for _ in 0..=MAX_REQUEST_EXPIRY_DELAY {
+				if <RequestExpiries<T>>::try_append(block, (patient_id.clone(), request_id)).is_ok()
+				{
+					return Ok(block)
+				}
+				block = block.saturating_add(One::one());
+			}
+			Err(Error::<T>::TooManyRequestExpiries)
+		}
+
+		// Return the deposit of a request that was answered or expired.
+		fn close_request(patient_id: &PatientAccountId<T>, request: &AccessRequest<T>) {
+			T::Currency::unreserve(&request.requester, request.deposit);
+			<OpenRequests<T>>::mutate_exists(patient_id, &request.requester, |open| {
+				*open = open.and_then(|open| open.checked_sub(1)).filter(|open| *open > 0);
+			});
+		}
+
+		// Remove the access requests expiring at `now` that were not answered yet.
+		fn remove_expired_requests(now: T::BlockNumber) -> Weight {
+			let db_weight = T::DbWeight::get();
+			let expiring = <RequestExpiries<T>>::take(now);
+			let mut weight = db_weight.reads_writes(1, 1);
+			for (patient_id, request_id) in expiring {
+				weight = weight.saturating_add(db_weight.reads_writes(1, 1));
+				if let Some(request) = <AccessRequests<T>>::take(&patient_id, request_id) {
+					weight = weight.saturating_add(db_weight.reads_writes(2, 2));
+					Self::close_request(&patient_id, &request);
+					Self::deposit_event(Event::AccessRequestExpired(
+						patient_id,
+						request.requester,
+						request_id,
+					));
+				}
+			}
+			weight
+		}
+
+		// Append an entry to the patient's audit log, dropping the oldest one if it is full.
+		fn audit(
+			patient_id: &PatientAccountId<T>,
+			actor: &T::AccountId,
+			action: AuditAction<T::AccountId>,
+			record_id: Option<RecordId>,
+			path: AccessPath,
+			purpose: Purpose<T>,
+		) {
+			let entry = AuditEntry {
+				actor: actor.clone(),
+				action,
+				record_id,
+				path,
+				purpose,
+				at: <frame_system::Pallet<T>>::block_number(),
+			};
+			<AuditLog<T>>::mutate(patient_id, |log| {
+				if log.is_full() {
+					log.remove(0);
+				}
+				// There is room after dropping the oldest entry.
+				let _ = log.try_push(entry);
+			});
+		}
+
+		fn do_revoke_guardian(
+			patient_id: PatientAccountId<T>,
+			guardian_id: T::AccountId,
+		) -> DispatchResult {
+			<Guardians<T>>::take(&patient_id, &guardian_id).ok_or(Error::<T>::NotAGuardian)?;
+			Self::deposit_event(Event::GuardianRevoked(patient_id, guardian_id));
+			Ok(())
+		}
+
+		// Check that `guardian_id` currently guards the patient with a scope that `allows` the
+		// call.
+		fn ensure_guardian(
+			patient_id: &PatientAccountId<T>,
+			guardian_id: &T::AccountId,
+			allows: impl FnOnce(&GuardianScope) -> bool,
+		) -> DispatchResult {
+			let guardianship =
+				Self::guardianship(patient_id, guardian_id).ok_or(Error::<T>::NotAGuardian)?;
+			let now = <frame_system::Pallet<T>>::block_number();
+			ensure!(
+				guardianship.expires_at.map_or(true, |expires_at| now < expires_at),
+				Error::<T>::GuardianshipExpired
+			);
+			ensure!(allows(&guardianship.scope), Error::<T>::OutsideGuardianScope);
+			Ok(())
+		}
+
+		fn add_verified_record(
+			doctor_id: DoctorAccountId<T>,
+			patient_id: PatientAccountId<T>,
+			category: RecordCategory,
+			title: Option<Title<T>>,
+			content: Content<T>,
+			signature: Signature<T>,
+		) -> DispatchResult {
+			// Adding a record as a doctor both adds and endorses it.
+			ensure!(<Accounts<T>>::contains_key(&doctor_id), Error::<T>::AccountNotFound);
+			ensure!(
+				Self::is_permitted(&doctor_id, RecordAction::Add, category) &&
+					Self::is_permitted(&doctor_id, RecordAction::Verify, category),
+				Error::<T>::NotPermitted
+			);
+			let new_record_id = Self::ensure_can_add_record(&patient_id)?;
+			ensure!(
+				Self::is_valid_signature(
+					&signature,
+					&doctor_id,
+					&patient_id,
+					new_record_id,
+					1,
+					&content,
+				),
+				Error::<T>::InvalidSignature
+			);
+
+			Self::push_version(&patient_id, new_record_id, 1, doctor_id.clone(), content.clone())?;
+			let record = Self::new_record(
+				new_record_id,
+				patient_id.clone(),
+				doctor_id.clone(),
+				category,
+				title,
+				RecordStatus::Unverified(content, Default::default()),
+			);
+			let record = Self::endorse(record, &patient_id, &doctor_id, signature)?;
+			Self::insert_record(&doctor_id, &patient_id, record)?;
+
+			Self::audit(
+				&patient_id,
+				&doctor_id,
+				AuditAction::Verify,
+				Some(new_record_id),
+				AccessPath::Policy,
+				Default::default(),
+			);
+			Self::deposit_event(Event::DoctorAddsRecordForPatient(
+				patient_id,
+				doctor_id,
+				new_record_id,
+			));
+			Ok(())
+		}
+
+		// The id a new record of the patient gets, if they can have another one.
+		fn ensure_can_add_record(patient_id: &PatientAccountId<T>) -> Result<RecordId, Error<T>> {
+			ensure!(Self::has_role(patient_id, UserType::Patient), Error::<T>::AccountNotFound);
+			ensure!(
+				Self::record_count(patient_id) < T::MaxRecordLength::get(),
+				Error::<T>::ExceedsMaxRecordLength
+			);
+			Ok(Self::next_record_id(patient_id))
+		}
+
+		// A record created in the current block.
+		fn new_record(
+			record_id: RecordId,
+			patient_id: PatientAccountId<T>,
+			author: T::AccountId,
+			category: RecordCategory,
+			title: Option<Title<T>>,
+			status: RecordStatus<T>,
+		) -> Record<T> {
+			Record {
+				id: record_id,
+				patient: patient_id,
+				author,
+				category,
+				title,
+				created_at: <frame_system::Pallet<T>>::block_number(),
+				created_on: <pallet_timestamp::Pallet<T>>::get(),
+				status,
+			}
+		}
+
+		// Store a new record, reserving a deposit for it from `depositor`.
+		fn insert_record(
+			depositor: &T::AccountId,
+			patient_id: &PatientAccountId<T>,
+			record: Record<T>,
+		) -> DispatchResult {
+			let record_id = record.get_id();
+			Self::set_record_deposit(depositor, patient_id, &record)?;
+			<PatientRecords<T>>::insert(patient_id, record_id, record);
+			<LastRecordId<T>>::insert(patient_id, record_id);
+			<RecordCount<T>>::mutate(patient_id, |count| *count = count.saturating_add(1));
+			Ok(())
+		}
+
+		// Make `depositor` pay for the record as it is now. If they paid for it before, only the
+		// difference is reserved or released; otherwise the earlier depositor gets theirs back.
+		fn set_record_deposit(
+			depositor: &T::AccountId,
+			patient_id: &PatientAccountId<T>,
+			record: &Record<T>,
+		) -> DispatchResult {
+			let deposit = Self::deposit_for(record.encoded_size());
+			match <RecordDeposits<T>>::get(patient_id, record.id) {
+				Some((old_depositor, old_deposit)) if old_depositor == *depositor =>
+					if deposit > old_deposit {
+						T::Currency::reserve(depositor, deposit - old_deposit)?;
+					} else {
+						T::Currency::unreserve(depositor, old_deposit - deposit);
+					},
+				old => {
+					T::Currency::reserve(depositor, deposit)?;
+					if let Some((old_depositor, old_deposit)) = old {
+						Self::return_deposit(&old_depositor, patient_id, record.id, old_deposit);
+					}
+				},
+			}
+			<RecordDeposits<T>>::insert(patient_id, record.id, (depositor.clone(), deposit));
+			Self::deposit_event(Event::RecordDepositReserved(
+				depositor.clone(),
+				patient_id.clone(),
+				record.id,
+				deposit,
+			));
+			Ok(())
+		}
+
+		fn deposit_for(encoded_size: usize) -> BalanceOf<T> {
+			T::DepositPerByte::get().saturating_mul((encoded_size as u32).into())
+		}
+
+		// An endorsement is kept both in the record and in its version, so the doctor pays for
+		// both.
+		pub(crate) fn endorsement_deposit(endorsement: &Endorsement<T>) -> BalanceOf<T> {
+			Self::deposit_for(2 * endorsement.encoded_size())
+		}
+
+		// Return a deposit of the record to whoever reserved it.
+		fn return_deposit(
+			depositor: &T::AccountId,
+			patient_id: &PatientAccountId<T>,
+			record_id: RecordId,
+			deposit: BalanceOf<T>,
+		) {
+			if deposit.is_zero() {
+				return
+			}
+			T::Currency::unreserve(depositor, deposit);
+			Self::deposit_event(Event::RecordDepositReturned(
+				depositor.clone(),
+				patient_id.clone(),
+				record_id,
+				deposit,
+			));
+		}
+
+		// Return the deposits of a version that is no longer kept and of its endorsements.
+		fn release_version(
+			patient_id: &PatientAccountId<T>,
+			record_id: RecordId,
+			version: &RecordVersion<T>,
+		) {
+			Self::return_deposit(&version.author, patient_id, record_id, version.deposit);
+			for endorsement in version.endorsements.iter() {
+				Self::return_deposit(
+					&endorsement.doctor,
+					patient_id,
+					record_id,
+					endorsement.deposit,
+				);
+			}
+		}
+
+		// Add the doctor's endorsement to the current version of the record, whose signature
+		// the caller checked, reserving a deposit for it from the doctor. The record is verified
+		// once its category's threshold is met, and stays verified if the threshold is raised
+		// later.
+		fn endorse(
+			record: Record<T>,
+			patient_id: &PatientAccountId<T>,
+			doctor_id: &DoctorAccountId<T>,
+			signature: Signature<T>,
+		) -> Result<Record<T>, DispatchError> {
+			let (content, mut endorsements, was_verified) = match record.status {
+				RecordStatus::Unverified(content, endorsements) => (content, endorsements, false),
+				RecordStatus::Verified(content, endorsements) => (content, endorsements, true),
+				RecordStatus::Erased(_) => return Err(Error::<T>::RecordAlreadyErased.into()),
+			};
+			ensure!(
+				endorsements.iter().all(|endorsement| endorsement.doctor != *doctor_id),
+				Error::<T>::AlreadyEndorsed
+			);
+			let mut endorsement = Endorsement {
+				doctor: doctor_id.clone(),
+				signature,
+				at: <frame_system::Pallet<T>>::block_number(),
+				deposit: Zero::zero(),
+			};
+			endorsement.deposit = Self::endorsement_deposit(&endorsement);
+			let deposit = endorsement.deposit;
+			endorsements
+				.try_push(endorsement)
+				.map_err(|_| Error::<T>::TooManyEndorsements)?;
+			T::Currency::reserve(doctor_id, deposit)?;
+			<RecordVersions<T>>::mutate(patient_id, record.id, |versions| {
+				if let Some(current) = versions.last_mut() {
+					current.endorsements = endorsements.clone();
+				}
+			});
+			Self::deposit_event(Event::RecordDepositReserved(
+				doctor_id.clone(),
+				patient_id.clone(),
+				record.id,
+				deposit,
+			));
+
+			let status = if was_verified ||
+				endorsements.len() as u32 >= Self::endorsement_threshold(record.category)
+			{
+				RecordStatus::Verified(content, endorsements)
+			} else {
+				RecordStatus::Unverified(content, endorsements)
+			};
+			Ok(Record { status, ..record })
+		}
+
+		// Make `content` the new, unendorsed version of a record.
+		fn amend(
+			author: T::AccountId,
+			patient_id: PatientAccountId<T>,
+			record_id: RecordId,
+			content: Content<T>,
+		) -> DispatchResult {
+			ensure!(Self::has_role(&patient_id, UserType::Patient), Error::<T>::AccountNotFound);
+			let record_to_be_amended =
+				Self::record(&patient_id, record_id).ok_or(Error::<T>::NonExistentRecord)?;
+			ensure!(
+				author == patient_id || Self::may_amend(&author, &record_to_be_amended),
+				Error::<T>::AmendmentNotAllowed
+			);
+			ensure!(!record_to_be_amended.is_erased(), Error::<T>::RecordAlreadyErased);
+
+			let held_payloads = Self::payload_hashes(&record_to_be_amended);
+			let version = Self::current_version(&patient_id, record_id) + 1;
+			Self::push_version(&patient_id, record_id, version, author.clone(), content.clone())?;
+			let amended_record = Record {
+				status: RecordStatus::Unverified(content, Default::default()),
+				..record_to_be_amended
+			};
+
+			// The new version may bring a payload, and a dropped version may take one away.
+			let payloads = Self::payload_hashes(&amended_record);
+			for payload_hash in payloads.difference(&held_payloads) {
+				Self::take_payload_reference(payload_hash);
+			}
+			for payload_hash in held_payloads.difference(&payloads) {
+				Self::release_payload(payload_hash);
+			}
+			Self::set_record_deposit(&author, &patient_id, &amended_record)?;
+			<PatientRecords<T>>::insert(&patient_id, record_id, amended_record);
+
+			Self::deposit_event(Event::RecordAmended(patient_id, author, record_id, version));
+			Ok(())
+		}
+
+		// Besides the patient, only whoever added the record or endorsed its current version
+		// may amend it, as long as their role still lets them add records of its category.
+		fn may_amend(author: &T::AccountId, record: &Record<T>) -> bool {
+			let involved = record.author == *author ||
+				record.endorsements().iter().any(|endorsement| endorsement.doctor == *author);
+			involved && Self::is_permitted(author, RecordAction::Add, record.category)
+		}
+
+		// Keep a new version of a record, reserving a deposit for it from its author. If
+		// `MaxRecordVersions` are kept already, the oldest one is dropped and the deposits of it
+		// and its endorsements are returned.
+		fn push_version(
+			patient_id: &PatientAccountId<T>,
+			record_id: RecordId,
+			number: VersionNumber,
+			author: T::AccountId,
+			content: Content<T>,
+		) -> DispatchResult {
+			let mut version = RecordVersion {
+				number,
+				author,
+				created_at: <frame_system::Pallet<T>>::block_number(),
+				content,
+				endorsements: Default::default(),
+				deposit: Zero::zero(),
+			};
+			version.deposit = Self::deposit_for(version.encoded_size());
+			T::Currency::reserve(&version.author, version.deposit)?;
+			let (author, deposit) = (version.author.clone(), version.deposit);
+			<RecordVersions<T>>::try_mutate(patient_id, record_id, |versions| {
+				if versions.len() as u32 >= T::MaxRecordVersions::get() && !versions.is_empty() {
+					let dropped = versions.remove(0);
+					Self::release_version(patient_id, record_id, &dropped);
+				}
+				versions.try_push(version).map_err(|_| Error::<T>::TooManyVersions)
+			})?;
+			Self::deposit_event(Event::RecordDepositReserved(
+				author,
+				patient_id.clone(),
+				record_id,
+				deposit,
+			));
+			Ok(())
+		}
+
+		fn anchor_payload(payload: &Payload<T>, media_type: MediaType<T>) -> PayloadAnchor<T> {
+			PayloadAnchor {
+				hash: T::Hashing::hash(payload),
+				size: payload.len() as u32,
+				media_type,
+			}
+		}
+
+		// Take a reference to the payload of a new record, indexing it in the offchain database.
+		fn hold_payload(anchor: &PayloadAnchor<T>, payload: &Payload<T>) {
+			Self::take_payload_reference(&anchor.hash);
+			Self::index_payload(&anchor.hash, payload);
+		}
+
+		fn take_payload_reference(payload_hash: &T::Hash) {
+			<PayloadReferences<T>>::mutate(payload_hash, |references| {
+				*references = references.saturating_add(1)
+			});
+		}
+
+		// Only nodes running with offchain indexing enabled keep the payload.
+		fn index_payload(payload_hash: &T::Hash, payload: &Payload<T>) {
+			sp_io::offchain_index::set(
+				&crate::offchain_payload_key(payload_hash.as_ref()),
+				payload,
+			);
+		}
+
+		// Drop a reference to a payload, clearing it from the offchain database if it was the
+		// last one.
+		fn release_payload(payload_hash: &T::Hash) {
+			let references = Self::payload_references(payload_hash).saturating_sub(1);
+			if references == 0 {
+				<PayloadReferences<T>>::remove(payload_hash);
+				sp_io::offchain_index::clear(&crate::offchain_payload_key(payload_hash.as_ref()));
+			} else {
+				<PayloadReferences<T>>::insert(payload_hash, references);
+			}
+		}
+
+		// The distinct payloads a record holds, in its content and kept versions.
+		pub(crate) fn payload_hashes(record: &Record<T>) -> BTreeSet<T::Hash> {
+			let versions = Self::record_versions(&record.patient, record.id);
+			record
+				.get_content()
+				.into_iter()
+				.chain(versions.iter().map(|version| &version.content))
+				.filter_map(|content| match content {
+					Content::OffChain(anchor) => Some(anchor.hash),
+					Content::OnChain(_) => None,
+				})
+				.collect()
+		}
+	}
+
+	// helper to read
+	impl<T: Config> Pallet<T> {
+		pub fn get_record_by_id(
+			patient_id: &PatientAccountId<T>,
+			record_id: u32,
+		) -> Option<Record<T>> {
+			Self::record(patient_id, record_id)
+		}
+
+		// The records of a patient, including tombstones, ordered by id.
+		pub fn records_of(patient_id: &PatientAccountId<T>) -> Vec<Record<T>> {
+			let mut records: Vec<Record<T>> =
+				<PatientRecords<T>>::iter_prefix_values(patient_id).collect();
+			records.sort_by_key(|record| record.get_id());
+			records
+		}
+
+		// The id the next record of the patient is stored under. Ids are never reused.
+		pub fn next_record_id(patient_id: &PatientAccountId<T>) -> RecordId {
+			Self::last_record_id(patient_id).saturating_add(1)
+		}
+
+		// Every known version of a record, oldest first.
+		pub fn record_history(
+			patient_id: &PatientAccountId<T>,
+			record_id: RecordId,
+		) -> Vec<RecordVersion<T>> {
+			Self::record_versions(patient_id, record_id).into_inner()
+		}
+
+		// The version a doctor has to sign to verify a record. Records start at version 1.
+		pub fn current_version(
+			patient_id: &PatientAccountId<T>,
+			record_id: RecordId,
+		) -> VersionNumber {
+			Self::record_versions(patient_id, record_id)
+				.last()
+				.map_or(1, |version| version.number)
+		}
+
+		// The payload a doctor signs to vouch for a version of a record.
+		pub fn record_signing_payload(
+			patient_id: &PatientAccountId<T>,
+			record_id: RecordId,
+			version: VersionNumber,
+			record_content: &Content<T>,
+		) -> T::Hash {
+			T::Hashing::hash_of(&(patient_id, record_id, version, record_content))
+		}
+
+		fn is_valid_signature(
+			signature: &Signature<T>,
+			doctor_id: &DoctorAccountId<T>,
+			patient_id: &PatientAccountId<T>,
+			record_id: RecordId,
+			version: VersionNumber,
+			record_content: &Content<T>,
+		) -> bool {
+			let payload =
+				Self::record_signing_payload(patient_id, record_id, version, record_content);
+			signature.verify(payload.as_ref(), doctor_id)
+		}
+
+		pub fn has_role(account: &T::AccountId, user_type: UserType) -> bool {
+			Self::account(account).map_or(false, |account| account.roles.contains(&user_type))
+		}
+
+		// Whether any of the account's roles may do `action` with records of `category`.
+		pub fn is_permitted(
+			account: &T::AccountId,
+			action: RecordAction,
+			category: RecordCategory,
+		) -> bool {
+			Self::account(account).map_or(false, |account| {
+				account
+					.roles
+					.iter()
+					.any(|user_type| Self::permissions(user_type, action).contains(&category))
+			})
+		}
+
+		pub(crate) fn category_set(categories: &[RecordCategory]) -> CategorySet {
+			let mut set = CategorySet::default();
+			for category in categories {
+				// Every category fits, see `CategorySet`.
+				let _ = set.try_insert(*category);
+			}
+			set
+		}
+
+		// Patients register themselves; every other role needs a license.
+		fn ensure_self_service(user_type: &UserType) -> DispatchResult {
+			match user_type {
+				UserType::Patient => Ok(()),
+				UserType::Doctor => Err(Error::<T>::DoctorLicenseRequired.into()),
+				_ => Err(Error::<T>::LicenseRequired.into()),
+			}
+		}
+
+		// Give the account a role, creating the account if it does not exist yet.
+		pub(crate) fn grant_role(account_id: &T::AccountId, user_type: UserType) -> DispatchResult {
+			<Accounts<T>>::try_mutate(account_id, |account| {
+				let account = account.get_or_insert_with(|| Account {
+					roles: Default::default(),
+					profile: Default::default(),
+					created_at: <frame_system::Pallet<T>>::block_number(),
+				});
+				match account.roles.try_insert(user_type) {
+					Ok(true) => Ok(()),
+					Ok(false) => Err(Error::<T>::RoleAlreadyHeld.into()),
+					Err(_) => Err(Error::<T>::TooManyRoles.into()),
+				}
+			})
+		}
+	}
+
+	// Queries behind the `MedicalRecordApi` runtime API.
+	impl<T: Config> Pallet<T> {
+		pub fn account_roles(account: &T::AccountId) -> Vec<api::Role> {
+			let roles = Self::account(account).map(|account| account.roles).unwrap_or_default();
+			roles
+				.into_iter()
+				.map(|user_type| match user_type {
+					UserType::Patient => api::Role::Patient,
+					UserType::Doctor => api::Role::Doctor,
+					UserType::Nurse => api::Role::Nurse,
+					UserType::Pharmacist => api::Role::Pharmacist,
+					UserType::Laboratory => api::Role::Laboratory,
+					UserType::Insurer => api::Role::Insurer,
+					UserType::Researcher => api::Role::Researcher,
+					UserType::EmergencyResponder => api::Role::EmergencyResponder,
+				})
+				.collect()
+		}
+
+		pub fn record_info(
+			patient_id: &PatientAccountId<T>,
+			record_id: RecordId,
+		) -> Option<RecordInfo<T>> {
+			Self::record(patient_id, record_id).map(Self::to_record_info)
+		}
+
+		pub fn record_infos_of(patient_id: &PatientAccountId<T>) -> Vec<RecordInfo<T>> {
+			Self::records_of(patient_id).into_iter().map(Self::to_record_info).collect()
+		}
+
+		// Only the records on the page are decoded.
+		pub fn record_infos_page(
+			patient_id: &PatientAccountId<T>,
+			offset: u32,
+			limit: u32,
+		) -> api::Page<RecordInfo<T>> {
+			let mut record_ids: Vec<RecordId> =
+				<PatientRecords<T>>::iter_key_prefix(patient_id).collect();
+			record_ids.sort_unstable();
+			let items = record_ids
+				.iter()
+				.skip(offset as usize)
+				.take(limit as usize)
+				.filter_map(|record_id| Self::record_info(patient_id, *record_id))
+				.collect();
+			api::Page { items, total: record_ids.len() as u32 }
+		}
+
+		pub fn record_infos_matching(
+			patient_id: &PatientAccountId<T>,
+			filter: api::RecordFilter<T::BlockNumber>,
+		) -> Vec<RecordInfo<T>> {
+			Self::record_infos_of(patient_id)
+				.into_iter()
+				.filter(|record| filter.matches(record))
+				.collect()
+		}
+
+		pub fn audit_infos_of(patient_id: &PatientAccountId<T>) -> Vec<AuditInfo<T>> {
+			Self::audit_log(patient_id)
+				.into_iter()
+				.map(|entry| api::AuditEntry {
+					actor: entry.actor,
+					action: match entry.action {
+						AuditAction::Grant { recipient } => api::AuditAction::Grant { recipient },
+						AuditAction::Revoke { recipient } => api::AuditAction::Revoke { recipient },
+						AuditAction::Verify => api::AuditAction::Verify,
+						AuditAction::BreakGlass => api::AuditAction::BreakGlass,
+						AuditAction::SetEmergencyProfile => api::AuditAction::SetEmergencyProfile,
+						AuditAction::ClearEmergencyProfile =>
+							api::AuditAction::ClearEmergencyProfile,
+					},
+					record_id: entry.record_id,
+					path: match entry.path {
+						AccessPath::Direct => api::AccessPath::Direct,
+						AccessPath::Guardian => api::AccessPath::Guardian,
+						AccessPath::Emergency => api::AccessPath::Emergency,
+						AccessPath::Policy => api::AccessPath::Policy,
+						AccessPath::Upgrade => api::AccessPath::Upgrade,
+					},
+					purpose: entry.purpose.into_inner(),
+					at: entry.at,
+				})
+				.collect()
+		}
+
+		fn to_record_info(record: Record<T>) -> RecordInfo<T> {
+			let status = match &record.status {
+				RecordStatus::Unverified(_, _) => api::RecordStatus::Unverified,
+				RecordStatus::Verified(_, _) => api::RecordStatus::Verified,
+				RecordStatus::Erased(at) => api::RecordStatus::Erased { at: *at },
+			};
+			let endorsements = record
+				.endorsements()
+				.iter()
+				.map(|endorsement| api::Endorsement {
+					doctor: endorsement.doctor.clone(),
+					at: endorsement.at,
+				})
+				.collect();
+			let category = match record.category {
+				RecordCategory::Note => api::RecordCategory::Note,
+				RecordCategory::Diagnosis => api::RecordCategory::Diagnosis,
+				RecordCategory::Prescription => api::RecordCategory::Prescription,
+				RecordCategory::Lab => api::RecordCategory::Lab,
+				RecordCategory::Imaging => api::RecordCategory::Imaging,
+				RecordCategory::Immunization => api::RecordCategory::Immunization,
+				RecordCategory::Allergy => api::RecordCategory::Allergy,
+			};
+			let content = record.get_content().map(|content| match content {
+				Content::OnChain(content) => api::RecordContent::OnChain(content.to_vec()),
+				Content::OffChain(anchor) => api::RecordContent::OffChain {
+					hash: anchor.hash,
+					size: anchor.size,
+					media_type: anchor.media_type.to_vec(),
+				},
+			});
+			let version = Self::current_version(&record.patient, record.id);
+			RecordInfo {
+				id: record.id,
+				patient: record.patient,
+				category,
+				title: record.title.map(BoundedVec::into_inner),
+				created_at: record.created_at,
+				created_on: record.created_on.unique_saturated_into(),
+				status,
+				endorsements,
+				content,
+				version,
+			}
+		}
+	}
+
+	impl<T: Config> RecordProvider<T::AccountId> for Pallet<T> {
+		fn is_registered(account: &T::AccountId) -> bool {
+			<Accounts<T>>::contains_key(account)
+		}
+
+		fn is_record_owner(owner: &T::AccountId, record_id: RecordId) -> bool {
+			Self::get_record_by_id(owner, record_id).map_or(false, |record| !record.is_erased())
+		}
+
+		fn log_access(
+			patient: &T::AccountId,
+			actor: &T::AccountId,
+			action: AuditAction<T::AccountId>,
+			record_id: Option<RecordId>,
+			path: AccessPath,
+		) {
+			Self::audit(patient, actor, action, record_id, path, Default::default())
+		}
+
+		fn may_receive(
+			recipient: &T::AccountId,
+			owner: &T::AccountId,
+			record_id: RecordId,
+		) -> bool {
+			Self::get_record_by_id(owner, record_id).map_or(false, |record| {
+				Self::is_permitted(recipient, RecordAction::Receive, record.category)
+			})
 		}
 	}
 }
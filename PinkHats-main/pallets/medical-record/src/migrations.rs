@@ -0,0 +1,219 @@
+//! Storage migrations of the medical record pallet.
+
+use crate::*;
+use frame_support::{
+	pallet_prelude::*,
+	storage_alias,
+	traits::{OnRuntimeUpgrade, StorageVersion},
+};
+use sp_std::{vec, vec::Vec};
+
+/// The storage layout the pallet was first deployed with.
+pub mod v0 {
+	use super::*;
+
+	/// A record as it was first stored. Contents and signatures were plain bounded bytes; they
+	/// are decoded as vectors here, which encode the same way.
+	#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, TypeInfo)]
+	#[scale_info(skip_type_params(T))]
+	pub enum Record<T: Config> {
+		VerifiedRecord(
+			RecordId,
+			<T as frame_system::Config>::AccountId,
+			<T as frame_system::Config>::AccountId,
+			Vec<u8>,
+			Vec<u8>,
+		),
+		UnverifiedRecord(RecordId, <T as frame_system::Config>::AccountId, Vec<u8>),
+	}
+
+	impl<T: Config> Record<T> {
+		pub fn get_id(&self) -> RecordId {
+			match self {
+				Record::VerifiedRecord(id, _, _, _, _) | Record::UnverifiedRecord(id, _, _) => *id,
+			}
+		}
+	}
+
+	/// Every account had a key per role it held. The records of a patient were kept in one
+	/// vector under their patient key; the vectors of other roles stayed empty.
+	#[storage_alias]
+	pub type Records<T: Config> = StorageDoubleMap<
+		Pallet<T>,
+		Blake2_128Concat,
+		<T as frame_system::Config>::AccountId,
+		Blake2_128Concat,
+		UserType,
+		Vec<Record<T>>,
+	>;
+}
+
+/// Moves the accounts and records of the first layout into `Accounts` and `PatientRecords`.
+///
+/// The roles of an account are the keys it had in `v0::Records`. Accounts are dated with the
+/// block of the upgrade, since the block they were created at was never kept. Doctors
+/// registered themselves without a license, so instead of the doctor role they get a pending
+/// application in `DoctorApplications`, which the licensing authority approves or rejects. Every
+/// record becomes a note without a title, dated with block 0 and time 0, and keeps its id, so
+/// sharings keep pointing at the same records. `LastRecordId` and `RecordCount` are initialised
+/// from the records, and the permissions matrix is filled with `default_permissions`.
+///
+/// Every record becomes unverified. Signatures of the first layout were never checked, and were
+/// not made over the payload doctors sign now, so they are dropped rather than turned into
+/// endorsements. Doctors have to endorse migrated records again. Each record gets its content as
+/// version 1 in `RecordVersions`, which keeps the endorsements and their deposits. No deposit
+/// was reserved for migrated records or their versions.
+pub mod v1 {
+	use super::*;
+	use sp_runtime::traits::Zero;
+
+	// The author and content of a record of the first layout, or `None` if its content no
+	// longer fits. `MaxRecordContentLength` bounded contents from the start, so they always do.
+	// Patients added unverified records, doctors verified ones.
+	fn translate_record<T: Config>(record: v0::Record<T>) -> Option<(T::AccountId, Content<T>)> {
+		let (author, content) = match record {
+			v0::Record::UnverifiedRecord(_, patient, content) => (patient, content),
+			v0::Record::VerifiedRecord(_, _, doctor, content, _) => (doctor, content),
+		};
+		Some((author, Content::OnChain(content.try_into().ok()?)))
+	}
+
+	pub struct MigrateToV1<T>(sp_std::marker::PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() >= 1 {
+				return T::DbWeight::get().reads(1)
+			}
+
+			let (mut reads, mut writes) = (1, 1);
+			for (account_id, user_type, records) in v0::Records::<T>::drain() {
+				reads += 2;
+				writes += 2;
+				if user_type == UserType::Doctor {
+					let now = <frame_system::Pallet<T>>::block_number();
+					<DoctorApplications<T>>::insert(&account_id, now);
+				} else {
+					// Accounts held each role only once, so this cannot fail.
+					let _ = Pallet::<T>::grant_role(&account_id, user_type);
+				}
+
+				for record in records {
+					let id = record.get_id();
+					let (author, content) = match translate_record::<T>(record) {
+						Some(translated) => translated,
+						None => continue,
+					};
+					writes += 4;
+					let version = RecordVersion {
+						number: 1,
+						author: author.clone(),
+						created_at: Zero::zero(),
+						content: content.clone(),
+						endorsements: BoundedVec::default(),
+						deposit: Zero::zero(),
+					};
+					<RecordVersions<T>>::insert(
+						&account_id,
+						id,
+						BoundedVec::<_, T::MaxRecordVersions>::truncate_from(vec![version]),
+					);
+					let record = Record {
+						id,
+						patient: account_id.clone(),
+						author,
+						category: RecordCategory::Note,
+						title: None,
+						created_at: Zero::zero(),
+						created_on: Zero::zero(),
+						status: RecordStatus::Unverified(content, BoundedVec::default()),
+					};
+					<PatientRecords<T>>::insert(&account_id, id, record);
+					<RecordCount<T>>::mutate(&account_id, |count| *count += 1);
+					<LastRecordId<T>>::mutate(&account_id, |last| *last = (*last).max(id));
+				}
+			}
+
+			let permissions = default_permissions();
+			writes += permissions.len() as u64;
+			for (user_type, action, categories) in permissions {
+				<Permissions<T>>::insert(user_type, action, Pallet::<T>::category_set(&categories));
+			}
+			StorageVersion::new(1).put::<Pallet<T>>();
+
+			T::DbWeight::get().reads_writes(reads, writes)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+			ensure!(
+				Pallet::<T>::on_chain_storage_version() == 0,
+				"can only upgrade from version 0"
+			);
+			let (roles, doctors, records) = v0::Records::<T>::iter().fold(
+				(0u32, 0u32, 0u32),
+				|(roles, doctors, records), (_, user_type, account_records)| {
+					let is_doctor = user_type == UserType::Doctor;
+					(
+						roles + !is_doctor as u32,
+						doctors + is_doctor as u32,
+						records + account_records.len() as u32,
+					)
+				},
+			);
+			Ok((roles, doctors, records).encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), &'static str> {
+			ensure!(Pallet::<T>::on_chain_storage_version() == 1, "must upgrade to version 1");
+			let (roles, doctors, records): (u32, u32, u32) =
+				Decode::decode(&mut &state[..]).map_err(|_| "pre_upgrade state must decode")?;
+
+			ensure!(v0::Records::<T>::iter().next().is_none(), "old records must be removed");
+			ensure!(
+				<Accounts<T>>::iter_values()
+					.map(|account| account.roles.len() as u32)
+					.sum::<u32>() == roles,
+				"every role but doctor must be kept"
+			);
+			ensure!(
+				<DoctorApplications<T>>::iter_keys().count() as u32 == doctors,
+				"every doctor must be left with a pending application"
+			);
+			ensure!(
+				<PatientRecords<T>>::iter().count() as u32 == records,
+				"every record must be kept"
+			);
+			ensure!(
+				<RecordCount<T>>::iter_values().sum::<u32>() == records,
+				"every record must be counted"
+			);
+			for (patient_id, record_id, record) in <PatientRecords<T>>::iter() {
+				ensure!(
+					record.id == record_id && record.patient == patient_id,
+					"records must be keyed by their patient and id"
+				);
+				ensure!(
+					record_id <= Pallet::<T>::last_record_id(&patient_id),
+					"record ids must not be handed out again"
+				);
+				ensure!(
+					Pallet::<T>::record_versions(&patient_id, record_id).len() == 1,
+					"records must be kept as their first version"
+				);
+			}
+			ensure!(
+				<PatientRecords<T>>::iter_values()
+					.all(|record| !record.is_verified() && record.endorsements().is_empty()),
+				"migrated records must be unverified and unendorsed"
+			);
+			ensure!(
+				Pallet::<T>::permissions(UserType::Doctor, RecordAction::Verify)
+					.contains(&RecordCategory::Note),
+				"doctors must be allowed to verify records"
+			);
+			Ok(())
+		}
+	}
+}
@@ -1,17 +1,31 @@
 use crate::{self as pallet_medical_record, UserType};
+use codec::{Decode, Encode, MaxEncodedLen};
 use frame_support::{
 	parameter_types,
 	traits::{ConstU16, ConstU64, GenesisBuild},
 };
 use frame_system as system;
+use scale_info::TypeInfo;
 use sp_core::H256;
 use sp_runtime::{
-	testing::Header,
-	traits::{BlakeTwo256, IdentityLookup},
+	testing::{Header, UintAuthorityId},
+	traits::{BlakeTwo256, IdentityLookup, Lazy, Verify},
 };
 
 pub type AccountId = u64;
 
+// A signature that is valid for exactly one signer and one signed hash.
+#[derive(Decode, Encode, MaxEncodedLen, Clone, PartialEq, Eq, Debug, TypeInfo)]
+pub struct MockSignature(pub AccountId, pub H256);
+
+impl Verify for MockSignature {
+	type Signer = UintAuthorityId;
+
+	fn verify<L: Lazy<[u8]>>(&self, mut msg: L, signer: &AccountId) -> bool {
+		self.0 == *signer && msg.get() == self.1.as_bytes()
+	}
+}
+
 type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
 type Block = frame_system::mocking::MockBlock<Test>;
 
@@ -23,6 +37,8 @@ frame_support::construct_runtime!(
 		UncheckedExtrinsic = UncheckedExtrinsic,
 	{
 		System: frame_system,
+		Balances: pallet_balances,
+		Timestamp: pallet_timestamp,
 		MedicalRecord: pallet_medical_record,
 		RecordSharing: pallet_record_sharing,
 	}
@@ -46,7 +62,7 @@ impl system::Config for Test {
 	type BlockHashCount = ConstU64<250>;
 	type Version = ();
 	type PalletInfo = PalletInfo;
-	type AccountData = ();
+	type AccountData = pallet_balances::AccountData<u64>;
 	type OnNewAccount = ();
 	type OnKilledAccount = ();
 	type SystemWeightInfo = ();
@@ -55,25 +71,147 @@ impl system::Config for Test {
 	type MaxConsumers = frame_support::traits::ConstU32<16>;
 }
 
+impl pallet_balances::Config for Test {
+	type Balance = u64;
+	type DustRemoval = ();
+	type RuntimeEvent = RuntimeEvent;
+	type ExistentialDeposit = ConstU64<1>;
+	type AccountStore = System;
+	type WeightInfo = ();
+	type MaxLocks = ();
+	type MaxReserves = ();
+	type ReserveIdentifier = [u8; 8];
+}
+
+impl pallet_timestamp::Config for Test {
+	type Moment = u64;
+	type OnTimestampSet = ();
+	type MinimumPeriod = ConstU64<1>;
+	type WeightInfo = ();
+}
+
 impl pallet_medical_record::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
 	type MaxRecordContentLength = MockMaxRecordContentLength;
-	type SignatureLength = MockSignatureLength;
+	type Signature = MockSignature;
+	type Signer = UintAuthorityId;
 	type MaxRecordLength = MockMaxRecordLength;
+	type LicensingOrigin = system::EnsureRoot<AccountId>;
+	type PermissionsOrigin = system::EnsureRoot<AccountId>;
+	type RecordSharing = RecordSharing;
+	type MaxPayloadLength = MockMaxPayloadLength;
+	type MaxMediaTypeLength = MockMaxMediaTypeLength;
+	type MaxRecordVersions = MockMaxRecordVersions;
+	type MaxEndorsements = MockMaxEndorsements;
+	type MaxProfileLength = MockMaxProfileLength;
+	type MaxTitleLength = MockMaxTitleLength;
+	type MaxPurposeLength = MockMaxPurposeLength;
+	type MaxAuditEntries = MockMaxAuditEntries;
+	type MaxRequestedRecords = MockMaxRequestedRecords;
+	type AccessRequestLifetime = MockAccessRequestLifetime;
+	type MaxRequestExpiriesPerBlock = MockMaxRequestExpiriesPerBlock;
+	type AccessRequestDeposit = MockAccessRequestDeposit;
+	type MaxOpenRequests = MockMaxOpenRequests;
+	type Currency = Balances;
+	type DepositPerByte = MockDepositPerByte;
+	type WeightInfo = ();
+	#[cfg(feature = "runtime-benchmarks")]
+	type BenchmarkHelper = MockBenchmarkHelper;
+}
+
+#[cfg(feature = "runtime-benchmarks")]
+pub struct MockBenchmarkHelper;
+
+#[cfg(feature = "runtime-benchmarks")]
+impl pallet_medical_record::BenchmarkHelper<Test> for MockBenchmarkHelper {
+	fn signer() -> AccountId {
+		1_000
+	}
+
+	fn sign(signer: &AccountId, message: &[u8]) -> MockSignature {
+		MockSignature(*signer, H256::from_slice(message))
+	}
+
+	fn sealed_key(
+		recipient: &AccountId,
+	) -> (pallet_record_sharing::EncryptedKey<Test>, pallet_record_sharing::KeyId) {
+		RecordSharing::register_encryption_key(RuntimeOrigin::signed(*recipient), [1; 32])
+			.expect("any account can register a key");
+		let sealed_key = pallet_record_sharing::SealedEnvelope {
+			version: pallet_record_sharing::ENVELOPE_VERSION,
+			algorithm: pallet_record_sharing::ALGORITHM_X25519_XSALSA20_POLY1305,
+			ephemeral_public_key: [2; 32],
+			nonce: [3; 24],
+			ciphertext: Default::default(),
+		};
+		let key_id = RecordSharing::current_encryption_key(recipient)
+			.expect("the key was just registered")
+			.key_id;
+		(sealed_key, key_id)
+	}
 }
 
 parameter_types! {
 	pub const MockMaxRecordContentLength: u32 = 1;
-	pub const MockSignatureLength: u32 = 3;
 	pub const MockMaxRecordLength: u32 = 3;
-	pub const MockMaxKeyLength: u32 = 3;
+	pub const MockMaxPayloadLength: u32 = 16;
+	pub const MockMaxMediaTypeLength: u32 = 16;
+	pub const MockMaxRecordVersions: u32 = 3;
+	pub const MockMaxEndorsements: u32 = 2;
+	pub const MockMaxProfileLength: u32 = 4;
+	pub const MockMaxTitleLength: u32 = 4;
+	pub const MockMaxPurposeLength: u32 = 8;
+	pub const MockMaxAuditEntries: u32 = 4;
+	pub const MockMaxRequestedRecords: u32 = 2;
+	pub const MockAccessRequestLifetime: u64 = 10;
+	pub const MockMaxRequestExpiriesPerBlock: u32 = 2;
+	pub const MockAccessRequestDeposit: u64 = 100;
+	pub const MockMaxOpenRequests: u32 = 2;
+	pub const MockMaxSharedRecords: u32 = 3;
+	pub const MockMaxCiphertextLength: u32 = 48;
+	pub const MockMaxExpiriesPerBlock: u32 = 3;
+	pub const MockMaxKeyHistory: u32 = 2;
+	pub const MockDepositPerByte: u64 = 1;
 }
 
 impl pallet_record_sharing::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
-	type MaxKeyLength = MockMaxKeyLength;
+	type MaxSharedRecords = MockMaxSharedRecords;
+	type MaxCiphertextLength = MockMaxCiphertextLength;
+	type MaxExpiriesPerBlock = MockMaxExpiriesPerBlock;
+	type Records = MedicalRecord;
+	type MaxKeyHistory = MockMaxKeyHistory;
+	type Currency = Balances;
+	type DepositPerByte = MockDepositPerByte;
+	type WeightInfo = ();
+	#[cfg(feature = "runtime-benchmarks")]
+	type BenchmarkHelper = MockSharingBenchmarkHelper;
 }
 
+#[cfg(feature = "runtime-benchmarks")]
+pub struct MockSharingBenchmarkHelper;
+
+#[cfg(feature = "runtime-benchmarks")]
+impl pallet_record_sharing::BenchmarkHelper<AccountId> for MockSharingBenchmarkHelper {
+	fn register(account: &AccountId) {
+		MedicalRecord::create_account(RuntimeOrigin::signed(*account), UserType::Patient)
+			.expect("the account is new");
+	}
+
+	fn add_record(owner: &AccountId) -> pallet_record_sharing::RecordId {
+		MedicalRecord::patient_adds_record(
+			RuntimeOrigin::signed(*owner),
+			pallet_medical_record::RecordCategory::Note,
+			None,
+			Default::default(),
+		)
+		.expect("the patient has room for a record");
+		MedicalRecord::last_record_id(owner)
+	}
+}
+
+pub const INITIAL_BALANCE: u64 = 1_000_000;
+
 #[derive(Default)]
 pub struct ExternalitiesBuilder {
 	accounts: Vec<(AccountId, UserType)>,
@@ -90,9 +228,18 @@ impl ExternalitiesBuilder {
 			.build_storage::<Test>()
 			.expect("Frame system builds valid default genesis config");
 
-		pallet_medical_record::GenesisConfig::<Test> { accounts: self.accounts }
-			.assimilate_storage(&mut t)
-			.expect("Can build genesis for medical_record pallet");
+		pallet_balances::GenesisConfig::<Test> {
+			balances: (1..=3).map(|account_id| (account_id, INITIAL_BALANCE)).collect(),
+		}
+		.assimilate_storage(&mut t)
+		.expect("Can build genesis for balances pallet");
+
+		pallet_medical_record::GenesisConfig::<Test> {
+			accounts: self.accounts,
+			..Default::default()
+		}
+		.assimilate_storage(&mut t)
+		.expect("Can build genesis for medical_record pallet");
 
 		sp_io::TestExternalities::from(t)
 	}
@@ -1,7 +1,23 @@
-use crate::{mock::*, Error, UserType};
-use frame_support::{assert_noop, assert_ok, bounded_vec, BoundedVec};
+use crate::{
+	migrations::v1::MigrateToV1, mock::*, offchain_payload_key, Content, Error, GuardianScope,
+	Permissions, RecordAction, RecordCategory, RecordStatus, RequestedRecords, UserType,
+};
+use codec::{Compact, Encode};
+use frame_support::{
+	assert_noop, assert_ok, bounded_vec,
+	storage::{storage_prefix, unhashed},
+	traits::{OnIdle, OnInitialize, OnRuntimeUpgrade, StorageVersion},
+	weights::Weight,
+	Blake2_128Concat, BoundedVec, StorageHasher,
+};
+use pallet_medical_record_runtime_api as api;
+use pallet_record_sharing::{
+	AccessPath, AuditAction, SealedEnvelope, ValidityWindow, ALGORITHM_X25519_XSALSA20_POLY1305,
+	ENVELOPE_VERSION,
+};
 
 use sp_core::Get;
+use sp_runtime::traits::{BadOrigin, BlakeTwo256, Hash};
 
 #[test]
 fn user_can_create_account() {
@@ -9,8 +25,7 @@ fn user_can_create_account() {
 	ExternalitiesBuilder::default().build().execute_with(|| {
 		assert_ok!(MedicalRecord::create_account(patient.clone(), UserType::Patient));
 
-		let account_created =
-			MedicalRecord::records(patient_account_id, UserType::Patient).is_some();
+		let account_created = MedicalRecord::has_role(&patient_account_id, UserType::Patient);
 		assert!(account_created, "failed to create an account");
 
 		assert_noop!(
@@ -20,6 +35,265 @@ fn user_can_create_account() {
 	});
 }
 
+#[test]
+fn account_can_hold_several_roles() {
+	let (doctor_account_id, doctor) = generate_account(2);
+	ExternalitiesBuilder::default()
+		.with_accounts(vec![(doctor_account_id, UserType::Doctor)])
+		.build()
+		.execute_with(|| {
+			assert_noop!(
+				MedicalRecord::create_account(doctor.clone(), UserType::Patient),
+				Error::<Test>::AccountAlreadyExist
+			);
+			assert_ok!(MedicalRecord::add_role(doctor.clone(), UserType::Patient));
+			assert_noop!(
+				MedicalRecord::add_role(doctor.clone(), UserType::Patient),
+				Error::<Test>::RoleAlreadyHeld
+			);
+			assert!(MedicalRecord::has_role(&doctor_account_id, UserType::Doctor));
+			assert!(MedicalRecord::has_role(&doctor_account_id, UserType::Patient));
+
+			assert_ok!(MedicalRecord::set_profile(doctor.clone(), bounded_vec![1, 2]));
+			let account = MedicalRecord::account(doctor_account_id).unwrap();
+			assert_eq!(account.profile.into_inner(), vec![1, 2]);
+			assert_eq!(account.created_at, 0);
+
+			// A patient can only leave once their records are erased
+			assert_ok!(MedicalRecord::patient_adds_record(
+				doctor.clone(),
+				RecordCategory::Note,
+				None,
+				bounded_vec![]
+			));
+			assert_noop!(
+				MedicalRecord::remove_role(doctor.clone(), UserType::Patient),
+				Error::<Test>::RecordsNotErased
+			);
+			assert_ok!(MedicalRecord::erase_record(doctor.clone(), 1, 0));
+			assert_ok!(MedicalRecord::remove_role(doctor.clone(), UserType::Patient));
+			assert!(!MedicalRecord::has_role(&doctor_account_id, UserType::Patient));
+			assert_noop!(
+				MedicalRecord::remove_role(doctor.clone(), UserType::Patient),
+				Error::<Test>::RoleNotHeld
+			);
+			assert_noop!(
+				MedicalRecord::remove_role(doctor, UserType::Doctor),
+				Error::<Test>::LastRoleCannotBeRemoved
+			);
+		});
+}
+
+#[test]
+fn permissions_matrix_decides_who_may_act_on_records() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (nurse_account_id, nurse) = generate_account(2);
+	let (pharmacist_account_id, pharmacist) = generate_account(3);
+	ExternalitiesBuilder::default()
+		.with_accounts(vec![(patient_account_id, UserType::Patient)])
+		.build()
+		.execute_with(|| {
+			assert_noop!(
+				MedicalRecord::add_role(patient.clone(), UserType::Nurse),
+				Error::<Test>::LicenseRequired
+			);
+			assert_ok!(MedicalRecord::grant_license(
+				RuntimeOrigin::root(),
+				nurse_account_id,
+				UserType::Nurse
+			));
+			assert_ok!(MedicalRecord::grant_license(
+				RuntimeOrigin::root(),
+				pharmacist_account_id,
+				UserType::Pharmacist
+			));
+			assert_ok!(MedicalRecord::patient_adds_record(
+				patient.clone(),
+				RecordCategory::Note,
+				None,
+				bounded_vec![]
+			));
+
+			// Nurses may not verify records until governance allows it
+			let signature = sign(nurse_account_id, patient_account_id, 1);
+			assert_noop!(
+				MedicalRecord::doctor_verifies_record(
+					nurse.clone(),
+					patient_account_id,
+					1,
+					signature.clone()
+				),
+				Error::<Test>::NotPermitted
+			);
+			let notes = MedicalRecord::category_set(&[RecordCategory::Note]);
+			assert_noop!(
+				MedicalRecord::set_permissions(
+					nurse.clone(),
+					UserType::Nurse,
+					RecordAction::Verify,
+					notes.clone()
+				),
+				BadOrigin
+			);
+			assert_ok!(MedicalRecord::set_permissions(
+				RuntimeOrigin::root(),
+				UserType::Nurse,
+				RecordAction::Verify,
+				notes
+			));
+			assert_ok!(MedicalRecord::doctor_verifies_record(
+				nurse,
+				patient_account_id,
+				1,
+				signature
+			));
+
+			// Pharmacists only receive prescriptions
+			assert_ok!(RecordSharing::register_encryption_key(pharmacist, [3; 32]));
+			assert_noop!(
+				MedicalRecord::share_record_with(
+					patient,
+					pharmacist_account_id,
+					sealed_key(),
+					1,
+					1,
+					None
+				),
+				pallet_record_sharing::Error::<Test>::RecipientNotPermitted
+			);
+		});
+}
+
+#[test]
+fn licenses_can_be_revoked() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (doctor_account_id, doctor) = generate_account(2);
+	let (nurse_account_id, _) = generate_account(3);
+	ExternalitiesBuilder::default()
+		.with_accounts(vec![
+			(patient_account_id, UserType::Patient),
+			(doctor_account_id, UserType::Doctor),
+		])
+		.build()
+		.execute_with(|| {
+			assert_ok!(MedicalRecord::add_role(doctor.clone(), UserType::Patient));
+			assert_ok!(MedicalRecord::grant_license(
+				RuntimeOrigin::root(),
+				nurse_account_id,
+				UserType::Nurse
+			));
+
+			assert_noop!(
+				MedicalRecord::revoke_license(patient, doctor_account_id, UserType::Doctor),
+				BadOrigin
+			);
+			// Patients are not licensed
+			assert_noop!(
+				MedicalRecord::revoke_license(
+					RuntimeOrigin::root(),
+					patient_account_id,
+					UserType::Patient
+				),
+				Error::<Test>::InvalidArgument
+			);
+			assert_noop!(
+				MedicalRecord::revoke_license(
+					RuntimeOrigin::root(),
+					patient_account_id,
+					UserType::Doctor
+				),
+				Error::<Test>::RoleNotHeld
+			);
+
+			// A struck off doctor keeps being a patient
+			assert_ok!(MedicalRecord::revoke_license(
+				RuntimeOrigin::root(),
+				doctor_account_id,
+				UserType::Doctor
+			));
+			System::assert_last_event(
+				crate::Event::RoleRemoved(doctor_account_id, UserType::Doctor).into(),
+			);
+			assert!(!MedicalRecord::has_role(&doctor_account_id, UserType::Doctor));
+			assert!(MedicalRecord::has_role(&doctor_account_id, UserType::Patient));
+			assert_noop!(
+				MedicalRecord::doctor_adds_record(
+					doctor,
+					patient_account_id,
+					RecordCategory::Note,
+					None,
+					bounded_vec![],
+					sign(doctor_account_id, patient_account_id, 1)
+				),
+				Error::<Test>::NotPermitted
+			);
+
+			// An account without any other role is removed
+			assert_ok!(MedicalRecord::revoke_license(
+				RuntimeOrigin::root(),
+				nurse_account_id,
+				UserType::Nurse
+			));
+			assert!(MedicalRecord::account(nurse_account_id).is_none());
+			assert_noop!(
+				MedicalRecord::revoke_license(
+					RuntimeOrigin::root(),
+					nurse_account_id,
+					UserType::Nurse
+				),
+				Error::<Test>::AccountNotFound
+			);
+		});
+}
+
+#[test]
+fn doctor_needs_a_license() {
+	let (doctor_account_id, doctor) = generate_account(2);
+	ExternalitiesBuilder::default().build().execute_with(|| {
+		assert_noop!(
+			MedicalRecord::create_account(doctor.clone(), UserType::Doctor),
+			Error::<Test>::DoctorLicenseRequired
+		);
+
+		assert_ok!(MedicalRecord::apply_for_doctor_license(doctor.clone()));
+		assert!(MedicalRecord::doctor_applications(doctor_account_id).is_some());
+		assert_noop!(
+			MedicalRecord::apply_for_doctor_license(doctor.clone()),
+			Error::<Test>::ApplicationAlreadyPending
+		);
+
+		// Only the licensing authority can decide on an application
+		assert_noop!(
+			MedicalRecord::approve_doctor_license(doctor.clone(), doctor_account_id),
+			BadOrigin
+		);
+		assert_ok!(MedicalRecord::approve_doctor_license(RuntimeOrigin::root(), doctor_account_id));
+		assert!(MedicalRecord::has_role(&doctor_account_id, UserType::Doctor));
+		assert!(MedicalRecord::doctor_applications(doctor_account_id).is_none());
+
+		assert_noop!(
+			MedicalRecord::apply_for_doctor_license(doctor),
+			Error::<Test>::AccountAlreadyExist
+		);
+	});
+}
+
+#[test]
+fn licensing_authority_can_reject_doctor() {
+	let (doctor_account_id, doctor) = generate_account(2);
+	ExternalitiesBuilder::default().build().execute_with(|| {
+		assert_noop!(
+			MedicalRecord::reject_doctor_license(RuntimeOrigin::root(), doctor_account_id),
+			Error::<Test>::ApplicationNotFound
+		);
+
+		assert_ok!(MedicalRecord::apply_for_doctor_license(doctor));
+		assert_ok!(MedicalRecord::reject_doctor_license(RuntimeOrigin::root(), doctor_account_id));
+		assert!(!MedicalRecord::has_role(&doctor_account_id, UserType::Doctor));
+		assert!(MedicalRecord::doctor_applications(doctor_account_id).is_none());
+	});
+}
+
 #[test]
 fn patient_can_add_record() {
 	let (patient_account_id, patient) = generate_account(1);
@@ -31,18 +305,60 @@ fn patient_can_add_record() {
 			for _ in 0..max_record_len {
 				assert_ok!(MedicalRecord::patient_adds_record(
 					patient.clone(),
+					RecordCategory::Note,
+					None,
 					BoundedVec::with_max_capacity()
 				));
 			}
 
-			let records = MedicalRecord::records(patient_account_id, UserType::Patient)
-				.expect("The record should exist");
+			let records = MedicalRecord::records_of(&patient_account_id);
 			assert_eq!(records.len(), max_record_len);
 			assert_eq!(records.into_iter().filter(|r| !r.is_verified()).count(), max_record_len);
 
 			assert_noop!(
 				MedicalRecord::patient_adds_record(
 					patient.clone(),
+					RecordCategory::Note,
+					None,
+					BoundedVec::with_max_capacity()
+				),
+				Error::<Test>::ExceedsMaxRecordLength
+			);
+		});
+}
+
+#[test]
+fn erasing_a_record_makes_room_for_another() {
+	let (patient_account_id, patient) = generate_account(1);
+	ExternalitiesBuilder::default()
+		.with_accounts(vec![(patient_account_id, UserType::Patient)])
+		.build()
+		.execute_with(|| {
+			let max_record_len = <MockMaxRecordLength as Get<u32>>::get();
+			for _ in 0..max_record_len {
+				assert_ok!(MedicalRecord::patient_adds_record(
+					patient.clone(),
+					RecordCategory::Note,
+					None,
+					BoundedVec::with_max_capacity()
+				));
+			}
+			assert_ok!(MedicalRecord::erase_record(patient.clone(), 2, 0));
+			assert_eq!(MedicalRecord::record_count(patient_account_id), max_record_len - 1);
+
+			assert_ok!(MedicalRecord::patient_adds_record(
+				patient.clone(),
+				RecordCategory::Note,
+				None,
+				BoundedVec::with_max_capacity()
+			));
+			// The erased record's id is not handed out again.
+			assert!(MedicalRecord::record(patient_account_id, max_record_len + 1).is_some());
+			assert_noop!(
+				MedicalRecord::patient_adds_record(
+					patient.clone(),
+					RecordCategory::Note,
+					None,
 					BoundedVec::with_max_capacity()
 				),
 				Error::<Test>::ExceedsMaxRecordLength
@@ -62,17 +378,18 @@ fn doctor_can_add_record_for_patient() {
 		.build()
 		.execute_with(|| {
 			let max_record_len = <MockMaxRecordLength as Get<u32>>::get() as usize;
-			for _ in 0..max_record_len {
+			for record_id in 1..=max_record_len as u32 {
 				assert_ok!(MedicalRecord::doctor_adds_record(
 					doctor.clone(),
 					patient_account_id,
+					RecordCategory::Note,
+					None,
 					BoundedVec::with_max_capacity(),
-					BoundedVec::with_max_capacity(),
+					sign(doctor_account_id, patient_account_id, record_id)
 				));
 			}
 
-			let patient_records = MedicalRecord::records(patient_account_id, UserType::Patient)
-				.expect("the record should exist");
+			let patient_records = MedicalRecord::records_of(&patient_account_id);
 			assert_eq!(patient_records.len(), max_record_len);
 			assert_eq!(
 				patient_records.into_iter().filter(|r| r.is_verified()).count(),
@@ -82,6 +399,8 @@ fn doctor_can_add_record_for_patient() {
 			assert_noop!(
 				MedicalRecord::patient_adds_record(
 					patient.clone(),
+					RecordCategory::Note,
+					None,
 					BoundedVec::with_max_capacity()
 				),
 				Error::<Test>::ExceedsMaxRecordLength
@@ -89,6 +408,75 @@ fn doctor_can_add_record_for_patient() {
 		});
 }
 
+#[test]
+fn nurse_can_add_unverified_note_for_patient() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (nurse_account_id, nurse) = generate_account(2);
+	let (doctor_account_id, doctor) = generate_account(3);
+	ExternalitiesBuilder::default()
+		.with_accounts(vec![
+			(patient_account_id, UserType::Patient),
+			(doctor_account_id, UserType::Doctor),
+		])
+		.build()
+		.execute_with(|| {
+			System::set_block_number(1);
+			assert_ok!(MedicalRecord::grant_license(
+				RuntimeOrigin::root(),
+				nurse_account_id,
+				UserType::Nurse
+			));
+
+			// Nurses add notes but no diagnoses; patients add their own records with
+			// `patient_adds_record`
+			assert_noop!(
+				MedicalRecord::author_adds_record(
+					nurse.clone(),
+					patient_account_id,
+					RecordCategory::Diagnosis,
+					None,
+					bounded_vec![]
+				),
+				Error::<Test>::NotPermitted
+			);
+			assert_noop!(
+				MedicalRecord::author_adds_record(
+					patient,
+					patient_account_id,
+					RecordCategory::Note,
+					None,
+					bounded_vec![]
+				),
+				Error::<Test>::NotPermitted
+			);
+			assert_ok!(MedicalRecord::author_adds_record(
+				nurse,
+				patient_account_id,
+				RecordCategory::Note,
+				None,
+				bounded_vec![]
+			));
+			System::assert_has_event(
+				crate::Event::AuthorAddsRecordForPatient(patient_account_id, nurse_account_id, 1)
+					.into(),
+			);
+
+			let record = MedicalRecord::record(&patient_account_id, 1).unwrap();
+			assert_eq!(record.author, nurse_account_id);
+			assert!(!record.is_verified());
+			assert!(Balances::reserved_balance(nurse_account_id) > 0);
+
+			// A doctor verifies the note like any other
+			assert_ok!(MedicalRecord::doctor_verifies_record(
+				doctor,
+				patient_account_id,
+				1,
+				sign(doctor_account_id, patient_account_id, 1)
+			));
+			assert!(MedicalRecord::record(&patient_account_id, 1).unwrap().is_verified());
+		});
+}
+
 #[test]
 fn doctor_can_transform_unverified_record() {
 	let (patient_account_id, patient) = generate_account(1);
@@ -105,12 +493,14 @@ fn doctor_can_transform_unverified_record() {
 			for _ in 0..max_record_len {
 				assert_ok!(MedicalRecord::patient_adds_record(
 					patient.clone(),
+					RecordCategory::Note,
+					None,
 					BoundedVec::with_max_capacity()
 				));
 			}
 			let record_id_to_verify = 1.min(max_record_len as u32 / 2);
 			// Doctor verifies the second record
-			let signature: BoundedVec<u8, MockSignatureLength> = bounded_vec![];
+			let signature = sign(doctor_account_id, patient_account_id, record_id_to_verify);
 			assert_ok!(MedicalRecord::doctor_verifies_record(
 				doctor.clone(),
 				patient_account_id,
@@ -118,23 +508,19 @@ fn doctor_can_transform_unverified_record() {
 				signature.clone()
 			));
 
-			let verified_record = MedicalRecord::get_record_by_id(
-				patient_account_id,
-				UserType::Patient,
-				record_id_to_verify,
-			)
-			.expect("Record should exist");
+			let verified_record =
+				MedicalRecord::get_record_by_id(&patient_account_id, record_id_to_verify)
+					.expect("Record should exist");
 
 			assert!(verified_record.is_verified());
 
-			let patient_records = MedicalRecord::records(patient_account_id, UserType::Patient)
-				.expect("records should exists");
+			let patient_records = MedicalRecord::records_of(&patient_account_id);
 			assert_eq!(
 				patient_records.into_iter().filter(|r| !r.is_verified()).count(),
 				max_record_len - 1
 			);
 
-			// A record verified once cannot be verified again
+			// A doctor endorses a version only once
 			assert_noop!(
 				MedicalRecord::doctor_verifies_record(
 					doctor.clone(),
@@ -142,11 +528,1757 @@ fn doctor_can_transform_unverified_record() {
 					record_id_to_verify,
 					signature
 				),
-				Error::<Test>::RecordAlreadyVerified
+				Error::<Test>::AlreadyEndorsed
+			);
+		});
+}
+
+#[test]
+fn doctor_signature_must_match_record() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (doctor_account_id, doctor) = generate_account(2);
+	let (other_doctor_account_id, _) = generate_account(3);
+	ExternalitiesBuilder::default()
+		.with_accounts(vec![
+			(patient_account_id, UserType::Patient),
+			(doctor_account_id, UserType::Doctor),
+		])
+		.build()
+		.execute_with(|| {
+			// Signed by someone else
+			assert_noop!(
+				MedicalRecord::doctor_adds_record(
+					doctor.clone(),
+					patient_account_id,
+					RecordCategory::Note,
+					None,
+					BoundedVec::with_max_capacity(),
+					sign(other_doctor_account_id, patient_account_id, 1)
+				),
+				Error::<Test>::InvalidSignature
+			);
+			// Signed for the wrong record id
+			assert_noop!(
+				MedicalRecord::doctor_adds_record(
+					doctor.clone(),
+					patient_account_id,
+					RecordCategory::Note,
+					None,
+					BoundedVec::with_max_capacity(),
+					sign(doctor_account_id, patient_account_id, 2)
+				),
+				Error::<Test>::InvalidSignature
+			);
+
+			assert_ok!(MedicalRecord::patient_adds_record(
+				patient.clone(),
+				RecordCategory::Note,
+				None,
+				BoundedVec::with_max_capacity()
+			));
+			// Signed for another patient
+			assert_noop!(
+				MedicalRecord::doctor_verifies_record(
+					doctor.clone(),
+					patient_account_id,
+					1,
+					sign(doctor_account_id, doctor_account_id, 1),
+				),
+				Error::<Test>::InvalidSignature
 			);
+			assert_ok!(MedicalRecord::doctor_verifies_record(
+				doctor,
+				patient_account_id,
+				1,
+				sign(doctor_account_id, patient_account_id, 1),
+			));
 		});
 }
 
+#[test]
+fn records_need_the_endorsements_their_category_asks_for() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (doctor_account_id, doctor) = generate_account(2);
+	let (surgeon_account_id, surgeon) = generate_account(3);
+	let (third_doctor_account_id, third_doctor) = generate_account(4);
+	ExternalitiesBuilder::default()
+		.with_accounts(vec![
+			(patient_account_id, UserType::Patient),
+			(doctor_account_id, UserType::Doctor),
+			(surgeon_account_id, UserType::Doctor),
+			(third_doctor_account_id, UserType::Doctor),
+		])
+		.build()
+		.execute_with(|| {
+			System::set_block_number(1);
+			assert_noop!(
+				MedicalRecord::set_endorsement_threshold(
+					doctor.clone(),
+					RecordCategory::Diagnosis,
+					2
+				),
+				BadOrigin
+			);
+			for threshold in [0, 3] {
+				assert_noop!(
+					MedicalRecord::set_endorsement_threshold(
+						RuntimeOrigin::root(),
+						RecordCategory::Diagnosis,
+						threshold
+					),
+					Error::<Test>::InvalidArgument
+				);
+			}
+			assert_ok!(MedicalRecord::set_endorsement_threshold(
+				RuntimeOrigin::root(),
+				RecordCategory::Diagnosis,
+				2
+			));
+			assert_eq!(MedicalRecord::endorsement_threshold(RecordCategory::Note), 1);
+
+			// The doctor adding a diagnosis only gives its first endorsement
+			assert_ok!(MedicalRecord::doctor_adds_record(
+				doctor.clone(),
+				patient_account_id,
+				RecordCategory::Diagnosis,
+				None,
+				BoundedVec::with_max_capacity(),
+				sign(doctor_account_id, patient_account_id, 1)
+			));
+			assert!(!MedicalRecord::record(patient_account_id, 1).unwrap().is_verified());
+			assert_noop!(
+				MedicalRecord::doctor_verifies_record(
+					doctor.clone(),
+					patient_account_id,
+					1,
+					sign(doctor_account_id, patient_account_id, 1)
+				),
+				Error::<Test>::AlreadyEndorsed
+			);
+
+			System::set_block_number(2);
+			assert_ok!(MedicalRecord::doctor_verifies_record(
+				surgeon,
+				patient_account_id,
+				1,
+				sign(surgeon_account_id, patient_account_id, 1)
+			));
+			System::assert_has_event(
+				crate::Event::RecordEndorsed(patient_account_id, surgeon_account_id, 1, 2).into(),
+			);
+			System::assert_last_event(
+				crate::Event::DoctorVerifiesRecordForPatient(
+					patient_account_id,
+					surgeon_account_id,
+					1,
+				)
+				.into(),
+			);
+			let record = MedicalRecord::record(patient_account_id, 1).unwrap();
+			assert!(record.is_verified());
+			assert_eq!(
+				record
+					.endorsements()
+					.iter()
+					.map(|endorsement| (endorsement.doctor, endorsement.at))
+					.collect::<Vec<_>>(),
+				vec![(doctor_account_id, 1), (surgeon_account_id, 2)]
+			);
+			assert_eq!(
+				MedicalRecord::record_history(&patient_account_id, 1)[0].endorsements.to_vec(),
+				record.endorsements().to_vec()
+			);
+			assert_noop!(
+				MedicalRecord::doctor_verifies_record(
+					third_doctor,
+					patient_account_id,
+					1,
+					sign(third_doctor_account_id, patient_account_id, 1)
+				),
+				Error::<Test>::TooManyEndorsements
+			);
+
+			// Raising the threshold leaves verified records verified
+			assert_ok!(MedicalRecord::doctor_adds_record(
+				doctor.clone(),
+				patient_account_id,
+				RecordCategory::Note,
+				None,
+				BoundedVec::with_max_capacity(),
+				sign(doctor_account_id, patient_account_id, 2)
+			));
+			assert_ok!(MedicalRecord::set_endorsement_threshold(
+				RuntimeOrigin::root(),
+				RecordCategory::Note,
+				2
+			));
+			assert!(MedicalRecord::record(patient_account_id, 2).unwrap().is_verified());
+
+			// An amendment starts over without endorsements
+			assert_ok!(MedicalRecord::amend_record(
+				patient,
+				patient_account_id,
+				1,
+				bounded_vec![1]
+			));
+			let record = MedicalRecord::record(patient_account_id, 1).unwrap();
+			assert!(!record.is_verified());
+			assert!(record.endorsements().is_empty());
+		});
+}
+
+#[test]
+fn records_can_keep_their_payload_offchain() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (doctor_account_id, doctor) = generate_account(2);
+	let mut ext = ExternalitiesBuilder::default()
+		.with_accounts(vec![
+			(patient_account_id, UserType::Patient),
+			(doctor_account_id, UserType::Doctor),
+		])
+		.build();
+	let payload: BoundedVec<u8, MockMaxPayloadLength> = b"blood test".to_vec().try_into().unwrap();
+	let payload_hash = BlakeTwo256::hash(&payload);
+
+	ext.execute_with(|| {
+		assert_ok!(MedicalRecord::patient_adds_offchain_record(
+			patient.clone(),
+			RecordCategory::Note,
+			None,
+			payload.clone(),
+			b"text/plain".to_vec().try_into().unwrap()
+		));
+		let record =
+			MedicalRecord::get_record_by_id(&patient_account_id, 1).expect("Record should exist");
+		let anchor = match record.get_content() {
+			Some(Content::OffChain(anchor)) => anchor.clone(),
+			_ => panic!("payload should be kept offchain"),
+		};
+		assert_eq!(anchor.hash, payload_hash);
+		assert_eq!(anchor.size, payload.len() as u32);
+
+		// The doctor signs over the anchor, not the payload
+		let content = Content::OffChain(anchor);
+		assert_ok!(MedicalRecord::doctor_adds_offchain_record(
+			doctor.clone(),
+			patient_account_id,
+			RecordCategory::Note,
+			None,
+			payload.clone(),
+			b"text/plain".to_vec().try_into().unwrap(),
+			sign_content(doctor_account_id, patient_account_id, 2, 1, &content)
+		));
+		assert!(MedicalRecord::get_record_by_id(&patient_account_id, 2)
+			.expect("Record should exist")
+			.is_verified());
+	});
+
+	ext.persist_offchain_overlay();
+	assert_eq!(
+		ext.offchain_db().get(&offchain_payload_key(payload_hash.as_ref())),
+		Some(payload.into_inner())
+	);
+}
+
+#[test]
+fn erasing_a_record_keeps_payloads_other_records_hold() {
+	let (patient_account_id, patient) = generate_account(1);
+	let mut ext = ExternalitiesBuilder::default()
+		.with_accounts(vec![(patient_account_id, UserType::Patient)])
+		.build();
+	let payload: BoundedVec<u8, MockMaxPayloadLength> = b"x-ray".to_vec().try_into().unwrap();
+	let payload_key = offchain_payload_key(BlakeTwo256::hash(&payload).as_ref());
+
+	ext.execute_with(|| {
+		for _ in 0..2 {
+			assert_ok!(MedicalRecord::patient_adds_offchain_record(
+				patient.clone(),
+				RecordCategory::Note,
+				None,
+				payload.clone(),
+				b"image/png".to_vec().try_into().unwrap()
+			));
+		}
+		assert_eq!(MedicalRecord::payload_references(BlakeTwo256::hash(&payload)), 2);
+		assert_ok!(MedicalRecord::erase_record(patient.clone(), 1, 0));
+		assert_eq!(MedicalRecord::payload_references(BlakeTwo256::hash(&payload)), 1);
+	});
+	ext.persist_offchain_overlay();
+	assert_eq!(ext.offchain_db().get(&payload_key), Some(payload.to_vec()));
+
+	ext.execute_with(|| {
+		assert_ok!(MedicalRecord::erase_record(patient.clone(), 2, 0));
+		assert_eq!(MedicalRecord::payload_references(BlakeTwo256::hash(&payload)), 0);
+	});
+	ext.persist_offchain_overlay();
+	assert_eq!(ext.offchain_db().get(&payload_key), None);
+}
+
+#[test]
+fn guardians_act_on_behalf_of_patients() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (guardian_account_id, guardian) = generate_account(2);
+	let (doctor_account_id, doctor) = generate_account(3);
+	ExternalitiesBuilder::default()
+		.with_accounts(vec![
+			(patient_account_id, UserType::Patient),
+			(guardian_account_id, UserType::Patient),
+			(doctor_account_id, UserType::Doctor),
+		])
+		.build()
+		.execute_with(|| {
+			System::set_block_number(1);
+			assert_ok!(RecordSharing::register_encryption_key(doctor, [3; 32]));
+			assert_noop!(
+				MedicalRecord::guardian_adds_record(
+					guardian.clone(),
+					patient_account_id,
+					RecordCategory::Note,
+					None,
+					bounded_vec![]
+				),
+				Error::<Test>::NotAGuardian
+			);
+
+			let scope = GuardianScope { add_records: true, share: true, revoke_shares: false };
+			assert_ok!(MedicalRecord::appoint_guardian(
+				patient.clone(),
+				guardian_account_id,
+				scope,
+				Some(10)
+			));
+			assert_ok!(MedicalRecord::guardian_adds_record(
+				guardian.clone(),
+				patient_account_id,
+				RecordCategory::Note,
+				None,
+				bounded_vec![]
+			));
+			System::assert_last_event(
+				crate::Event::GuardianAddsRecordForPatient(
+					patient_account_id,
+					guardian_account_id,
+					1,
+				)
+				.into(),
+			);
+			assert!(Balances::reserved_balance(guardian_account_id) > 0);
+
+			assert_ok!(MedicalRecord::guardian_shares_record(
+				guardian.clone(),
+				patient_account_id,
+				doctor_account_id,
+				sealed_key(),
+				1,
+				1,
+				None
+			));
+			System::assert_last_event(
+				crate::Event::GuardianSharesRecord(
+					patient_account_id,
+					guardian_account_id,
+					doctor_account_id,
+					1,
+				)
+				.into(),
+			);
+			assert_eq!(
+				RecordSharing::records_shared(patient_account_id, doctor_account_id).len(),
+				1
+			);
+			assert_noop!(
+				MedicalRecord::guardian_revokes_share(
+					guardian.clone(),
+					patient_account_id,
+					doctor_account_id,
+					1
+				),
+				Error::<Test>::OutsideGuardianScope
+			);
+
+			// The guardianship ends at its expiry or when revoked
+			System::set_block_number(10);
+			assert_noop!(
+				MedicalRecord::guardian_adds_record(
+					guardian.clone(),
+					patient_account_id,
+					RecordCategory::Note,
+					None,
+					bounded_vec![]
+				),
+				Error::<Test>::GuardianshipExpired
+			);
+			assert_ok!(MedicalRecord::appoint_guardian_for(
+				RuntimeOrigin::root(),
+				patient_account_id,
+				guardian_account_id,
+				scope,
+				None
+			));
+			assert_ok!(MedicalRecord::revoke_guardian(
+				patient,
+				patient_account_id,
+				guardian_account_id
+			));
+			assert!(MedicalRecord::guardianship(patient_account_id, guardian_account_id).is_none());
+			assert_noop!(
+				MedicalRecord::guardian_adds_record(
+					guardian,
+					patient_account_id,
+					RecordCategory::Note,
+					None,
+					bounded_vec![]
+				),
+				Error::<Test>::NotAGuardian
+			);
+		});
+}
+
+#[test]
+fn responders_can_break_the_glass_on_emergency_profiles() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (responder_account_id, responder) = generate_account(2);
+	ExternalitiesBuilder::default()
+		.with_accounts(vec![(patient_account_id, UserType::Patient)])
+		.build()
+		.execute_with(|| {
+			System::set_block_number(1);
+			assert_ok!(MedicalRecord::patient_adds_record(
+				patient.clone(),
+				RecordCategory::Note,
+				None,
+				bounded_vec![]
+			));
+			assert_noop!(
+				MedicalRecord::set_emergency_record(patient.clone(), 1, sealed_key(), 1),
+				Error::<Test>::StaleEmergencyKey
+			);
+			assert_ok!(MedicalRecord::set_emergency_key(RuntimeOrigin::root(), [5; 32]));
+			assert_ok!(MedicalRecord::set_emergency_record(patient.clone(), 1, sealed_key(), 1));
+
+			// Only licensed responders can break the glass
+			assert_noop!(
+				MedicalRecord::break_glass(patient.clone(), patient_account_id, bounded_vec![]),
+				Error::<Test>::NotPermitted
+			);
+			assert_ok!(MedicalRecord::grant_license(
+				RuntimeOrigin::root(),
+				responder_account_id,
+				UserType::EmergencyResponder
+			));
+			assert_noop!(
+				MedicalRecord::break_glass(responder.clone(), 3, bounded_vec![]),
+				Error::<Test>::AccountNotFound
+			);
+
+			// Once the emergency key is rotated, the profile has to be wrapped for the new key
+			assert_ok!(MedicalRecord::set_emergency_key(RuntimeOrigin::root(), [6; 32]));
+			assert_noop!(
+				MedicalRecord::break_glass(responder.clone(), patient_account_id, bounded_vec![]),
+				Error::<Test>::StaleEmergencyKey
+			);
+			assert_ok!(MedicalRecord::set_emergency_record(patient.clone(), 1, sealed_key(), 2));
+			for reason in 1..=5 {
+				assert_ok!(MedicalRecord::break_glass(
+					responder.clone(),
+					patient_account_id,
+					bounded_vec![reason]
+				));
+			}
+			System::assert_last_event(
+				crate::Event::EmergencyAccessed(
+					patient_account_id,
+					responder_account_id,
+					bounded_vec![5],
+				)
+				.into(),
+			);
+			// Accesses are logged, and the oldest entries are dropped once the log is full
+			let log = MedicalRecord::audit_log(patient_account_id);
+			assert!(log.iter().all(|entry| entry.actor == responder_account_id &&
+				entry.action == AuditAction::BreakGlass &&
+				entry.path == AccessPath::Emergency));
+			let reasons: Vec<_> = log.into_iter().map(|entry| entry.purpose.into_inner()).collect();
+			assert_eq!(reasons, vec![vec![2], vec![3], vec![4], vec![5]]);
+
+			// Erasing a record takes it out of the profile
+			assert_ok!(MedicalRecord::erase_record(patient, 1, 0));
+			assert!(MedicalRecord::emergency_record(patient_account_id, 1).is_none());
+			assert_noop!(
+				MedicalRecord::break_glass(responder, patient_account_id, bounded_vec![]),
+				Error::<Test>::NoEmergencyProfile
+			);
+		});
+}
+
+#[test]
+fn emergency_profile_changes_are_audited() {
+	let (patient_account_id, patient) = generate_account(1);
+	ExternalitiesBuilder::default()
+		.with_accounts(vec![(patient_account_id, UserType::Patient)])
+		.build()
+		.execute_with(|| {
+			System::set_block_number(1);
+			assert_ok!(MedicalRecord::patient_adds_record(
+				patient.clone(),
+				RecordCategory::Note,
+				None,
+				bounded_vec![]
+			));
+			assert_ok!(MedicalRecord::set_emergency_key(RuntimeOrigin::root(), [5; 32]));
+			assert_ok!(MedicalRecord::set_emergency_record(patient.clone(), 1, sealed_key(), 1));
+			assert_ok!(MedicalRecord::clear_emergency_record(patient.clone(), 1));
+			assert_noop!(
+				MedicalRecord::clear_emergency_record(patient, 1),
+				Error::<Test>::NonExistentRecord
+			);
+
+			let log: Vec<_> = MedicalRecord::audit_log(patient_account_id)
+				.into_iter()
+				.map(|entry| (entry.actor, entry.action, entry.record_id, entry.path))
+				.collect();
+			assert_eq!(
+				log,
+				vec![
+					(
+						patient_account_id,
+						AuditAction::SetEmergencyProfile,
+						Some(1),
+						AccessPath::Direct
+					),
+					(
+						patient_account_id,
+						AuditAction::ClearEmergencyProfile,
+						Some(1),
+						AccessPath::Direct
+					),
+				]
+			);
+		});
+}
+
+#[test]
+fn grants_revocations_and_verifications_are_audited() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (guardian_account_id, guardian) = generate_account(2);
+	let (doctor_account_id, doctor) = generate_account(3);
+	ExternalitiesBuilder::default()
+		.with_accounts(vec![
+			(patient_account_id, UserType::Patient),
+			(guardian_account_id, UserType::Patient),
+			(doctor_account_id, UserType::Doctor),
+		])
+		.build()
+		.execute_with(|| {
+			System::set_block_number(1);
+			assert_ok!(RecordSharing::register_encryption_key(doctor.clone(), [3; 32]));
+			assert_ok!(MedicalRecord::patient_adds_record(
+				patient.clone(),
+				RecordCategory::Note,
+				None,
+				bounded_vec![]
+			));
+			assert_ok!(MedicalRecord::doctor_verifies_record(
+				doctor,
+				patient_account_id,
+				1,
+				sign(doctor_account_id, patient_account_id, 1)
+			));
+			assert_ok!(RecordSharing::share_record(
+				patient.clone(),
+				doctor_account_id,
+				sealed_key(),
+				1,
+				1,
+				None
+			));
+			assert_ok!(MedicalRecord::appoint_guardian(
+				patient,
+				guardian_account_id,
+				GuardianScope { add_records: false, share: false, revoke_shares: true },
+				None
+			));
+			System::set_block_number(2);
+			assert_ok!(MedicalRecord::guardian_revokes_share(
+				guardian,
+				patient_account_id,
+				doctor_account_id,
+				1
+			));
+
+			let entries: Vec<_> = MedicalRecord::audit_infos_of(&patient_account_id)
+				.into_iter()
+				.map(|entry| (entry.actor, entry.action, entry.record_id, entry.path, entry.at))
+				.collect();
+			assert_eq!(
+				entries,
+				vec![
+					(
+						doctor_account_id,
+						api::AuditAction::Verify,
+						Some(1),
+						api::AccessPath::Policy,
+						1
+					),
+					(
+						patient_account_id,
+						api::AuditAction::Grant { recipient: doctor_account_id },
+						Some(1),
+						api::AccessPath::Direct,
+						1
+					),
+					(
+						guardian_account_id,
+						api::AuditAction::Revoke { recipient: doctor_account_id },
+						Some(1),
+						api::AccessPath::Guardian,
+						2
+					),
+				]
+			);
+			// Other accounts' logs are not touched
+			assert!(MedicalRecord::audit_log(doctor_account_id).is_empty());
+		});
+}
+
+#[test]
+fn expired_sharings_are_logged() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (doctor_account_id, doctor) = generate_account(2);
+	ExternalitiesBuilder::default()
+		.with_accounts(vec![
+			(patient_account_id, UserType::Patient),
+			(doctor_account_id, UserType::Doctor),
+		])
+		.build()
+		.execute_with(|| {
+			System::set_block_number(1);
+			assert_ok!(RecordSharing::register_encryption_key(doctor, [2; 32]));
+			assert_ok!(MedicalRecord::patient_adds_record(
+				patient.clone(),
+				RecordCategory::Note,
+				None,
+				bounded_vec![]
+			));
+			assert_ok!(RecordSharing::share_record(
+				patient,
+				doctor_account_id,
+				sealed_key(),
+				1,
+				1,
+				Some(ValidityWindow { starts_at: 1, ends_at: 3 })
+			));
+
+			System::set_block_number(3);
+			RecordSharing::on_idle(3, Weight::MAX);
+
+			assert!(RecordSharing::records_shared(patient_account_id, doctor_account_id).is_empty());
+			let entry = MedicalRecord::audit_log(patient_account_id).last().cloned().unwrap();
+			assert_eq!(entry.actor, patient_account_id);
+			assert_eq!(entry.action, AuditAction::Revoke { recipient: doctor_account_id });
+			assert_eq!(entry.record_id, Some(1));
+			assert_eq!(entry.path, AccessPath::Direct);
+			assert_eq!(entry.at, 3);
+		});
+}
+
+#[test]
+fn doctors_request_access_and_patients_answer() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (doctor_account_id, doctor) = generate_account(2);
+	let (pharmacist_account_id, pharmacist) = generate_account(3);
+	ExternalitiesBuilder::default()
+		.with_accounts(vec![
+			(patient_account_id, UserType::Patient),
+			(doctor_account_id, UserType::Doctor),
+			(pharmacist_account_id, UserType::Pharmacist),
+		])
+		.build()
+		.execute_with(|| {
+			System::set_block_number(1);
+			assert_ok!(RecordSharing::register_encryption_key(doctor.clone(), [2; 32]));
+			assert_ok!(MedicalRecord::patient_adds_record(
+				patient.clone(),
+				RecordCategory::Note,
+				None,
+				bounded_vec![]
+			));
+			assert_ok!(MedicalRecord::patient_adds_record(
+				patient.clone(),
+				RecordCategory::Note,
+				None,
+				bounded_vec![]
+			));
+
+			// Pharmacists may only receive prescriptions
+			assert_noop!(
+				MedicalRecord::request_access(
+					pharmacist,
+					patient_account_id,
+					RequestedRecords::Records(bounded_vec![1]),
+					bounded_vec![],
+					5
+				),
+				Error::<Test>::NotPermitted
+			);
+			assert_noop!(
+				MedicalRecord::request_access(
+					doctor.clone(),
+					patient_account_id,
+					RequestedRecords::Records(bounded_vec![3]),
+					bounded_vec![],
+					5
+				),
+				Error::<Test>::NonExistentRecord
+			);
+			assert_ok!(MedicalRecord::request_access(
+				doctor.clone(),
+				patient_account_id,
+				RequestedRecords::Records(bounded_vec![1, 2]),
+				bounded_vec![7],
+				5
+			));
+			System::assert_last_event(
+				crate::Event::AccessRequested(patient_account_id, doctor_account_id, 1).into(),
+			);
+
+			// The patient has to seal the key of every requested record
+			assert_noop!(
+				MedicalRecord::approve_access_request(
+					patient.clone(),
+					1,
+					bounded_vec![(1, sealed_key())],
+					1
+				),
+				Error::<Test>::KeysDoNotMatchRequest
+			);
+			assert_noop!(
+				MedicalRecord::approve_access_request(
+					doctor.clone(),
+					1,
+					bounded_vec![(1, sealed_key()), (2, sealed_key())],
+					1
+				),
+				Error::<Test>::AccessRequestNotFound
+			);
+			assert_ok!(MedicalRecord::approve_access_request(
+				patient.clone(),
+				1,
+				bounded_vec![(1, sealed_key()), (2, sealed_key())],
+				1
+			));
+			System::assert_last_event(
+				crate::Event::AccessRequestApproved(patient_account_id, doctor_account_id, 1)
+					.into(),
+			);
+			let shared = RecordSharing::records_shared(patient_account_id, doctor_account_id);
+			assert_eq!(shared.len(), 2);
+			assert!(shared
+				.iter()
+				.all(|grant| grant.validity == Some(ValidityWindow { starts_at: 1, ends_at: 6 })));
+			let entry = MedicalRecord::audit_log(patient_account_id).last().cloned().unwrap();
+			assert_eq!(entry.action, AuditAction::Grant { recipient: doctor_account_id });
+			assert_eq!(entry.purpose.into_inner(), vec![7]);
+			assert_noop!(
+				MedicalRecord::deny_access_request(patient.clone(), 1),
+				Error::<Test>::AccessRequestNotFound
+			);
+
+			assert_ok!(MedicalRecord::request_access(
+				doctor.clone(),
+				patient_account_id,
+				RequestedRecords::Category(RecordCategory::Note),
+				bounded_vec![],
+				5
+			));
+			assert_ok!(MedicalRecord::deny_access_request(patient, 2));
+			System::assert_last_event(
+				crate::Event::AccessRequestDenied(patient_account_id, doctor_account_id, 2).into(),
+			);
+
+			// Requests the patient does not answer expire
+			System::set_block_number(2);
+			assert_ok!(MedicalRecord::request_access(
+				doctor,
+				patient_account_id,
+				RequestedRecords::Category(RecordCategory::Note),
+				bounded_vec![],
+				5
+			));
+			MedicalRecord::on_initialize(11);
+			assert!(MedicalRecord::access_request(patient_account_id, 3).is_some());
+			MedicalRecord::on_initialize(12);
+			assert!(MedicalRecord::access_request(patient_account_id, 3).is_none());
+			System::assert_last_event(
+				crate::Event::AccessRequestExpired(patient_account_id, doctor_account_id, 3).into(),
+			);
+		});
+}
+
+#[test]
+fn access_requests_reserve_a_deposit_until_they_are_closed() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (doctor_account_id, doctor) = generate_account(2);
+	ExternalitiesBuilder::default()
+		.with_accounts(vec![
+			(patient_account_id, UserType::Patient),
+			(doctor_account_id, UserType::Doctor),
+		])
+		.build()
+		.execute_with(|| {
+			System::set_block_number(1);
+			assert_ok!(RecordSharing::register_encryption_key(doctor.clone(), [2; 32]));
+			assert_ok!(MedicalRecord::patient_adds_record(
+				patient.clone(),
+				RecordCategory::Note,
+				None,
+				bounded_vec![]
+			));
+			let deposit: u64 = <Test as crate::Config>::AccessRequestDeposit::get();
+			let request = || {
+				MedicalRecord::request_access(
+					doctor.clone(),
+					patient_account_id,
+					RequestedRecords::Category(RecordCategory::Note),
+					bounded_vec![],
+					5,
+				)
+			};
+
+			assert_ok!(request());
+			assert_ok!(request());
+			assert_eq!(Balances::reserved_balance(doctor_account_id), 2 * deposit);
+			assert_eq!(MedicalRecord::open_requests(patient_account_id, doctor_account_id), 2);
+
+			// A requester cannot keep more requests open
+			assert_noop!(request(), Error::<Test>::TooManyOpenRequests);
+
+			// Answering a request returns its deposit and makes room for another
+			assert_ok!(MedicalRecord::deny_access_request(patient.clone(), 1));
+			assert_eq!(Balances::reserved_balance(doctor_account_id), deposit);
+			assert_eq!(MedicalRecord::open_requests(patient_account_id, doctor_account_id), 1);
+			assert_ok!(MedicalRecord::approve_access_request(
+				patient,
+				2,
+				bounded_vec![(1, sealed_key())],
+				1
+			));
+			assert_eq!(Balances::reserved_balance(doctor_account_id), 0);
+
+			// So does expiring
+			System::set_block_number(2);
+			assert_ok!(request());
+			assert_eq!(Balances::reserved_balance(doctor_account_id), deposit);
+			MedicalRecord::on_initialize(12);
+			assert!(MedicalRecord::access_request(patient_account_id, 3).is_none());
+			assert_eq!(Balances::reserved_balance(doctor_account_id), 0);
+			assert_eq!(Balances::free_balance(doctor_account_id), INITIAL_BALANCE);
+			assert_eq!(MedicalRecord::open_requests(patient_account_id, doctor_account_id), 0);
+		});
+}
+
+#[test]
+fn access_requests_expire_later_when_a_block_is_full() {
+	let (patient_account_id, _) = generate_account(1);
+	let (doctor_account_id, doctor) = generate_account(2);
+	let (pharmacist_account_id, pharmacist) = generate_account(3);
+	ExternalitiesBuilder::default()
+		.with_accounts(vec![
+			(patient_account_id, UserType::Patient),
+			(doctor_account_id, UserType::Doctor),
+			(pharmacist_account_id, UserType::Pharmacist),
+		])
+		.build()
+		.execute_with(|| {
+			System::set_block_number(1);
+			for _ in 0..2 {
+				assert_ok!(MedicalRecord::request_access(
+					doctor.clone(),
+					patient_account_id,
+					RequestedRecords::Category(RecordCategory::Note),
+					bounded_vec![],
+					5
+				));
+			}
+			// Block 11 already expires as many requests as it may
+			assert_ok!(MedicalRecord::request_access(
+				pharmacist,
+				patient_account_id,
+				RequestedRecords::Category(RecordCategory::Prescription),
+				bounded_vec![],
+				5
+			));
+			assert_eq!(
+				MedicalRecord::access_request(patient_account_id, 3).unwrap().expires_at,
+				12
+			);
+
+			MedicalRecord::on_initialize(11);
+			assert!(MedicalRecord::access_request(patient_account_id, 2).is_none());
+			assert!(MedicalRecord::access_request(patient_account_id, 3).is_some());
+			MedicalRecord::on_initialize(12);
+			assert!(MedicalRecord::access_request(patient_account_id, 3).is_none());
+			System::assert_last_event(
+				crate::Event::AccessRequestExpired(patient_account_id, pharmacist_account_id, 3)
+					.into(),
+			);
+		});
+}
+
+#[test]
+fn patient_can_revoke_shared_record() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (doctor_account_id, doctor) = generate_account(2);
+	ExternalitiesBuilder::default()
+		.with_accounts(vec![
+			(patient_account_id, UserType::Patient),
+			(doctor_account_id, UserType::Doctor),
+		])
+		.build()
+		.execute_with(|| {
+			assert_ok!(RecordSharing::register_encryption_key(doctor.clone(), [2; 32]));
+			assert_ok!(MedicalRecord::patient_adds_record(
+				patient.clone(),
+				RecordCategory::Note,
+				None,
+				BoundedVec::with_max_capacity()
+			));
+			assert_ok!(MedicalRecord::share_record_with(
+				patient.clone(),
+				doctor_account_id,
+				sealed_key(),
+				1,
+				1,
+				None
+			));
+			assert_eq!(
+				RecordSharing::records_shared(patient_account_id, doctor_account_id).len(),
+				1
+			);
+
+			assert_ok!(MedicalRecord::revoke_share_with(patient.clone(), doctor_account_id, 1));
+			assert!(RecordSharing::records_shared(patient_account_id, doctor_account_id).is_empty());
+
+			assert_noop!(
+				MedicalRecord::revoke_share_with(patient, doctor_account_id, 1),
+				pallet_record_sharing::Error::<Test>::SharingNotFound
+			);
+		});
+}
+
+#[test]
+fn sharing_pallet_checks_record_ownership() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (doctor_account_id, doctor) = generate_account(2);
+	let (unregistered_account_id, _) = generate_account(3);
+	ExternalitiesBuilder::default()
+		.with_accounts(vec![
+			(patient_account_id, UserType::Patient),
+			(doctor_account_id, UserType::Doctor),
+		])
+		.build()
+		.execute_with(|| {
+			assert_ok!(RecordSharing::register_encryption_key(doctor.clone(), [2; 32]));
+			assert_ok!(MedicalRecord::patient_adds_record(
+				patient.clone(),
+				RecordCategory::Note,
+				None,
+				BoundedVec::with_max_capacity()
+			));
+
+			// Calling the sharing pallet directly goes through the same checks
+			assert_noop!(
+				RecordSharing::share_record(
+					patient.clone(),
+					doctor_account_id,
+					sealed_key(),
+					1,
+					2,
+					None
+				),
+				pallet_record_sharing::Error::<Test>::WrongRecordId
+			);
+			assert_noop!(
+				RecordSharing::share_record(doctor, doctor_account_id, sealed_key(), 1, 1, None),
+				pallet_record_sharing::Error::<Test>::WrongRecordId
+			);
+			assert_noop!(
+				RecordSharing::share_record(
+					patient.clone(),
+					unregistered_account_id,
+					sealed_key(),
+					1,
+					1,
+					None
+				),
+				pallet_record_sharing::Error::<Test>::UserUnregistered
+			);
+			assert_ok!(RecordSharing::share_record(
+				patient,
+				doctor_account_id,
+				sealed_key(),
+				1,
+				1,
+				None
+			));
+		});
+}
+
+#[test]
+fn amendments_keep_the_version_history() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (doctor_account_id, doctor) = generate_account(2);
+	let (_, outsider) = generate_account(3);
+	ExternalitiesBuilder::default()
+		.with_accounts(vec![
+			(patient_account_id, UserType::Patient),
+			(doctor_account_id, UserType::Doctor),
+		])
+		.build()
+		.execute_with(|| {
+			System::set_block_number(1);
+			assert_ok!(MedicalRecord::doctor_adds_record(
+				doctor.clone(),
+				patient_account_id,
+				RecordCategory::Note,
+				None,
+				BoundedVec::with_max_capacity(),
+				sign(doctor_account_id, patient_account_id, 1)
+			));
+
+			System::set_block_number(2);
+			assert_noop!(
+				MedicalRecord::amend_record(outsider, patient_account_id, 1, bounded_vec![1]),
+				Error::<Test>::AmendmentNotAllowed
+			);
+			assert_ok!(MedicalRecord::amend_record(
+				patient.clone(),
+				patient_account_id,
+				1,
+				bounded_vec![1]
+			));
+			System::assert_last_event(
+				crate::Event::RecordAmended(patient_account_id, patient_account_id, 1, 2).into(),
+			);
+
+			// The amended record has to be verified again, for its new version
+			let record = MedicalRecord::get_record_by_id(&patient_account_id, 1)
+				.expect("Record should exist");
+			assert!(!record.is_verified());
+			assert_eq!(MedicalRecord::current_version(&patient_account_id, 1), 2);
+			let amended_content = Content::OnChain(bounded_vec![1]);
+			assert_noop!(
+				MedicalRecord::doctor_verifies_record(
+					doctor.clone(),
+					patient_account_id,
+					1,
+					sign_content(doctor_account_id, patient_account_id, 1, 1, &amended_content),
+				),
+				Error::<Test>::InvalidSignature
+			);
+			assert_ok!(MedicalRecord::doctor_verifies_record(
+				doctor.clone(),
+				patient_account_id,
+				1,
+				sign_content(doctor_account_id, patient_account_id, 1, 2, &amended_content),
+			));
+
+			let history = MedicalRecord::record_history(&patient_account_id, 1);
+			assert_eq!(history.len(), 2);
+			assert_eq!(
+				(history[0].number, history[0].author, history[0].created_at),
+				(1, doctor_account_id, 1)
+			);
+			assert_eq!(history[0].content, Content::OnChain(BoundedVec::with_max_capacity()));
+			assert_eq!(
+				(history[1].number, history[1].author, history[1].created_at),
+				(2, patient_account_id, 2)
+			);
+			assert_eq!(history[1].content, amended_content);
+			assert!(history.iter().all(|version| version
+				.endorsements
+				.iter()
+				.map(|endorsement| endorsement.doctor)
+				.eq([doctor_account_id])));
+
+			let max_versions = <MockMaxRecordVersions as Get<u32>>::get();
+			for _ in 2..max_versions {
+				assert_ok!(MedicalRecord::amend_record(
+					doctor.clone(),
+					patient_account_id,
+					1,
+					bounded_vec![2]
+				));
+			}
+			// Once the record keeps as many versions as it can, the oldest one is dropped.
+			assert_ok!(MedicalRecord::amend_record(doctor, patient_account_id, 1, bounded_vec![3]));
+			let history = MedicalRecord::record_history(&patient_account_id, 1);
+			assert_eq!(history.len() as u32, max_versions);
+			assert_eq!(history.first().map(|version| version.number), Some(2));
+			assert_eq!(history.last().map(|version| version.number), Some(max_versions + 1));
+		});
+}
+
+#[test]
+fn only_the_author_or_an_endorser_amends_a_record() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (doctor_account_id, doctor) = generate_account(2);
+	let (other_doctor_account_id, other_doctor) = generate_account(3);
+	ExternalitiesBuilder::default()
+		.with_accounts(vec![
+			(patient_account_id, UserType::Patient),
+			(doctor_account_id, UserType::Doctor),
+			(other_doctor_account_id, UserType::Doctor),
+		])
+		.build()
+		.execute_with(|| {
+			assert_ok!(MedicalRecord::patient_adds_record(
+				patient,
+				RecordCategory::Note,
+				None,
+				BoundedVec::with_max_capacity()
+			));
+
+			// A licensed doctor who has nothing to do with the record cannot overwrite it
+			assert_noop!(
+				MedicalRecord::amend_record(
+					other_doctor.clone(),
+					patient_account_id,
+					1,
+					bounded_vec![1]
+				),
+				Error::<Test>::AmendmentNotAllowed
+			);
+
+			// Endorsing the current version lets a doctor amend it
+			assert_ok!(MedicalRecord::doctor_verifies_record(
+				doctor.clone(),
+				patient_account_id,
+				1,
+				sign(doctor_account_id, patient_account_id, 1)
+			));
+			assert_ok!(MedicalRecord::amend_record(
+				doctor.clone(),
+				patient_account_id,
+				1,
+				bounded_vec![1]
+			));
+
+			// The amendment is not endorsed, so the other doctor still cannot amend it
+			assert_noop!(
+				MedicalRecord::amend_record(other_doctor, patient_account_id, 1, bounded_vec![2]),
+				Error::<Test>::AmendmentNotAllowed
+			);
+		});
+}
+
+#[test]
+fn records_can_be_amended_offchain() {
+	let (patient_account_id, patient) = generate_account(1);
+	let mut ext = ExternalitiesBuilder::default()
+		.with_accounts(vec![(patient_account_id, UserType::Patient)])
+		.build();
+	let payload: BoundedVec<u8, MockMaxPayloadLength> = b"scan".to_vec().try_into().unwrap();
+	let payload_hash = BlakeTwo256::hash(&payload);
+
+	ext.execute_with(|| {
+		assert_ok!(MedicalRecord::patient_adds_record(
+			patient.clone(),
+			RecordCategory::Note,
+			None,
+			bounded_vec![1]
+		));
+		assert_ok!(MedicalRecord::amend_offchain_record(
+			patient.clone(),
+			patient_account_id,
+			1,
+			payload.clone(),
+			b"image/png".to_vec().try_into().unwrap()
+		));
+		let record = MedicalRecord::record(patient_account_id, 1).expect("Record should exist");
+		assert!(matches!(
+			record.get_content(),
+			Some(Content::OffChain(anchor)) if anchor.hash == payload_hash
+		));
+		assert_eq!(MedicalRecord::payload_references(payload_hash), 1);
+	});
+	ext.persist_offchain_overlay();
+	assert_eq!(
+		ext.offchain_db().get(&offchain_payload_key(payload_hash.as_ref())),
+		Some(payload.to_vec())
+	);
+
+	ext.execute_with(|| {
+		// The payload goes once the version holding it is dropped.
+		let max_versions = <MockMaxRecordVersions as Get<u32>>::get();
+		for _ in 0..max_versions {
+			assert_ok!(MedicalRecord::amend_record(
+				patient.clone(),
+				patient_account_id,
+				1,
+				bounded_vec![2]
+			));
+		}
+		assert_eq!(MedicalRecord::payload_references(payload_hash), 0);
+	});
+	ext.persist_offchain_overlay();
+	assert_eq!(ext.offchain_db().get(&offchain_payload_key(payload_hash.as_ref())), None);
+}
+
+#[test]
+fn patient_can_erase_record() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (doctor_account_id, doctor) = generate_account(2);
+	let (other_doctor_account_id, other_doctor) = generate_account(3);
+	ExternalitiesBuilder::default()
+		.with_accounts(vec![
+			(patient_account_id, UserType::Patient),
+			(doctor_account_id, UserType::Doctor),
+			(other_doctor_account_id, UserType::Doctor),
+		])
+		.build()
+		.execute_with(|| {
+			System::set_block_number(5);
+			assert_ok!(RecordSharing::register_encryption_key(doctor.clone(), [2; 32]));
+			assert_ok!(RecordSharing::register_encryption_key(other_doctor, [3; 32]));
+			assert_ok!(MedicalRecord::doctor_adds_record(
+				doctor.clone(),
+				patient_account_id,
+				RecordCategory::Note,
+				None,
+				BoundedVec::with_max_capacity(),
+				sign(doctor_account_id, patient_account_id, 1)
+			));
+			assert_ok!(MedicalRecord::patient_adds_record(
+				patient.clone(),
+				RecordCategory::Note,
+				None,
+				BoundedVec::with_max_capacity()
+			));
+			for recipient in [doctor_account_id, other_doctor_account_id] {
+				for record_id in [1, 2] {
+					assert_ok!(MedicalRecord::share_record_with(
+						patient.clone(),
+						recipient,
+						sealed_key(),
+						1,
+						record_id,
+						None
+					));
+				}
+			}
+
+			assert_noop!(
+				MedicalRecord::erase_record(patient.clone(), 3, 0),
+				Error::<Test>::NonExistentRecord
+			);
+			// Record 1 is shared with both doctors
+			assert_noop!(
+				MedicalRecord::erase_record(patient.clone(), 1, 1),
+				Error::<Test>::TooManySharings
+			);
+			assert_ok!(MedicalRecord::erase_record(patient.clone(), 1, 2));
+			System::assert_last_event(crate::Event::RecordErased(patient_account_id, 1).into());
+
+			let erased = MedicalRecord::get_record_by_id(&patient_account_id, 1)
+				.expect("the tombstone should exist");
+			assert_eq!(erased.status, RecordStatus::Erased(5));
+			assert!(erased.get_content().is_none());
+			assert!(MedicalRecord::record_history(&patient_account_id, 1).is_empty());
+
+			// Only the erased record is no longer shared
+			for recipient in [doctor_account_id, other_doctor_account_id] {
+				let shared = RecordSharing::records_shared(patient_account_id, recipient);
+				assert_eq!(shared.iter().map(|grant| grant.record_id).collect::<Vec<_>>(), vec![2]);
+			}
+
+			assert_noop!(
+				MedicalRecord::erase_record(patient.clone(), 1, 0),
+				Error::<Test>::RecordAlreadyErased
+			);
+			assert_noop!(
+				MedicalRecord::share_record_with(
+					patient.clone(),
+					doctor_account_id,
+					sealed_key(),
+					1,
+					1,
+					None
+				),
+				pallet_record_sharing::Error::<Test>::WrongRecordId
+			);
+
+			// The record id is not reused
+			assert_ok!(MedicalRecord::patient_adds_record(
+				patient,
+				RecordCategory::Note,
+				None,
+				BoundedVec::with_max_capacity()
+			));
+			assert!(MedicalRecord::get_record_by_id(&patient_account_id, 3).is_some());
+			assert_noop!(
+				MedicalRecord::doctor_verifies_record(
+					doctor,
+					patient_account_id,
+					1,
+					sign(doctor_account_id, patient_account_id, 1),
+				),
+				Error::<Test>::RecordAlreadyErased
+			);
+		});
+}
+
+#[test]
+fn records_hold_a_deposit_until_erased() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (doctor_account_id, doctor) = generate_account(2);
+	ExternalitiesBuilder::default()
+		.with_accounts(vec![
+			(patient_account_id, UserType::Patient),
+			(doctor_account_id, UserType::Doctor),
+		])
+		.build()
+		.execute_with(|| {
+			System::set_block_number(1);
+			assert_ok!(MedicalRecord::patient_adds_record(
+				patient.clone(),
+				RecordCategory::Note,
+				None,
+				bounded_vec![1]
+			));
+			assert_ok!(MedicalRecord::doctor_adds_record(
+				doctor,
+				patient_account_id,
+				RecordCategory::Note,
+				None,
+				BoundedVec::with_max_capacity(),
+				sign(doctor_account_id, patient_account_id, 2)
+			));
+
+			// Whoever adds a record pays for the bytes it takes up
+			let record = MedicalRecord::get_record_by_id(&patient_account_id, 1)
+				.expect("Record should exist");
+			let deposit = record.encoded_size() as u64 * MockDepositPerByte::get();
+			assert_eq!(
+				MedicalRecord::record_deposit(patient_account_id, 1),
+				Some((patient_account_id, deposit))
+			);
+			System::assert_has_event(
+				crate::Event::RecordDepositReserved(
+					patient_account_id,
+					patient_account_id,
+					1,
+					deposit,
+				)
+				.into(),
+			);
+			// and for the bytes its first version takes up
+			assert_eq!(
+				Balances::reserved_balance(patient_account_id),
+				deposit + version_deposits(patient_account_id, 1)
+			);
+			System::assert_has_event(
+				crate::Event::RecordDepositReserved(
+					patient_account_id,
+					patient_account_id,
+					1,
+					version_deposits(patient_account_id, 1),
+				)
+				.into(),
+			);
+			// A doctor adding a record also pays for their endorsement
+			let (_, doctor_deposit) = MedicalRecord::record_deposit(patient_account_id, 2)
+				.expect("the doctor reserved a deposit");
+			let endorsement_deposit = endorsement_deposits(patient_account_id, 2);
+			assert!(endorsement_deposit > 0);
+			System::assert_has_event(
+				crate::Event::RecordDepositReserved(
+					doctor_account_id,
+					patient_account_id,
+					2,
+					endorsement_deposit,
+				)
+				.into(),
+			);
+			assert_eq!(
+				Balances::reserved_balance(doctor_account_id),
+				doctor_deposit + version_deposits(patient_account_id, 2) + endorsement_deposit
+			);
+
+			// Erasing returns the deposits to whoever paid them
+			assert_ok!(MedicalRecord::erase_record(patient.clone(), 2, 0));
+			for deposit in [doctor_deposit, endorsement_deposit] {
+				System::assert_has_event(
+					crate::Event::RecordDepositReturned(
+						doctor_account_id,
+						patient_account_id,
+						2,
+						deposit,
+					)
+					.into(),
+				);
+			}
+			assert_eq!(Balances::reserved_balance(doctor_account_id), 0);
+			assert!(MedicalRecord::record_deposit(patient_account_id, 2).is_none());
+			assert_ok!(MedicalRecord::erase_record(patient, 1, 0));
+			assert_eq!(Balances::free_balance(patient_account_id), INITIAL_BALANCE);
+		});
+}
+
+#[test]
+fn amendments_adjust_deposits() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (doctor_account_id, doctor) = generate_account(2);
+	ExternalitiesBuilder::default()
+		.with_accounts(vec![
+			(patient_account_id, UserType::Patient),
+			(doctor_account_id, UserType::Doctor),
+		])
+		.build()
+		.execute_with(|| {
+			System::set_block_number(1);
+			let record_deposit = || {
+				let record = MedicalRecord::record(patient_account_id, 1).unwrap();
+				record.encoded_size() as u64 * MockDepositPerByte::get()
+			};
+			assert_ok!(MedicalRecord::patient_adds_record(
+				patient.clone(),
+				RecordCategory::Note,
+				None,
+				bounded_vec![]
+			));
+
+			// The record's deposit grows and shrinks with it, and every version is paid for
+			for content in [vec![1], vec![]] {
+				assert_ok!(MedicalRecord::amend_record(
+					patient.clone(),
+					patient_account_id,
+					1,
+					content.try_into().unwrap()
+				));
+				assert_eq!(
+					MedicalRecord::record_deposit(patient_account_id, 1),
+					Some((patient_account_id, record_deposit()))
+				);
+				assert_eq!(
+					Balances::reserved_balance(patient_account_id),
+					record_deposit() + version_deposits(patient_account_id, 1)
+				);
+			}
+
+			// The author of the current version pays for the record, and endorsers for their
+			// endorsements; dropping the oldest version returns its deposit
+			let oldest_deposit = MedicalRecord::record_versions(patient_account_id, 1)[0].deposit;
+			assert_ok!(MedicalRecord::doctor_verifies_record(
+				doctor.clone(),
+				patient_account_id,
+				1,
+				sign_content(
+					doctor_account_id,
+					patient_account_id,
+					1,
+					3,
+					&Content::OnChain(bounded_vec![])
+				),
+			));
+			assert_ok!(MedicalRecord::amend_record(doctor, patient_account_id, 1, bounded_vec![1]));
+			System::assert_has_event(
+				crate::Event::RecordDepositReturned(
+					patient_account_id,
+					patient_account_id,
+					1,
+					oldest_deposit,
+				)
+				.into(),
+			);
+			assert_eq!(
+				MedicalRecord::record_deposit(patient_account_id, 1),
+				Some((doctor_account_id, record_deposit()))
+			);
+			let versions = MedicalRecord::record_versions(patient_account_id, 1);
+			let deposits_of = |author| {
+				versions.iter().filter(|v| v.author == author).map(|v| v.deposit).sum::<u64>()
+			};
+			assert_eq!(
+				Balances::reserved_balance(patient_account_id),
+				deposits_of(patient_account_id)
+			);
+			assert_eq!(
+				Balances::reserved_balance(doctor_account_id),
+				record_deposit() +
+					deposits_of(doctor_account_id) +
+					endorsement_deposits(patient_account_id, 1)
+			);
+
+			// Erasing returns every deposit
+			assert_ok!(MedicalRecord::erase_record(patient, 1, 0));
+			assert_eq!(Balances::free_balance(patient_account_id), INITIAL_BALANCE);
+			assert_eq!(Balances::free_balance(doctor_account_id), INITIAL_BALANCE);
+		});
+}
+
+#[test]
+fn records_are_served_as_runtime_api_types() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (doctor_account_id, doctor) = generate_account(2);
+	ExternalitiesBuilder::default()
+		.with_accounts(vec![
+			(patient_account_id, UserType::Patient),
+			(doctor_account_id, UserType::Doctor),
+		])
+		.build()
+		.execute_with(|| {
+			System::set_block_number(3);
+			Timestamp::set_timestamp(1_000);
+			assert_eq!(MedicalRecord::account_roles(&patient_account_id), vec![api::Role::Patient]);
+			assert!(MedicalRecord::account_roles(&4).is_empty());
+
+			assert_ok!(MedicalRecord::patient_adds_record(
+				patient.clone(),
+				RecordCategory::Prescription,
+				Some(bounded_vec![7]),
+				bounded_vec![1]
+			));
+			assert_ok!(MedicalRecord::doctor_adds_record(
+				doctor,
+				patient_account_id,
+				RecordCategory::Lab,
+				None,
+				BoundedVec::with_max_capacity(),
+				sign(doctor_account_id, patient_account_id, 2)
+			));
+			assert_ok!(MedicalRecord::erase_record(patient.clone(), 1, 0));
+			System::set_block_number(4);
+			Timestamp::set_timestamp(2_000);
+			assert_ok!(MedicalRecord::patient_adds_record(
+				patient,
+				RecordCategory::Allergy,
+				Some(bounded_vec![8, 9]),
+				bounded_vec![]
+			));
+
+			assert_eq!(
+				MedicalRecord::record_infos_of(&patient_account_id),
+				vec![
+					api::RecordInfo {
+						id: 1,
+						patient: patient_account_id,
+						category: api::RecordCategory::Prescription,
+						// The title goes with the content
+						title: None,
+						created_at: 3,
+						created_on: 1_000,
+						status: api::RecordStatus::Erased { at: 3 },
+						endorsements: vec![],
+						content: None,
+						version: 1,
+					},
+					api::RecordInfo {
+						id: 2,
+						patient: patient_account_id,
+						category: api::RecordCategory::Lab,
+						title: None,
+						created_at: 3,
+						created_on: 1_000,
+						status: api::RecordStatus::Verified,
+						endorsements: vec![api::Endorsement { doctor: doctor_account_id, at: 3 }],
+						content: Some(api::RecordContent::OnChain(vec![])),
+						version: 1,
+					},
+					api::RecordInfo {
+						id: 3,
+						patient: patient_account_id,
+						category: api::RecordCategory::Allergy,
+						title: Some(vec![8, 9]),
+						created_at: 4,
+						created_on: 2_000,
+						status: api::RecordStatus::Unverified,
+						endorsements: vec![],
+						content: Some(api::RecordContent::OnChain(vec![])),
+						version: 1,
+					},
+				]
+			);
+			assert!(MedicalRecord::record_info(&patient_account_id, 4).is_none());
+
+			// Pages are cut from the records ordered by id
+			let page = MedicalRecord::record_infos_page(&patient_account_id, 1, 1);
+			assert_eq!(page.total, 3);
+			assert_eq!(page.items.iter().map(|record| record.id).collect::<Vec<_>>(), vec![2]);
+			let page = MedicalRecord::record_infos_page(&patient_account_id, 2, 5);
+			assert_eq!(page.items.iter().map(|record| record.id).collect::<Vec<_>>(), vec![3]);
+			assert!(MedicalRecord::record_infos_page(&patient_account_id, 3, 5).items.is_empty());
+
+			let matching = |filter| -> Vec<_> {
+				MedicalRecord::record_infos_matching(&patient_account_id, filter)
+					.into_iter()
+					.map(|record| record.id)
+					.collect()
+			};
+			assert_eq!(matching(Default::default()), vec![1, 2, 3]);
+			assert_eq!(
+				matching(api::RecordFilter {
+					categories: vec![api::RecordCategory::Lab, api::RecordCategory::Allergy],
+					..Default::default()
+				}),
+				vec![2, 3]
+			);
+			assert_eq!(
+				matching(api::RecordFilter { created_at: Some((4, 5)), ..Default::default() }),
+				vec![3]
+			);
+			assert_eq!(
+				matching(api::RecordFilter {
+					created_on: Some((0, 2_000)),
+					has_title: Some(false),
+					..Default::default()
+				}),
+				vec![1, 2]
+			);
+		});
+}
+
+#[test]
+fn migration_moves_baseline_accounts_and_records() {
+	let (patient_account_id, _) = generate_account(1);
+	let (doctor_account_id, _) = generate_account(2);
+	ExternalitiesBuilder::default().build().execute_with(|| {
+		// Records as the first version of the pallet stored them, all in one vector per role.
+		let records_key = |account_id: AccountId, user_type: UserType| {
+			let mut key = storage_prefix(b"MedicalRecord", b"Records").to_vec();
+			key.extend(Blake2_128Concat::hash(&account_id.encode()));
+			key.extend(Blake2_128Concat::hash(&user_type.encode()));
+			key
+		};
+		let signature = sign(doctor_account_id, patient_account_id, 3);
+		let mut records = Compact(3u32).encode();
+		// An unverified record.
+		records.extend((1u8, 1u32, patient_account_id, vec![1u8]).encode());
+		// A verified record whose signature cannot be decoded.
+		records.extend(
+			(0u8, 2u32, patient_account_id, doctor_account_id, vec![3u8], vec![4u8]).encode(),
+		);
+		// A verified record whose signature decodes, but was never checked.
+		records.extend(
+			(
+				0u8,
+				3u32,
+				patient_account_id,
+				doctor_account_id,
+				Vec::<u8>::new(),
+				signature.encode(),
+			)
+				.encode(),
+		);
+		unhashed::put_raw(&records_key(patient_account_id, UserType::Patient), &records);
+		unhashed::put_raw(
+			&records_key(doctor_account_id, UserType::Doctor),
+			&Compact(0u32).encode(),
+		);
+		unhashed::put_raw(
+			&records_key(doctor_account_id, UserType::Patient),
+			&Compact(0u32).encode(),
+		);
+		// There was no permissions matrix either.
+		let _ = Permissions::<Test>::clear(u32::MAX, None);
+		StorageVersion::new(0).put::<MedicalRecord>();
+
+		MigrateToV1::<Test>::on_runtime_upgrade();
+
+		assert_eq!(StorageVersion::get::<MedicalRecord>(), 1);
+		assert!(unhashed::get_raw(&records_key(patient_account_id, UserType::Patient)).is_none());
+		assert!(MedicalRecord::has_role(&patient_account_id, UserType::Patient));
+		assert!(MedicalRecord::has_role(&doctor_account_id, UserType::Patient));
+		// Doctors were never licensed, so they have to be approved first.
+		assert!(!MedicalRecord::has_role(&doctor_account_id, UserType::Doctor));
+		assert!(MedicalRecord::doctor_applications(doctor_account_id).is_some());
+		assert_eq!(MedicalRecord::last_record_id(patient_account_id), 3);
+		assert_eq!(MedicalRecord::next_record_id(&patient_account_id), 4);
+		assert_eq!(MedicalRecord::record_count(patient_account_id), 3);
+		let authors: Vec<_> = MedicalRecord::records_of(&patient_account_id)
+			.into_iter()
+			.map(|record| record.author)
+			.collect();
+		assert_eq!(authors, vec![patient_account_id, doctor_account_id, doctor_account_id]);
+		// Each record is kept as its first version, without a deposit.
+		assert!((1..=3).all(|record_id| {
+			let versions = MedicalRecord::record_versions(patient_account_id, record_id);
+			versions.len() == 1 && versions[0].number == 1 && versions[0].deposit == 0
+		}));
+
+		let records = MedicalRecord::records_of(&patient_account_id);
+		assert!(records.iter().all(|record| record.patient == patient_account_id &&
+			record.category == RecordCategory::Note &&
+			record.title.is_none() &&
+			record.created_at == 0));
+		assert_eq!(
+			records.into_iter().map(|record| record.status).collect::<Vec<_>>(),
+			vec![
+				RecordStatus::Unverified(Content::OnChain(bounded_vec![1]), bounded_vec![]),
+				RecordStatus::Unverified(Content::OnChain(bounded_vec![3]), bounded_vec![]),
+				RecordStatus::Unverified(Content::OnChain(bounded_vec![]), bounded_vec![]),
+			]
+		);
+
+		assert_ok!(MedicalRecord::approve_doctor_license(RuntimeOrigin::root(), doctor_account_id));
+		assert!(MedicalRecord::is_permitted(
+			&doctor_account_id,
+			RecordAction::Verify,
+			RecordCategory::Note
+		));
+	});
+}
+
+// The deposits reserved for the endorsements of the kept versions of a record.
+fn endorsement_deposits(patient_id: AccountId, record_id: u32) -> u64 {
+	MedicalRecord::record_versions(patient_id, record_id)
+		.iter()
+		.flat_map(|v| v.endorsements.iter().map(|e| e.deposit))
+		.sum()
+}
+
+// The deposits reserved for the kept versions of a record.
+fn version_deposits(patient_id: AccountId, record_id: u32) -> u64 {
+	MedicalRecord::record_versions(patient_id, record_id)
+		.iter()
+		.map(|v| v.deposit)
+		.sum()
+}
+
+fn sealed_key() -> SealedEnvelope<Test> {
+	SealedEnvelope {
+		version: ENVELOPE_VERSION,
+		algorithm: ALGORITHM_X25519_XSALSA20_POLY1305,
+		ephemeral_public_key: [7; 32],
+		nonce: [9; 24],
+		ciphertext: bounded_vec![1, 2, 3],
+	}
+}
+
+// Sign an empty record the way a doctor would off-chain.
+fn sign(doctor_id: AccountId, patient_id: AccountId, record_id: u32) -> MockSignature {
+	sign_content(
+		doctor_id,
+		patient_id,
+		record_id,
+		1,
+		&Content::OnChain(BoundedVec::with_max_capacity()),
+	)
+}
+
+fn sign_content(
+	doctor_id: AccountId,
+	patient_id: AccountId,
+	record_id: u32,
+	version: u32,
+	content: &Content<Test>,
+) -> MockSignature {
+	MockSignature(
+		doctor_id,
+		MedicalRecord::record_signing_payload(&patient_id, record_id, version, content),
+	)
+}
+
 fn generate_account(account_id: AccountId) -> (AccountId, RuntimeOrigin) {
 	(account_id, RuntimeOrigin::signed(account_id))
 }
@@ -0,0 +1,493 @@
+//! Weights for pallet_medical_record
+//!
+//! NOT BENCHMARKED YET. These are rounded placeholders: the reads and writes are counted from
+//! the code of each call and the times are generous round guesses. Replace this file with the
+//! output of the command below, run on reference hardware with a node built with
+//! `--features runtime-benchmarks`, and do not edit it by hand.
+
+// Command:
+// ./target/release/node-template
+// benchmark
+// pallet
+// --chain=dev
+// --steps=50
+// --repeat=20
+// --pallet=pallet_medical_record
+// --extrinsic=*
+// --execution=wasm
+// --wasm-execution=compiled
+// --output=pallets/medical-record/src/weights.rs
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use sp_std::marker::PhantomData;
+
+/// Weight functions needed for pallet_medical_record.
+pub trait WeightInfo {
+	fn create_account() -> Weight;
+	fn add_role() -> Weight;
+	fn remove_role() -> Weight;
+	fn set_profile(p: u32, ) -> Weight;
+	fn apply_for_doctor_license() -> Weight;
+	fn approve_doctor_license() -> Weight;
+	fn reject_doctor_license() -> Weight;
+	fn grant_license() -> Weight;
+	fn revoke_license() -> Weight;
+	fn set_permissions() -> Weight;
+	fn set_endorsement_threshold() -> Weight;
+	fn patient_adds_record(c: u32, r: u32, ) -> Weight;
+	fn patient_adds_offchain_record(p: u32, r: u32, ) -> Weight;
+	fn doctor_adds_record(c: u32, r: u32, ) -> Weight;
+	fn doctor_adds_offchain_record(p: u32, r: u32, ) -> Weight;
+	fn doctor_verifies_record(e: u32, ) -> Weight;
+	fn amend_record(c: u32, d: u32, ) -> Weight;
+	fn amend_offchain_record(p: u32, d: u32, ) -> Weight;
+	fn erase_record(s: u32, ) -> Weight;
+	fn share_record_with() -> Weight;
+	fn revoke_share_with() -> Weight;
+	fn set_emergency_key() -> Weight;
+	fn set_emergency_record() -> Weight;
+	fn clear_emergency_record() -> Weight;
+	fn break_glass(r: u32, ) -> Weight;
+	fn appoint_guardian() -> Weight;
+	fn appoint_guardian_for() -> Weight;
+	fn revoke_guardian() -> Weight;
+	fn revoke_guardian_for() -> Weight;
+	fn guardian_adds_record(c: u32, r: u32, ) -> Weight;
+	fn guardian_shares_record() -> Weight;
+	fn guardian_revokes_share() -> Weight;
+	fn request_access(k: u32, ) -> Weight;
+	fn approve_access_request(k: u32, ) -> Weight;
+	fn deny_access_request() -> Weight;
+	fn author_adds_record(c: u32, r: u32, ) -> Weight;
+}
+
+/// Placeholder weights for pallet_medical_record, to be replaced by benchmarked ones.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	fn create_account() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(1 as u64))
+			.saturating_add(T::DbWeight::get().writes(1 as u64))
+	}
+	fn add_role() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(1 as u64))
+			.saturating_add(T::DbWeight::get().writes(1 as u64))
+	}
+	fn remove_role() -> Weight {
+		Weight::from_ref_time(30_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(2 as u64))
+			.saturating_add(T::DbWeight::get().writes(1 as u64))
+	}
+	fn set_profile(p: u32, ) -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(1_000 as u64).saturating_mul(p as u64))
+			.saturating_add(T::DbWeight::get().reads(1 as u64))
+			.saturating_add(T::DbWeight::get().writes(1 as u64))
+	}
+	fn apply_for_doctor_license() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(2 as u64))
+			.saturating_add(T::DbWeight::get().writes(1 as u64))
+	}
+	fn approve_doctor_license() -> Weight {
+		Weight::from_ref_time(30_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(2 as u64))
+			.saturating_add(T::DbWeight::get().writes(2 as u64))
+	}
+	fn reject_doctor_license() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(1 as u64))
+			.saturating_add(T::DbWeight::get().writes(1 as u64))
+	}
+	fn grant_license() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(1 as u64))
+			.saturating_add(T::DbWeight::get().writes(1 as u64))
+	}
+	fn revoke_license() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(1 as u64))
+			.saturating_add(T::DbWeight::get().writes(1 as u64))
+	}
+	fn set_permissions() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(T::DbWeight::get().writes(1 as u64))
+	}
+	fn set_endorsement_threshold() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(T::DbWeight::get().writes(1 as u64))
+	}
+	fn patient_adds_record(c: u32, r: u32, ) -> Weight {
+		Weight::from_ref_time(40_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(3_000 as u64).saturating_mul(c as u64))
+			.saturating_add(Weight::from_ref_time(20_000 as u64).saturating_mul(r as u64))
+			.saturating_add(T::DbWeight::get().reads(7 as u64))
+			.saturating_add(T::DbWeight::get().writes(6 as u64))
+	}
+	fn patient_adds_offchain_record(p: u32, r: u32, ) -> Weight {
+		Weight::from_ref_time(40_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(2_000 as u64).saturating_mul(p as u64))
+			.saturating_add(Weight::from_ref_time(20_000 as u64).saturating_mul(r as u64))
+			.saturating_add(T::DbWeight::get().reads(8 as u64))
+			.saturating_add(T::DbWeight::get().writes(7 as u64))
+	}
+	fn doctor_adds_record(c: u32, r: u32, ) -> Weight {
+		Weight::from_ref_time(90_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(5_000 as u64).saturating_mul(c as u64))
+			.saturating_add(Weight::from_ref_time(20_000 as u64).saturating_mul(r as u64))
+			.saturating_add(T::DbWeight::get().reads(13 as u64))
+			.saturating_add(T::DbWeight::get().writes(8 as u64))
+	}
+	fn doctor_adds_offchain_record(p: u32, r: u32, ) -> Weight {
+		Weight::from_ref_time(90_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(2_000 as u64).saturating_mul(p as u64))
+			.saturating_add(Weight::from_ref_time(20_000 as u64).saturating_mul(r as u64))
+			.saturating_add(T::DbWeight::get().reads(14 as u64))
+			.saturating_add(T::DbWeight::get().writes(9 as u64))
+	}
+	fn doctor_verifies_record(e: u32, ) -> Weight {
+		Weight::from_ref_time(80_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(2_000_000 as u64).saturating_mul(e as u64))
+			.saturating_add(T::DbWeight::get().reads(8 as u64))
+			.saturating_add(T::DbWeight::get().writes(4 as u64))
+	}
+	fn amend_record(c: u32, d: u32, ) -> Weight {
+		Weight::from_ref_time(50_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(3_000 as u64).saturating_mul(c as u64))
+			.saturating_add(Weight::from_ref_time(30_000_000 as u64).saturating_mul(d as u64))
+			.saturating_add(T::DbWeight::get().reads(5 as u64))
+			.saturating_add(T::DbWeight::get().reads((4 as u64).saturating_mul(d as u64)))
+			.saturating_add(T::DbWeight::get().writes(4 as u64))
+			.saturating_add(T::DbWeight::get().writes((4 as u64).saturating_mul(d as u64)))
+	}
+	fn amend_offchain_record(p: u32, d: u32, ) -> Weight {
+		Weight::from_ref_time(50_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(2_000 as u64).saturating_mul(p as u64))
+			.saturating_add(Weight::from_ref_time(30_000_000 as u64).saturating_mul(d as u64))
+			.saturating_add(T::DbWeight::get().reads(6 as u64))
+			.saturating_add(T::DbWeight::get().reads((4 as u64).saturating_mul(d as u64)))
+			.saturating_add(T::DbWeight::get().writes(5 as u64))
+			.saturating_add(T::DbWeight::get().writes((4 as u64).saturating_mul(d as u64)))
+	}
+	fn erase_record(s: u32, ) -> Weight {
+		Weight::from_ref_time(100_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(20_000_000 as u64).saturating_mul(s as u64))
+			.saturating_add(T::DbWeight::get().reads(20 as u64))
+			.saturating_add(T::DbWeight::get().reads((1 as u64).saturating_mul(s as u64)))
+			.saturating_add(T::DbWeight::get().writes(19 as u64))
+			.saturating_add(T::DbWeight::get().writes((2 as u64).saturating_mul(s as u64)))
+	}
+	fn share_record_with() -> Weight {
+		Weight::from_ref_time(50_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(8 as u64))
+			.saturating_add(T::DbWeight::get().writes(4 as u64))
+	}
+	fn revoke_share_with() -> Weight {
+		Weight::from_ref_time(40_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(3 as u64))
+			.saturating_add(T::DbWeight::get().writes(4 as u64))
+	}
+	fn set_emergency_key() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(1 as u64))
+			.saturating_add(T::DbWeight::get().writes(1 as u64))
+	}
+	fn set_emergency_record() -> Weight {
+		Weight::from_ref_time(30_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(4 as u64))
+			.saturating_add(T::DbWeight::get().writes(2 as u64))
+	}
+	fn clear_emergency_record() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(2 as u64))
+			.saturating_add(T::DbWeight::get().writes(2 as u64))
+	}
+	fn break_glass(r: u32, ) -> Weight {
+		Weight::from_ref_time(40_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(3_000_000 as u64).saturating_mul(r as u64))
+			.saturating_add(T::DbWeight::get().reads(4 as u64))
+			.saturating_add(T::DbWeight::get().reads((1 as u64).saturating_mul(r as u64)))
+			.saturating_add(T::DbWeight::get().writes(1 as u64))
+	}
+	fn appoint_guardian() -> Weight {
+		Weight::from_ref_time(30_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(2 as u64))
+			.saturating_add(T::DbWeight::get().writes(1 as u64))
+	}
+	fn appoint_guardian_for() -> Weight {
+		Weight::from_ref_time(30_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(2 as u64))
+			.saturating_add(T::DbWeight::get().writes(1 as u64))
+	}
+	fn revoke_guardian() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(1 as u64))
+			.saturating_add(T::DbWeight::get().writes(1 as u64))
+	}
+	fn revoke_guardian_for() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(1 as u64))
+			.saturating_add(T::DbWeight::get().writes(1 as u64))
+	}
+	fn guardian_adds_record(c: u32, r: u32, ) -> Weight {
+		Weight::from_ref_time(40_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(3_000 as u64).saturating_mul(c as u64))
+			.saturating_add(Weight::from_ref_time(20_000 as u64).saturating_mul(r as u64))
+			.saturating_add(T::DbWeight::get().reads(8 as u64))
+			.saturating_add(T::DbWeight::get().writes(6 as u64))
+	}
+	fn guardian_shares_record() -> Weight {
+		Weight::from_ref_time(60_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(9 as u64))
+			.saturating_add(T::DbWeight::get().writes(4 as u64))
+	}
+	fn guardian_revokes_share() -> Weight {
+		Weight::from_ref_time(50_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(4 as u64))
+			.saturating_add(T::DbWeight::get().writes(4 as u64))
+	}
+	fn request_access(k: u32, ) -> Weight {
+		Weight::from_ref_time(100_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(4_000_000 as u64).saturating_mul(k as u64))
+			.saturating_add(T::DbWeight::get().reads(23 as u64))
+			.saturating_add(T::DbWeight::get().reads((1 as u64).saturating_mul(k as u64)))
+			.saturating_add(T::DbWeight::get().writes(5 as u64))
+	}
+	fn approve_access_request(k: u32, ) -> Weight {
+		Weight::from_ref_time(60_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(30_000_000 as u64).saturating_mul(k as u64))
+			.saturating_add(T::DbWeight::get().reads(11 as u64))
+			.saturating_add(T::DbWeight::get().reads((1 as u64).saturating_mul(k as u64)))
+			.saturating_add(T::DbWeight::get().writes(8 as u64))
+	}
+	fn deny_access_request() -> Weight {
+		Weight::from_ref_time(40_000_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(3 as u64))
+			.saturating_add(T::DbWeight::get().writes(3 as u64))
+	}
+	fn author_adds_record(c: u32, r: u32, ) -> Weight {
+		Weight::from_ref_time(40_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(3_000 as u64).saturating_mul(c as u64))
+			.saturating_add(Weight::from_ref_time(20_000 as u64).saturating_mul(r as u64))
+			.saturating_add(T::DbWeight::get().reads(9 as u64))
+			.saturating_add(T::DbWeight::get().writes(6 as u64))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	fn create_account() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(1 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	fn add_role() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(1 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	fn remove_role() -> Weight {
+		Weight::from_ref_time(30_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(2 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	fn set_profile(p: u32, ) -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(1_000 as u64).saturating_mul(p as u64))
+			.saturating_add(RocksDbWeight::get().reads(1 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	fn apply_for_doctor_license() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(2 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	fn approve_doctor_license() -> Weight {
+		Weight::from_ref_time(30_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(2 as u64))
+			.saturating_add(RocksDbWeight::get().writes(2 as u64))
+	}
+	fn reject_doctor_license() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(1 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	fn grant_license() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(1 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	fn revoke_license() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(1 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	fn set_permissions() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	fn set_endorsement_threshold() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	fn patient_adds_record(c: u32, r: u32, ) -> Weight {
+		Weight::from_ref_time(40_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(3_000 as u64).saturating_mul(c as u64))
+			.saturating_add(Weight::from_ref_time(20_000 as u64).saturating_mul(r as u64))
+			.saturating_add(RocksDbWeight::get().reads(7 as u64))
+			.saturating_add(RocksDbWeight::get().writes(6 as u64))
+	}
+	fn patient_adds_offchain_record(p: u32, r: u32, ) -> Weight {
+		Weight::from_ref_time(40_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(2_000 as u64).saturating_mul(p as u64))
+			.saturating_add(Weight::from_ref_time(20_000 as u64).saturating_mul(r as u64))
+			.saturating_add(RocksDbWeight::get().reads(8 as u64))
+			.saturating_add(RocksDbWeight::get().writes(7 as u64))
+	}
+	fn doctor_adds_record(c: u32, r: u32, ) -> Weight {
+		Weight::from_ref_time(90_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(5_000 as u64).saturating_mul(c as u64))
+			.saturating_add(Weight::from_ref_time(20_000 as u64).saturating_mul(r as u64))
+			.saturating_add(RocksDbWeight::get().reads(13 as u64))
+			.saturating_add(RocksDbWeight::get().writes(8 as u64))
+	}
+	fn doctor_adds_offchain_record(p: u32, r: u32, ) -> Weight {
+		Weight::from_ref_time(90_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(2_000 as u64).saturating_mul(p as u64))
+			.saturating_add(Weight::from_ref_time(20_000 as u64).saturating_mul(r as u64))
+			.saturating_add(RocksDbWeight::get().reads(14 as u64))
+			.saturating_add(RocksDbWeight::get().writes(9 as u64))
+	}
+	fn doctor_verifies_record(e: u32, ) -> Weight {
+		Weight::from_ref_time(80_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(2_000_000 as u64).saturating_mul(e as u64))
+			.saturating_add(RocksDbWeight::get().reads(8 as u64))
+			.saturating_add(RocksDbWeight::get().writes(4 as u64))
+	}
+	fn amend_record(c: u32, d: u32, ) -> Weight {
+		Weight::from_ref_time(50_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(3_000 as u64).saturating_mul(c as u64))
+			.saturating_add(Weight::from_ref_time(30_000_000 as u64).saturating_mul(d as u64))
+			.saturating_add(RocksDbWeight::get().reads(5 as u64))
+			.saturating_add(RocksDbWeight::get().reads((4 as u64).saturating_mul(d as u64)))
+			.saturating_add(RocksDbWeight::get().writes(4 as u64))
+			.saturating_add(RocksDbWeight::get().writes((4 as u64).saturating_mul(d as u64)))
+	}
+	fn amend_offchain_record(p: u32, d: u32, ) -> Weight {
+		Weight::from_ref_time(50_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(2_000 as u64).saturating_mul(p as u64))
+			.saturating_add(Weight::from_ref_time(30_000_000 as u64).saturating_mul(d as u64))
+			.saturating_add(RocksDbWeight::get().reads(6 as u64))
+			.saturating_add(RocksDbWeight::get().reads((4 as u64).saturating_mul(d as u64)))
+			.saturating_add(RocksDbWeight::get().writes(5 as u64))
+			.saturating_add(RocksDbWeight::get().writes((4 as u64).saturating_mul(d as u64)))
+	}
+	fn erase_record(s: u32, ) -> Weight {
+		Weight::from_ref_time(100_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(20_000_000 as u64).saturating_mul(s as u64))
+			.saturating_add(RocksDbWeight::get().reads(20 as u64))
+			.saturating_add(RocksDbWeight::get().reads((1 as u64).saturating_mul(s as u64)))
+			.saturating_add(RocksDbWeight::get().writes(19 as u64))
+			.saturating_add(RocksDbWeight::get().writes((2 as u64).saturating_mul(s as u64)))
+	}
+	fn share_record_with() -> Weight {
+		Weight::from_ref_time(50_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(8 as u64))
+			.saturating_add(RocksDbWeight::get().writes(4 as u64))
+	}
+	fn revoke_share_with() -> Weight {
+		Weight::from_ref_time(40_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(3 as u64))
+			.saturating_add(RocksDbWeight::get().writes(4 as u64))
+	}
+	fn set_emergency_key() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(1 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	fn set_emergency_record() -> Weight {
+		Weight::from_ref_time(30_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(4 as u64))
+			.saturating_add(RocksDbWeight::get().writes(2 as u64))
+	}
+	fn clear_emergency_record() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(2 as u64))
+			.saturating_add(RocksDbWeight::get().writes(2 as u64))
+	}
+	fn break_glass(r: u32, ) -> Weight {
+		Weight::from_ref_time(40_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(3_000_000 as u64).saturating_mul(r as u64))
+			.saturating_add(RocksDbWeight::get().reads(4 as u64))
+			.saturating_add(RocksDbWeight::get().reads((1 as u64).saturating_mul(r as u64)))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	fn appoint_guardian() -> Weight {
+		Weight::from_ref_time(30_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(2 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	fn appoint_guardian_for() -> Weight {
+		Weight::from_ref_time(30_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(2 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	fn revoke_guardian() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(1 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	fn revoke_guardian_for() -> Weight {
+		Weight::from_ref_time(20_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(1 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	fn guardian_adds_record(c: u32, r: u32, ) -> Weight {
+		Weight::from_ref_time(40_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(3_000 as u64).saturating_mul(c as u64))
+			.saturating_add(Weight::from_ref_time(20_000 as u64).saturating_mul(r as u64))
+			.saturating_add(RocksDbWeight::get().reads(8 as u64))
+			.saturating_add(RocksDbWeight::get().writes(6 as u64))
+	}
+	fn guardian_shares_record() -> Weight {
+		Weight::from_ref_time(60_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(9 as u64))
+			.saturating_add(RocksDbWeight::get().writes(4 as u64))
+	}
+	fn guardian_revokes_share() -> Weight {
+		Weight::from_ref_time(50_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(4 as u64))
+			.saturating_add(RocksDbWeight::get().writes(4 as u64))
+	}
+	fn request_access(k: u32, ) -> Weight {
+		Weight::from_ref_time(100_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(4_000_000 as u64).saturating_mul(k as u64))
+			.saturating_add(RocksDbWeight::get().reads(23 as u64))
+			.saturating_add(RocksDbWeight::get().reads((1 as u64).saturating_mul(k as u64)))
+			.saturating_add(RocksDbWeight::get().writes(5 as u64))
+	}
+	fn approve_access_request(k: u32, ) -> Weight {
+		Weight::from_ref_time(60_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(30_000_000 as u64).saturating_mul(k as u64))
+			.saturating_add(RocksDbWeight::get().reads(11 as u64))
+			.saturating_add(RocksDbWeight::get().reads((1 as u64).saturating_mul(k as u64)))
+			.saturating_add(RocksDbWeight::get().writes(8 as u64))
+	}
+	fn deny_access_request() -> Weight {
+		Weight::from_ref_time(40_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(3 as u64))
+			.saturating_add(RocksDbWeight::get().writes(3 as u64))
+	}
+	fn author_adds_record(c: u32, r: u32, ) -> Weight {
+		Weight::from_ref_time(40_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(3_000 as u64).saturating_mul(c as u64))
+			.saturating_add(Weight::from_ref_time(20_000 as u64).saturating_mul(r as u64))
+			.saturating_add(RocksDbWeight::get().reads(9 as u64))
+			.saturating_add(RocksDbWeight::get().writes(6 as u64))
+	}
+}
@@ -0,0 +1,58 @@
+//! Runtime API to query the sharings of medical records.
+//!
+//! The types returned here are kept independent of the pallet's storage layout, so clients can
+//! depend on them across storage migrations.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Codec, Decode, Encode};
+use scale_info::TypeInfo;
+use sp_std::vec::Vec;
+
+pub type RecordId = u32;
+pub type KeyId = u32;
+
+/// A record key sealed for the recipient, see `pallet_record_sharing::SealedEnvelope`.
+#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, TypeInfo)]
+pub struct SealedKey {
+	pub version: u8,
+	pub algorithm: u8,
+	pub ephemeral_public_key: [u8; 32],
+	pub nonce: [u8; 24],
+	pub ciphertext: Vec<u8>,
+}
+
+#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, TypeInfo)]
+pub struct GrantInfo<AccountId, BlockNumber> {
+	pub sender: AccountId,
+	pub recipient: AccountId,
+	pub record_id: RecordId,
+	pub sealed_key: SealedKey,
+	/// The recipient's encryption key `sealed_key` was sealed for.
+	pub recipient_key_id: KeyId,
+	/// The blocks the grant can be used in, as `[starts_at, ends_at)`. `None` if it never
+	/// expires.
+	pub validity: Option<(BlockNumber, BlockNumber)>,
+}
+
+/// Part of a list, starting at the requested offset.
+#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, TypeInfo)]
+pub struct Page<T> {
+	pub items: Vec<T>,
+	/// How many items the whole list holds.
+	pub total: u32,
+}
+
+sp_api::decl_runtime_apis! {
+	pub trait RecordSharingApi<AccountId, BlockNumber>
+	where
+		AccountId: Codec,
+		BlockNumber: Codec,
+	{
+		/// Up to `limit` of the grants `sender` gave that have not expired, from `offset` on.
+		fn grants_by(sender: AccountId, offset: u32, limit: u32) -> Page<GrantInfo<AccountId, BlockNumber>>;
+		/// Up to `limit` of the grants `recipient` received that have not expired, from
+		/// `offset` on.
+		fn grants_to(recipient: AccountId, offset: u32, limit: u32) -> Page<GrantInfo<AccountId, BlockNumber>>;
+	}
+}
@@ -1,20 +1,179 @@
-//! Benchmarking setup for pallet-template
+//! Benchmarking setup for pallet-record-sharing
 
 use super::*;
 
-#[allow(unused)]
-use crate::Pallet as Template;
-use frame_benchmarking::{benchmarks, whitelisted_caller};
+use crate::Pallet as RecordSharing;
+use frame_benchmarking::{account, benchmarks, whitelisted_caller};
+use frame_support::{
+	traits::{Currency, Get},
+	BoundedVec,
+};
 use frame_system::RawOrigin;
+use sp_runtime::traits::Bounded;
+use sp_std::{vec, vec::Vec};
+
+const SEED: u32 = 0;
+
+// Give the account enough to pay for the deposits and register it.
+fn register<T: Config>(account_id: &T::AccountId) {
+	T::Currency::make_free_balance_be(account_id, BalanceOf::<T>::max_value() / 2u32.into());
+	T::BenchmarkHelper::register(account_id);
+}
+
+// Publish a key for the recipient and return a record key of the maximum length sealed for it.
+fn sealed_key<T: Config>(recipient: &T::AccountId) -> (EncryptedKey<T>, KeyId) {
+	RecordSharing::<T>::register_encryption_key(
+		RawOrigin::Signed(recipient.clone()).into(),
+		[1; 32],
+	)
+	.expect("any account can register a key");
+	let key_id = RecordSharing::<T>::current_encryption_key(recipient)
+		.expect("the key was just registered")
+		.key_id;
+	let ciphertext = vec![4; T::MaxCiphertextLength::get() as usize];
+	let sealed_key = SealedEnvelope {
+		version: ENVELOPE_VERSION,
+		algorithm: ALGORITHM_X25519_XSALSA20_POLY1305,
+		ephemeral_public_key: [2; 32],
+		nonce: [3; 24],
+		ciphertext: ciphertext.try_into().expect("the ciphertext has the maximum length"),
+	};
+	(sealed_key, key_id)
+}
+
+// A validity window from now until `blocks` blocks from now.
+fn validity<T: Config>(blocks: u32) -> Option<ValidityWindow<T::BlockNumber>> {
+	let now = frame_system::Pallet::<T>::block_number();
+	Some(ValidityWindow { starts_at: now, ends_at: now + blocks.into() })
+}
+
+// Share the record `count` times with the recipient, each grant expiring in a block of its
+// own, so the grants take up as much storage as they can.
+fn share_record<T: Config>(
+	sender: &T::AccountId,
+	recipient: &T::AccountId,
+	record_id: RecordId,
+	count: u32,
+) {
+	let (sealed_key, key_id) = sealed_key::<T>(recipient);
+	for i in 0..count {
+		RecordSharing::<T>::do_share_record(
+			sender.clone(),
+			recipient.clone(),
+			sealed_key.clone(),
+			key_id,
+			record_id,
+			validity::<T>(100 + i),
+		)
+		.expect("the sender has room for more grants");
+	}
+}
+
+// Fill the expiries of the block `blocks` blocks from now, but for one.
+fn fill_expiries<T: Config>(blocks: u32) {
+	let now = frame_system::Pallet::<T>::block_number();
+	let expiries: Vec<_> = (1..T::MaxExpiriesPerBlock::get())
+		.map(|i| (account("sender", i, SEED), account("recipient", i, SEED), 0))
+		.collect();
+	let expiries = BoundedVec::<_, T::MaxExpiriesPerBlock>::try_from(expiries)
+		.expect("there is room for one more expiry");
+	SharingExpiries::<T>::insert(now + blocks.into(), expiries);
+}
 
 benchmarks! {
-	do_something {
-		let s in 0 .. 100;
+	share_record {
+		let caller: T::AccountId = whitelisted_caller();
+		let recipient: T::AccountId = account("recipient", 0, SEED);
+		register::<T>(&caller);
+		register::<T>(&recipient);
+		let record_id = T::BenchmarkHelper::add_record(&caller);
+		share_record::<T>(&caller, &recipient, record_id, T::MaxSharedRecords::get() - 1);
+		fill_expiries::<T>(10);
+		let (sealed_key, key_id) = sealed_key::<T>(&recipient);
+		let validity = validity::<T>(10);
+	}: _(RawOrigin::Signed(caller.clone()), recipient.clone(), sealed_key, key_id, record_id, validity)
+	verify {
+		let grants = SharedRecords::<T>::get(&caller, &recipient);
+		assert_eq!(grants.len() as u32, T::MaxSharedRecords::get());
+	}
+
+	revoke_share {
+		let caller: T::AccountId = whitelisted_caller();
+		let recipient: T::AccountId = account("recipient", 0, SEED);
+		register::<T>(&caller);
+		register::<T>(&recipient);
+		let record_id = T::BenchmarkHelper::add_record(&caller);
+		share_record::<T>(&caller, &recipient, record_id, T::MaxSharedRecords::get());
+	}: _(RawOrigin::Signed(caller.clone()), recipient.clone(), record_id)
+	verify {
+		assert!(!SharedRecords::<T>::contains_key(&caller, &recipient));
+		assert!(!GrantsByRecipient::<T>::contains_key(&recipient, &caller));
+	}
+
+	revoke_all_shares {
+		let n in 1 .. T::MaxSharedRecords::get();
+		let caller: T::AccountId = whitelisted_caller();
+		let recipient: T::AccountId = account("recipient", 0, SEED);
+		register::<T>(&caller);
+		register::<T>(&recipient);
+		let record_id = T::BenchmarkHelper::add_record(&caller);
+		share_record::<T>(&caller, &recipient, record_id, n);
+	}: _(RawOrigin::Signed(caller.clone()), recipient.clone(), n)
+	verify {
+		assert!(!SharedRecords::<T>::contains_key(&caller, &recipient));
+		assert!(!GrantsByRecipient::<T>::contains_key(&recipient, &caller));
+	}
+
+	register_encryption_key {
 		let caller: T::AccountId = whitelisted_caller();
-	}: _(RawOrigin::Signed(caller), s)
+		for _ in 0..T::MaxKeyHistory::get() {
+			let origin = RawOrigin::Signed(caller.clone()).into();
+			RecordSharing::<T>::register_encryption_key(origin, [1; 32])
+				.expect("any account can register a key");
+		}
+	}: _(RawOrigin::Signed(caller.clone()), [2; 32])
+	verify {
+		let key = RecordSharing::<T>::current_encryption_key(&caller).expect("a key was registered");
+		assert_eq!(key.key_id, T::MaxKeyHistory::get() + 1);
+		assert_eq!(EncryptionKeys::<T>::get(&caller).len() as u32, T::MaxKeyHistory::get());
+	}
+
+	// The sharings expiring in a block, each from a sender with as many grants as fit, so
+	// finding the expired one takes as long as it can.
+	expire_sharings {
+		let n in 0 .. T::MaxExpiriesPerBlock::get();
+		let recipient: T::AccountId = account("recipient", 0, SEED);
+		register::<T>(&recipient);
+		let (sealed_key, key_id) = sealed_key::<T>(&recipient);
+		let senders: Vec<T::AccountId> = (0..n).map(|i| account("sender", i, SEED)).collect();
+		for sender in senders.iter() {
+			register::<T>(sender);
+			let record_id = T::BenchmarkHelper::add_record(sender);
+			for i in 0..T::MaxSharedRecords::get() {
+				let validity = if i == 0 { validity::<T>(10) } else { None };
+				RecordSharing::<T>::do_share_record(
+					sender.clone(),
+					recipient.clone(),
+					sealed_key.clone(),
+					key_id,
+					record_id,
+					validity,
+				)
+				.expect("the sender has room for more grants");
+			}
+		}
+		let block = frame_system::Pallet::<T>::block_number() + 10u32.into();
+		frame_system::Pallet::<T>::set_block_number(block);
+	}: {
+		RecordSharing::<T>::expire_sharings(block, block);
+	}
 	verify {
-		assert_eq!(Something::<T>::get(), Some(s));
+		assert!(SharingExpiries::<T>::get(block).is_empty());
+		for sender in senders.iter() {
+			let grants = SharedRecords::<T>::get(sender, &recipient);
+			assert_eq!(grants.len() as u32, T::MaxSharedRecords::get() - 1);
+		}
 	}
 
-	impl_benchmark_test_suite!(Template, crate::mock::new_test_ext(), crate::mock::Test);
+	impl_benchmark_test_suite!(RecordSharing, crate::mock::new_test_ext(), crate::mock::Test);
 }
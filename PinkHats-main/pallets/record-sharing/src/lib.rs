@@ -6,6 +6,9 @@ extern crate alloc;
 /// <https://docs.substrate.io/reference/frame-pallets/>
 pub use pallet::*;
 
+use frame_support::pallet_prelude::{DispatchResult, MaxEncodedLen, Parameter};
+use sp_std::vec::Vec;
+
 #[cfg(test)]
 mod mock;
 
@@ -15,13 +18,29 @@ mod tests;
 #[cfg(feature = "runtime-benchmarks")]
 mod benchmarking;
 
+pub mod migrations;
+pub mod weights;
+pub use weights::WeightInfo;
+
 #[frame_support::pallet]
 pub mod pallet {
-	use frame_support::{pallet_prelude::*, BoundedVec};
+	use crate::{RecordProvider, WeightInfo};
+	use frame_support::{
+		pallet_prelude::*,
+		traits::{Currency, ReservableCurrency},
+		BoundedVec,
+	};
 	use frame_system::pallet_prelude::*;
+	use pallet_record_sharing_runtime_api as api;
+	use sp_runtime::traits::{One, Saturating, Zero};
+	use sp_std::vec::Vec;
+
+	/// The current storage version.
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
 
 	#[pallet::pallet]
 	#[pallet::generate_store(pub(super) trait Store)]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	/// Configure the pallet by specifying the parameters and types on which it depends.
@@ -29,10 +48,131 @@ pub mod pallet {
 	pub trait Config: frame_system::Config {
 		/// Because this pallet emits events, it depends on the runtime's definition of an event.
 		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
-		type MaxKeyLength: Get<u32>;
+		/// How many records a sender can share with the same recipient.
+		type MaxSharedRecords: Get<u32>;
+		/// Maximum length in bytes of a sealed record key.
+		type MaxCiphertextLength: Get<u32>;
+		/// How many time-bounded sharings can expire in the same block.
+		type MaxExpiriesPerBlock: Get<u32>;
+		/// The pallet that keeps the accounts and records being shared.
+		type Records: RecordProvider<Self::AccountId>;
+		/// How many encryption keys of an account are kept, including the current one.
+		type MaxKeyHistory: Get<u32>;
+		/// The currency grant deposits are reserved in.
+		type Currency: ReservableCurrency<Self::AccountId>;
+		/// Deposit reserved from the sender for each byte a grant takes up in storage.
+		#[pallet::constant]
+		type DepositPerByte: Get<BalanceOf<Self>>;
+		/// Weight information for extrinsics in this pallet.
+		type WeightInfo: WeightInfo;
+		/// Creates the accounts and records the benchmarks share.
+		#[cfg(feature = "runtime-benchmarks")]
+		type BenchmarkHelper: crate::BenchmarkHelper<Self::AccountId>;
+	}
+	pub type RecordId = u32;
+	pub type BalanceOf<T> =
+		<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+	type GrantInfo<T> = api::GrantInfo<
+		<T as frame_system::Config>::AccountId,
+		<T as frame_system::Config>::BlockNumber,
+	>;
+	pub type EncryptedKey<T> = SealedEnvelope<T>;
+	pub type KeyId = u32;
+	/// An X25519 public key used to wrap record keys for its owner.
+	pub type EncryptionPublicKey = [u8; 32];
+
+	/// The `SealedEnvelope` format version this runtime understands.
+	pub const ENVELOPE_VERSION: u8 = 1;
+	/// X25519 key agreement with XSalsa20-Poly1305, as in NaCl's `crypto_box`.
+	pub const ALGORITHM_X25519_XSALSA20_POLY1305: u8 = 1;
+	/// Sealing algorithms this runtime accepts.
+	pub const SUPPORTED_ALGORITHMS: [u8; 1] = [ALGORITHM_X25519_XSALSA20_POLY1305];
+
+	/// A record key sealed for a recipient's encryption key.
+	///
+	/// The sender derives a shared secret from `ephemeral_public_key` and the recipient's public
+	/// key, then encrypts the record key with it under `nonce` into `ciphertext`.
+	#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, MaxEncodedLen, TypeInfo)]
+	#[scale_info(skip_type_params(T))]
+	pub struct SealedEnvelope<T: Config> {
+		pub version: u8,
+		pub algorithm: u8,
+		pub ephemeral_public_key: [u8; 32],
+		pub nonce: [u8; 24],
+		pub ciphertext: BoundedVec<u8, T::MaxCiphertextLength>,
+	}
+
+	/// A published encryption key. Key ids start at 1 and grow with every rotation.
+	#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, MaxEncodedLen, TypeInfo)]
+	pub struct EncryptionKey<BlockNumber> {
+		pub key_id: KeyId,
+		pub public_key: EncryptionPublicKey,
+		pub registered_at: BlockNumber,
+	}
+
+	/// How an actor came to act on a patient's records.
+	#[derive(Decode, Encode, Clone, Copy, Debug, Eq, PartialEq, MaxEncodedLen, TypeInfo)]
+	pub enum AccessPath {
+		/// The patient acted themselves.
+		Direct,
+		/// A guardian acted on the patient's behalf.
+		Guardian,
+		/// A responder broke the glass on the patient's emergency profile.
+		Emergency,
+		/// The permissions matrix allowed the actor's role to act.
+		Policy,
+		/// A runtime upgrade acted, e.g. dropped a grant that no longer fit in storage.
+		Upgrade,
+	}
+
+	/// What an actor did to a patient's records, as kept in the patient's audit log.
+	#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, MaxEncodedLen, TypeInfo)]
+	pub enum AuditAction<AccountId> {
+		Grant { recipient: AccountId },
+		Revoke { recipient: AccountId },
+		Verify,
+		BreakGlass,
+		SetEmergencyProfile,
+		ClearEmergencyProfile,
+	}
+
+	/// Blocks in which a sharing can be used: from `starts_at` up to, but excluding, `ends_at`.
+	#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, MaxEncodedLen, TypeInfo)]
+	pub struct ValidityWindow<BlockNumber> {
+		pub starts_at: BlockNumber,
+		pub ends_at: BlockNumber,
+	}
+
+	/// A record shared with a recipient, together with the key to decrypt it.
+	#[derive(Decode, Encode, Clone, Debug, Eq, PartialEq, MaxEncodedLen, TypeInfo)]
+	#[scale_info(skip_type_params(T))]
+	pub struct Grant<T: Config> {
+		pub encrypted_key: EncryptedKey<T>,
+		/// The recipient's encryption key `encrypted_key` was wrapped for.
+		pub recipient_key_id: KeyId,
+		pub record_id: RecordId,
+		/// `None` if the sharing never expires.
+		pub validity: Option<ValidityWindow<T::BlockNumber>>,
+		/// Reserved from the sender until the grant is revoked or expires.
+		pub deposit: BalanceOf<T>,
+	}
+
+	impl<T: Config> Grant<T> {
+		pub fn is_active(&self, now: T::BlockNumber) -> bool {
+			match &self.validity {
+				None => true,
+				Some(window) => window.starts_at <= now && now < window.ends_at,
+			}
+		}
+
+		pub fn is_expired(&self, now: T::BlockNumber) -> bool {
+			match &self.validity {
+				None => false,
+				Some(window) => window.ends_at <= now,
+			}
+		}
 	}
-	type RecordId = u32;
-	pub type EncryptedKey<T> = BoundedVec<u32, <T as Config>::MaxKeyLength>;
+
 	// The pallet's runtime storage items.
 	// https://docs.substrate.io/main-docs/build/runtime-storage/
 	#[pallet::storage]
@@ -45,16 +185,73 @@ pub mod pallet {
 		T::AccountId,
 		Blake2_128Concat,
 		T::AccountId,
-		BoundedVec<(EncryptedKey<T>, RecordId), T::MaxKeyLength>,
+		BoundedVec<Grant<T>, T::MaxSharedRecords>,
+		ValueQuery,
+	>;
+
+	// The senders sharing records with each recipient, so a recipient's grants are found without
+	// going through every sharing.
+	#[pallet::storage]
+	pub type GrantsByRecipient<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		Blake2_128Concat,
+		T::AccountId,
+		(),
+		OptionQuery,
+	>;
+
+	// Time-bounded sharings indexed by the block they expire at, as
+	// (sender, recipient, record id).
+	#[pallet::storage]
+	pub type SharingExpiries<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		T::BlockNumber,
+		BoundedVec<(T::AccountId, T::AccountId, RecordId), T::MaxExpiriesPerBlock>,
+		ValueQuery,
+	>;
+
+	// The encryption keys each account published, oldest first. The last one is current.
+	#[pallet::storage]
+	#[pallet::getter(fn encryption_keys)]
+	pub type EncryptionKeys<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		BoundedVec<EncryptionKey<T::BlockNumber>, T::MaxKeyHistory>,
 		ValueQuery,
 	>;
 
+	// The next block whose expiries `on_idle` has not processed yet.
+	#[pallet::storage]
+	pub type NextExpiryBlock<T: Config> = StorageValue<_, T::BlockNumber>;
+
 	// Pallets use events to inform users when important changes are made.
 	// https://docs.substrate.io/main-docs/build/events-errors/
 	#[pallet::event]
 	#[pallet::generate_deposit(pub(super) fn deposit_event)]
 	pub enum Event<T: Config> {
 		SharingStored(T::AccountId, RecordId),
+		/// A single record shared by the sender was revoked from the recipient.
+		/// [sender, recipient, record_id]
+		SharingRevoked(T::AccountId, T::AccountId, RecordId),
+		/// Every record shared by the sender was revoked from the recipient.
+		/// [sender, recipient]
+		AllSharingsRevoked(T::AccountId, T::AccountId),
+		/// A time-bounded sharing reached the end of its validity window and was removed.
+		/// [sender, recipient, record_id]
+		ShareExpired(T::AccountId, T::AccountId, RecordId),
+		/// An account published a new current encryption key. [account, key_id]
+		EncryptionKeyRegistered(T::AccountId, KeyId),
+		/// A deposit was reserved for a new grant. [sender, amount]
+		GrantDepositReserved(T::AccountId, BalanceOf<T>),
+		/// The deposit of removed grants was returned. [sender, amount]
+		GrantDepositReturned(T::AccountId, BalanceOf<T>),
+		/// A grant made before grants were bounded did not fit in storage and was dropped by the
+		/// runtime upgrade. [sender, recipient, record_id]
+		LegacyGrantDropped(T::AccountId, T::AccountId, RecordId),
 	}
 
 	// Errors inform users that something went wrong.
@@ -73,26 +270,557 @@ pub mod pallet {
 		//Key is too long
 		KeyTooLong,
 		VectorFull,
+		// The record was never shared with this user, or the sharing was already revoked.
+		SharingNotFound,
+		// The validity window is empty or already over.
+		InvalidValidityWindow,
+		// Too many sharings already expire in the requested block.
+		TooManyExpiriesInBlock,
+		// The key was not wrapped for the recipient's current encryption key.
+		UnknownEncryptionKey,
+		// The sealed key uses a format version this runtime does not understand.
+		UnsupportedEnvelopeVersion,
+		// The sealed key uses an algorithm this runtime does not accept.
+		UnsupportedAlgorithm,
+		// None of the recipient's roles may receive this kind of record.
+		RecipientNotPermitted,
+		// More records are shared with the recipient than the caller said.
+		GrantCountTooLow,
+	}
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig<T: Config> {
+		pub encryption_keys: Vec<(T::AccountId, EncryptionPublicKey)>,
+	}
+
+	#[cfg(feature = "std")]
+	impl<T: Config> Default for GenesisConfig<T> {
+		fn default() -> Self {
+			Self { encryption_keys: Default::default() }
+		}
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
+		fn build(&self) {
+			for (account_id, public_key) in self.encryption_keys.iter() {
+				<EncryptionKeys<T>>::mutate(account_id, |keys| {
+					let key_id = keys.last().map_or(1, |key| key.key_id.saturating_add(1));
+					let key = EncryptionKey {
+						key_id,
+						public_key: *public_key,
+						registered_at: Zero::zero(),
+					};
+					keys.try_push(key).expect("genesis keys must fit the key history");
+				});
+			}
+		}
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_idle(now: T::BlockNumber, remaining_weight: Weight) -> Weight {
+			Self::remove_expired_sharings(now, remaining_weight)
+		}
 	}
 
 	#[pallet::call]
 	impl<T: Config> Pallet<T> {
-		#[pallet::weight(10_000 + T::DbWeight::get().writes(1).ref_time())]
+		#[pallet::weight(T::WeightInfo::share_record())]
 		pub fn share_record(
 			origin: OriginFor<T>,
 			recipient: T::AccountId,
 			encrypted_key: EncryptedKey<T>,
+			recipient_key_id: KeyId,
+			record_id: RecordId,
+			validity: Option<ValidityWindow<T::BlockNumber>>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::do_share_record(
+				who.clone(),
+				recipient.clone(),
+				encrypted_key,
+				recipient_key_id,
+				record_id,
+				validity,
+			)?;
+
+			T::Records::log_access(
+				&who,
+				&who,
+				AuditAction::Grant { recipient },
+				Some(record_id),
+				AccessPath::Direct,
+			);
+			Ok(())
+		}
+
+		#[pallet::weight(T::WeightInfo::revoke_share())]
+		pub fn revoke_share(
+			origin: OriginFor<T>,
+			recipient: T::AccountId,
 			record_id: RecordId,
 		) -> DispatchResult {
 			let who = ensure_signed(origin)?;
+			Self::do_revoke_share(who.clone(), recipient.clone(), record_id)?;
+
+			T::Records::log_access(
+				&who,
+				&who,
+				AuditAction::Revoke { recipient },
+				Some(record_id),
+				AccessPath::Direct,
+			);
+			Ok(())
+		}
+
+		// Revoke every record shared with the recipient. Every grant is logged, so the call is
+		// weighed by `grant_count`, which must be at least the number of grants.
+		#[pallet::weight(T::WeightInfo::revoke_all_shares(*grant_count))]
+		pub fn revoke_all_shares(
+			origin: OriginFor<T>,
+			recipient: T::AccountId,
+			grant_count: u32,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			ensure!(
+				<SharedRecords<T>>::contains_key(&who, &recipient),
+				Error::<T>::SharingNotFound
+			);
+			let shared = <SharedRecords<T>>::get(&who, &recipient);
+			ensure!(shared.len() as u32 <= grant_count, Error::<T>::GrantCountTooLow);
+			<SharedRecords<T>>::remove(&who, &recipient);
+			<GrantsByRecipient<T>>::remove(&recipient, &who);
+
+			Self::return_deposit(&who, shared.iter().map(|grant| grant.deposit));
+			for grant in shared.iter() {
+				T::Records::log_access(
+					&who,
+					&who,
+					AuditAction::Revoke { recipient: recipient.clone() },
+					Some(grant.record_id),
+					AccessPath::Direct,
+				);
+			}
+			Self::deposit_event(Event::<T>::AllSharingsRevoked(who, recipient));
+			Ok(())
+		}
+
+		// Publish a new encryption key. It becomes the key others wrap record keys for;
+		// the oldest key is forgotten once the history is full.
+		#[pallet::weight(T::WeightInfo::register_encryption_key())]
+		pub fn register_encryption_key(
+			origin: OriginFor<T>,
+			public_key: EncryptionPublicKey,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let key_id = <EncryptionKeys<T>>::mutate(&who, |keys| {
+				let key_id = keys.last().map_or(1, |key| key.key_id.saturating_add(1));
+				if keys.is_full() {
+					keys.remove(0);
+				}
+				let key = EncryptionKey {
+					key_id,
+					public_key,
+					registered_at: <frame_system::Pallet<T>>::block_number(),
+				};
+				keys.try_push(key).expect("a key was removed if the history was full; qed");
+				key_id
+			});
+
+			Self::deposit_event(Event::<T>::EncryptionKeyRegistered(who, key_id));
+			Ok(())
+		}
+	}
 
-			<SharedRecords<T>>::try_mutate(who, recipient.clone(), |x| {
-				x.try_push((encrypted_key, record_id)).map_err(|_| Error::<T>::VectorFull)?;
+	impl<T: Config> Pallet<T> {
+		// Share a record owned by `who` with a registered `recipient`.
+		pub(crate) fn do_share_record(
+			who: T::AccountId,
+			recipient: T::AccountId,
+			encrypted_key: EncryptedKey<T>,
+			recipient_key_id: KeyId,
+			record_id: RecordId,
+			validity: Option<ValidityWindow<T::BlockNumber>>,
+		) -> DispatchResult {
+			ensure!(
+				T::Records::is_registered(&who) && T::Records::is_registered(&recipient),
+				Error::<T>::UserUnregistered
+			);
+			ensure!(T::Records::is_record_owner(&who, record_id), Error::<T>::WrongRecordId);
+			ensure!(
+				T::Records::may_receive(&recipient, &who, record_id),
+				Error::<T>::RecipientNotPermitted
+			);
+			ensure!(
+				encrypted_key.version == ENVELOPE_VERSION,
+				Error::<T>::UnsupportedEnvelopeVersion
+			);
+			ensure!(
+				SUPPORTED_ALGORITHMS.contains(&encrypted_key.algorithm),
+				Error::<T>::UnsupportedAlgorithm
+			);
+			ensure!(
+				Self::current_encryption_key(&recipient).map(|key| key.key_id) ==
+					Some(recipient_key_id),
+				Error::<T>::UnknownEncryptionKey
+			);
+
+			if let Some(window) = &validity {
+				let now = <frame_system::Pallet<T>>::block_number();
+				ensure!(
+					window.starts_at < window.ends_at && now < window.ends_at,
+					Error::<T>::InvalidValidityWindow
+				);
+				<SharingExpiries<T>>::try_append(
+					window.ends_at,
+					(who.clone(), recipient.clone(), record_id),
+				)
+				.map_err(|_| Error::<T>::TooManyExpiriesInBlock)?;
+			}
+
+			let mut grant = Grant {
+				encrypted_key,
+				recipient_key_id,
+				record_id,
+				validity,
+				deposit: Zero::zero(),
+			};
+			grant.deposit =
+				T::DepositPerByte::get().saturating_mul((grant.encoded_size() as u32).into());
+			let deposit = grant.deposit;
+
+			T::Currency::reserve(&who, deposit)?;
+			<SharedRecords<T>>::try_mutate(&who, recipient.clone(), |x| {
+				x.try_push(grant).map_err(|_| Error::<T>::VectorFull)?;
 				Ok::<(), Error<T>>(())
 			})?;
+			<GrantsByRecipient<T>>::insert(&recipient, &who, ());
 
+			Self::deposit_event(Event::<T>::GrantDepositReserved(who, deposit));
 			Self::deposit_event(Event::<T>::SharingStored(recipient, record_id));
 			Ok(())
 		}
+
+		pub(crate) fn do_revoke_share(
+			who: T::AccountId,
+			recipient: T::AccountId,
+			record_id: RecordId,
+		) -> DispatchResult {
+			let removed =
+				<SharedRecords<T>>::try_mutate_exists(&who, &recipient, |maybe_shared| {
+					let shared = maybe_shared.as_mut().ok_or(Error::<T>::SharingNotFound)?;
+					let removed = Self::remove_grants(shared, |grant| grant.record_id == record_id);
+					ensure!(!removed.is_empty(), Error::<T>::SharingNotFound);
+					if shared.is_empty() {
+						*maybe_shared = None;
+					}
+					Ok::<_, Error<T>>(removed)
+				})?;
+			Self::unindex_if_empty(&who, &recipient);
+
+			Self::return_deposit(&who, removed.iter().map(|grant| grant.deposit));
+			Self::deposit_event(Event::<T>::SharingRevoked(who, recipient, record_id));
+			Ok(())
+		}
+
+		// Revoke the record from every recipient `who` shared it with, and return them.
+		pub(crate) fn do_revoke_record(
+			who: T::AccountId,
+			record_id: RecordId,
+		) -> Vec<T::AccountId> {
+			let recipients: Vec<T::AccountId> = <SharedRecords<T>>::iter_prefix(&who)
+				.filter(|(_, shared)| shared.iter().any(|grant| grant.record_id == record_id))
+				.map(|(recipient, _)| recipient)
+				.collect();
+
+			for recipient in recipients.iter().cloned() {
+				let removed = <SharedRecords<T>>::mutate_exists(&who, &recipient, |maybe_shared| {
+					let shared = match maybe_shared {
+						Some(shared) => shared,
+						None => return Vec::new(),
+					};
+					let removed = Self::remove_grants(shared, |grant| grant.record_id == record_id);
+					if shared.is_empty() {
+						*maybe_shared = None;
+					}
+					removed
+				});
+				Self::unindex_if_empty(&who, &recipient);
+				Self::return_deposit(&who, removed.iter().map(|grant| grant.deposit));
+				Self::deposit_event(Event::<T>::SharingRevoked(who.clone(), recipient, record_id));
+			}
+			recipients
+		}
+
+		// Remove the grants matching `filter` from `shared` and return them.
+		fn remove_grants(
+			shared: &mut BoundedVec<Grant<T>, T::MaxSharedRecords>,
+			filter: impl Fn(&Grant<T>) -> bool,
+		) -> Vec<Grant<T>> {
+			let mut removed = Vec::new();
+			shared.retain(|grant| {
+				if filter(grant) {
+					removed.push(grant.clone());
+					false
+				} else {
+					true
+				}
+			});
+			removed
+		}
+
+		// Drop `sender` from the senders of `recipient` once none of their grants are left.
+		fn unindex_if_empty(sender: &T::AccountId, recipient: &T::AccountId) {
+			if !<SharedRecords<T>>::contains_key(sender, recipient) {
+				<GrantsByRecipient<T>>::remove(recipient, sender);
+			}
+		}
+
+		// Return the deposits of removed grants to their sender.
+		fn return_deposit(who: &T::AccountId, deposits: impl Iterator<Item = BalanceOf<T>>) {
+			let deposit = deposits
+				.fold(Zero::zero(), |total: BalanceOf<T>, deposit| total.saturating_add(deposit));
+			if deposit.is_zero() {
+				return
+			}
+			T::Currency::unreserve(who, deposit);
+			Self::deposit_event(Event::<T>::GrantDepositReturned(who.clone(), deposit));
+		}
+
+		// The key others should wrap record keys for when sharing with `account`.
+		pub fn current_encryption_key(
+			account: &T::AccountId,
+		) -> Option<EncryptionKey<T::BlockNumber>> {
+			Self::encryption_keys(account).last().cloned()
+		}
+
+		// The grants `sender` gave that have not expired, for the `RecordSharingApi` runtime API.
+		pub fn grants_by(
+			sender: &T::AccountId,
+			offset: u32,
+			limit: u32,
+		) -> api::Page<GrantInfo<T>> {
+			let grants: Vec<GrantInfo<T>> = <SharedRecords<T>>::iter_prefix(sender)
+				.flat_map(|(recipient, shared)| Self::to_grant_infos(sender, &recipient, shared))
+				.collect();
+			Self::page(grants, offset, limit)
+		}
+
+		// The grants `recipient` received that have not expired, for the `RecordSharingApi`
+		// runtime API.
+		pub fn grants_to(
+			recipient: &T::AccountId,
+			offset: u32,
+			limit: u32,
+		) -> api::Page<GrantInfo<T>> {
+			let grants: Vec<GrantInfo<T>> = <GrantsByRecipient<T>>::iter_key_prefix(recipient)
+				.flat_map(|sender| {
+					let shared = Self::records_shared(&sender, recipient);
+					Self::to_grant_infos(&sender, recipient, shared)
+				})
+				.collect();
+			Self::page(grants, offset, limit)
+		}
+
+		fn page(grants: Vec<GrantInfo<T>>, offset: u32, limit: u32) -> api::Page<GrantInfo<T>> {
+			let total = grants.len() as u32;
+			let items = grants.into_iter().skip(offset as usize).take(limit as usize).collect();
+			api::Page { items, total }
+		}
+
+		// Expired grants may not have been removed by `on_idle` yet, so they are left out here.
+		fn to_grant_infos(
+			sender: &T::AccountId,
+			recipient: &T::AccountId,
+			shared: BoundedVec<Grant<T>, T::MaxSharedRecords>,
+		) -> Vec<GrantInfo<T>> {
+			let now = <frame_system::Pallet<T>>::block_number();
+			shared
+				.into_iter()
+				.filter(|grant| !grant.is_expired(now))
+				.map(|grant| api::GrantInfo {
+					sender: sender.clone(),
+					recipient: recipient.clone(),
+					record_id: grant.record_id,
+					sealed_key: api::SealedKey {
+						version: grant.encrypted_key.version,
+						algorithm: grant.encrypted_key.algorithm,
+						ephemeral_public_key: grant.encrypted_key.ephemeral_public_key,
+						nonce: grant.encrypted_key.nonce,
+						ciphertext: grant.encrypted_key.ciphertext.into_inner(),
+					},
+					recipient_key_id: grant.recipient_key_id,
+					validity: grant.validity.map(|window| (window.starts_at, window.ends_at)),
+				})
+				.collect()
+		}
+
+		// The sharings from `sender` to `recipient` that can be used in the current block.
+		pub fn active_shares(sender: &T::AccountId, recipient: &T::AccountId) -> Vec<Grant<T>> {
+			let now = <frame_system::Pallet<T>>::block_number();
+			Self::records_shared(sender, recipient)
+				.into_iter()
+				.filter(|grant| grant.is_active(now))
+				.collect()
+		}
+
+		// Remove the sharings that expired up to `now`, for as many blocks as the weight allows.
+		// The expiries of a block are only removed if all of them fit in the remaining weight.
+		fn remove_expired_sharings(now: T::BlockNumber, remaining_weight: Weight) -> Weight {
+			let db_weight = T::DbWeight::get();
+			let mut used_weight = db_weight.reads_writes(1, 1);
+			if remaining_weight
+				.any_lt(used_weight.saturating_add(T::WeightInfo::expire_sharings(0)))
+			{
+				return Weight::zero()
+			}
+
+			let mut block = <NextExpiryBlock<T>>::get().unwrap_or(now);
+			while block <= now &&
+				used_weight.saturating_add(db_weight.reads(1)).all_lte(remaining_weight)
+			{
+				let expiries = <SharingExpiries<T>>::decode_len(block).unwrap_or(0) as u32;
+				let block_weight = T::WeightInfo::expire_sharings(expiries);
+				if used_weight.saturating_add(block_weight).any_gt(remaining_weight) {
+					// The expiries were counted but are left for a later block.
+					used_weight = used_weight.saturating_add(db_weight.reads(1));
+					break
+				}
+				Self::expire_sharings(block, now);
+				used_weight = used_weight.saturating_add(block_weight);
+				block += One::one();
+			}
+			<NextExpiryBlock<T>>::put(block);
+
+			used_weight
+		}
+
+		// Remove the sharings set to expire in `block`.
+		pub(crate) fn expire_sharings(block: T::BlockNumber, now: T::BlockNumber) {
+			for (sender, recipient, record_id) in <SharingExpiries<T>>::take(block) {
+				Self::remove_expired_sharing(sender, recipient, record_id, now);
+			}
+		}
+
+		// Revoked or re-shared records may no longer have an expired grant, so only
+		// grants that are actually expired are removed.
+		fn remove_expired_sharing(
+			sender: T::AccountId,
+			recipient: T::AccountId,
+			record_id: RecordId,
+			now: T::BlockNumber,
+		) {
+			let expired = <SharedRecords<T>>::mutate_exists(&sender, &recipient, |maybe_shared| {
+				let shared = match maybe_shared {
+					Some(shared) => shared,
+					None => return Vec::new(),
+				};
+				let expired = Self::remove_grants(shared, |grant| {
+					grant.record_id == record_id && grant.is_expired(now)
+				});
+				if shared.is_empty() {
+					*maybe_shared = None;
+				}
+				expired
+			});
+
+			if !expired.is_empty() {
+				Self::unindex_if_empty(&sender, &recipient);
+				Self::return_deposit(&sender, expired.iter().map(|grant| grant.deposit));
+				// The sender chose the validity window, so the expiry is logged as their
+				// revocation.
+				T::Records::log_access(
+					&sender,
+					&sender,
+					AuditAction::Revoke { recipient: recipient.clone() },
+					Some(record_id),
+					AccessPath::Direct,
+				);
+				Self::deposit_event(Event::<T>::ShareExpired(sender, recipient, record_id));
+			}
+		}
+	}
+}
+
+/// Lets this pallet check the accounts and records kept by the pallet owning them.
+pub trait RecordProvider<AccountId> {
+	/// Whether `account` is registered and may share or receive records.
+	fn is_registered(account: &AccountId) -> bool;
+	/// Whether the record `record_id` belongs to `owner`.
+	fn is_record_owner(owner: &AccountId, record_id: RecordId) -> bool;
+	/// Whether `recipient` may have the record `record_id` of `owner` shared with them.
+	fn may_receive(recipient: &AccountId, owner: &AccountId, record_id: RecordId) -> bool;
+	/// Add an entry to the audit log of `patient`.
+	fn log_access(
+		patient: &AccountId,
+		actor: &AccountId,
+		action: AuditAction<AccountId>,
+		record_id: Option<RecordId>,
+		path: AccessPath,
+	);
+}
+
+/// Creates the accounts and records the benchmarks share, in the pallet that keeps them.
+#[cfg(feature = "runtime-benchmarks")]
+pub trait BenchmarkHelper<AccountId> {
+	/// Register `account`, which can already pay deposits, so it may share and receive records.
+	fn register(account: &AccountId);
+	/// Add a record for `owner` that every registered account may receive, and return its id.
+	fn add_record(owner: &AccountId) -> RecordId;
+}
+
+/// Lets other pallets share records on behalf of their users.
+pub trait SharingManager<AccountId, BlockNumber> {
+	type EncryptedKey: Parameter + MaxEncodedLen;
+
+	fn share(
+		sender: AccountId,
+		recipient: AccountId,
+		encrypted_key: Self::EncryptedKey,
+		recipient_key_id: KeyId,
+		record_id: RecordId,
+		validity: Option<ValidityWindow<BlockNumber>>,
+	) -> DispatchResult;
+
+	fn revoke(sender: AccountId, recipient: AccountId, record_id: RecordId) -> DispatchResult;
+
+	/// Revoke the record from everyone the sender shared it with, and return who they were.
+	fn revoke_record(sender: AccountId, record_id: RecordId) -> Vec<AccountId>;
+}
+
+impl<T: Config> SharingManager<T::AccountId, T::BlockNumber> for Pallet<T> {
+	type EncryptedKey = EncryptedKey<T>;
+
+	fn share(
+		sender: T::AccountId,
+		recipient: T::AccountId,
+		encrypted_key: Self::EncryptedKey,
+		recipient_key_id: KeyId,
+		record_id: RecordId,
+		validity: Option<ValidityWindow<T::BlockNumber>>,
+	) -> DispatchResult {
+		Self::do_share_record(
+			sender,
+			recipient,
+			encrypted_key,
+			recipient_key_id,
+			record_id,
+			validity,
+		)
+	}
+
+	fn revoke(
+		sender: T::AccountId,
+		recipient: T::AccountId,
+		record_id: RecordId,
+	) -> DispatchResult {
+		Self::do_revoke_share(sender, recipient, record_id)
+	}
+
+	fn revoke_record(sender: T::AccountId, record_id: RecordId) -> Vec<T::AccountId> {
+		Self::do_revoke_record(sender, record_id)
 	}
 }
@@ -0,0 +1,173 @@
+//! Storage migrations of the record sharing pallet.
+
+use crate::*;
+use frame_support::{
+	log,
+	pallet_prelude::*,
+	storage_alias,
+	traits::{OnRuntimeUpgrade, StorageVersion},
+};
+use sp_std::vec::Vec;
+
+/// The envelope version of record keys shared before envelopes existed.
+pub const LEGACY_ENVELOPE_VERSION: u8 = 0;
+
+/// The storage layout the pallet was first deployed with.
+pub mod v0 {
+	use super::*;
+
+	/// A record key as it was first stored. It is decoded as a vector, which encodes the same
+	/// way as the bounded vector it was stored as.
+	pub type EncryptedKey = Vec<u32>;
+
+	/// Sharings as first stored: the key of each shared record next to its id.
+	#[storage_alias]
+	pub type SharedRecords<T: Config> = StorageDoubleMap<
+		Pallet<T>,
+		Blake2_128Concat,
+		<T as frame_system::Config>::AccountId,
+		Blake2_128Concat,
+		<T as frame_system::Config>::AccountId,
+		Vec<(EncryptedKey, RecordId)>,
+		ValueQuery,
+	>;
+}
+
+/// Turns the sharings of the first layout into grants and indexes them in `GrantsByRecipient`.
+///
+/// A legacy key is kept as the little-endian bytes of its words, in an envelope of version
+/// `LEGACY_ENVELOPE_VERSION` with no ephemeral key or nonce. It was not wrapped for a
+/// registered key, so it gets key id 0, which no registered key has. Legacy grants never
+/// expire and no deposit was reserved for them.
+///
+/// The first layout allowed keys of up to `MaxKeyLength` words and as many grants per sender
+/// and recipient. `MaxCiphertextLength` has to be at least four times and `MaxSharedRecords`
+/// at least as large for every grant to be kept. A grant that does not fit is dropped, with a
+/// `LegacyGrantDropped` event and a `Revoke` entry in the sender's audit log.
+pub mod v1 {
+	use super::*;
+	use sp_runtime::traits::Zero;
+
+	// Wrap a legacy record key in an envelope, if it fits.
+	fn seal_legacy_key<T: Config>(key: v0::EncryptedKey) -> Option<EncryptedKey<T>> {
+		let ciphertext: Vec<u8> = key.into_iter().flat_map(u32::to_le_bytes).collect();
+		Some(SealedEnvelope {
+			version: LEGACY_ENVELOPE_VERSION,
+			algorithm: 0,
+			ephemeral_public_key: [0; 32],
+			nonce: [0; 24],
+			ciphertext: ciphertext.try_into().ok()?,
+		})
+	}
+
+	// Tell the sender and their audit log about a grant the upgrade could not keep.
+	fn drop_legacy_grant<T: Config>(
+		sender: &T::AccountId,
+		recipient: &T::AccountId,
+		record_id: RecordId,
+	) {
+		T::Records::log_access(
+			sender,
+			sender,
+			AuditAction::Revoke { recipient: recipient.clone() },
+			Some(record_id),
+			AccessPath::Upgrade,
+		);
+		Pallet::<T>::deposit_event(Event::<T>::LegacyGrantDropped(
+			sender.clone(),
+			recipient.clone(),
+			record_id,
+		));
+	}
+
+	pub struct MigrateToV1<T>(sp_std::marker::PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() >= 1 {
+				return T::DbWeight::get().reads(1)
+			}
+
+			let (mut translated, mut dropped) = (0u64, 0u64);
+			<SharedRecords<T>>::translate::<Vec<(v0::EncryptedKey, RecordId)>, _>(
+				|sender, recipient, sharings| {
+					translated += 1;
+					let mut grants = BoundedVec::<Grant<T>, T::MaxSharedRecords>::default();
+					for (encrypted_key, record_id) in sharings {
+						let kept = seal_legacy_key::<T>(encrypted_key).map_or(false, |key| {
+							let grant = Grant {
+								encrypted_key: key,
+								recipient_key_id: 0,
+								record_id,
+								validity: None,
+								deposit: Zero::zero(),
+							};
+							grants.try_push(grant).is_ok()
+						});
+						if !kept {
+							dropped += 1;
+							drop_legacy_grant::<T>(&sender, &recipient, record_id);
+						}
+					}
+					if grants.is_empty() {
+						return None
+					}
+					<GrantsByRecipient<T>>::insert(recipient, sender, ());
+					Some(grants)
+				},
+			);
+			if dropped > 0 {
+				log::warn!(
+					target: "runtime::record-sharing",
+					"dropped {} grants whose keys or number exceed the bounds",
+					dropped,
+				);
+			}
+			StorageVersion::new(1).put::<Pallet<T>>();
+
+			T::DbWeight::get().reads_writes(translated + dropped + 1, 2 * translated + dropped + 1)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+			ensure!(
+				Pallet::<T>::on_chain_storage_version() == 0,
+				"can only upgrade from version 0"
+			);
+			let max_ciphertext = T::MaxCiphertextLength::get() as usize;
+			let kept = v0::SharedRecords::<T>::iter_values()
+				.map(|sharings| {
+					let fitting =
+						sharings.iter().filter(|(key, _)| key.len() * 4 <= max_ciphertext).count();
+					fitting.min(T::MaxSharedRecords::get() as usize) as u32
+				})
+				.sum::<u32>();
+			Ok(kept.encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), &'static str> {
+			ensure!(Pallet::<T>::on_chain_storage_version() == 1, "must upgrade to version 1");
+			let grants: u32 =
+				Decode::decode(&mut &state[..]).map_err(|_| "pre_upgrade state must decode")?;
+
+			ensure!(
+				<SharedRecords<T>>::iter_values().map(|grants| grants.len() as u32).sum::<u32>() ==
+					grants,
+				"every grant that fits must be kept"
+			);
+			ensure!(
+				<SharedRecords<T>>::iter_keys().all(|(sender, recipient)| {
+					<GrantsByRecipient<T>>::contains_key(recipient, sender)
+				}),
+				"every sharing must be indexed by its recipient"
+			);
+			ensure!(
+				<GrantsByRecipient<T>>::iter_keys().count() ==
+					<SharedRecords<T>>::iter_keys().count(),
+				"only sharings must be indexed"
+			);
+			Ok(())
+		}
+	}
+}
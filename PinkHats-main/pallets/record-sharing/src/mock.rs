@@ -1,5 +1,5 @@
-use crate as pallet_record_sharing;
-use frame_support::traits::{ConstU16, ConstU64};
+use crate::{self as pallet_record_sharing, AccessPath, AuditAction, RecordId, RecordProvider};
+use frame_support::traits::{ConstU16, ConstU64, GenesisBuild};
 use frame_system as system;
 use sp_core::H256;
 use sp_runtime::{
@@ -20,6 +20,7 @@ frame_support::construct_runtime!(
 		UncheckedExtrinsic = UncheckedExtrinsic,
 	{
 		System: frame_system,
+		Balances: pallet_balances,
 		RecordSharing: pallet_record_sharing,
 	}
 );
@@ -42,7 +43,7 @@ impl system::Config for Test {
 	type BlockHashCount = ConstU64<250>;
 	type Version = ();
 	type PalletInfo = PalletInfo;
-	type AccountData = ();
+	type AccountData = pallet_balances::AccountData<u64>;
 	type OnNewAccount = ();
 	type OnKilledAccount = ();
 	type SystemWeightInfo = ();
@@ -51,15 +52,86 @@ impl system::Config for Test {
 	type MaxConsumers = frame_support::traits::ConstU32<16>;
 }
 
+impl pallet_balances::Config for Test {
+	type Balance = u64;
+	type DustRemoval = ();
+	type RuntimeEvent = RuntimeEvent;
+	type ExistentialDeposit = ConstU64<1>;
+	type AccountStore = System;
+	type WeightInfo = ();
+	type MaxLocks = ();
+	type MaxReserves = ();
+	type ReserveIdentifier = [u8; 8];
+}
+
 parameter_types! {
-	pub const MockMaxKeyLength: u32 = 3;
+	pub const MockMaxSharedRecords: u32 = 3;
+	pub const MockMaxCiphertextLength: u32 = 48;
+	pub const MockMaxExpiriesPerBlock: u32 = 3;
+	pub const MockMaxKeyHistory: u32 = 2;
+	pub const MockDepositPerByte: u64 = 1;
 }
 impl pallet_record_sharing::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
-	type MaxKeyLength = MockMaxKeyLength;
+	type MaxSharedRecords = MockMaxSharedRecords;
+	type MaxCiphertextLength = MockMaxCiphertextLength;
+	type MaxExpiriesPerBlock = MockMaxExpiriesPerBlock;
+	type Records = MockRecords;
+	type MaxKeyHistory = MockMaxKeyHistory;
+	type Currency = Balances;
+	type DepositPerByte = MockDepositPerByte;
+	type WeightInfo = ();
+	#[cfg(feature = "runtime-benchmarks")]
+	type BenchmarkHelper = MockRecords;
+}
+
+pub const UNREGISTERED_ACCOUNT: u64 = 99;
+pub const UNKNOWN_RECORD_ID: RecordId = 99;
+pub const INITIAL_BALANCE: u64 = 1_000_000;
+
+// Every account but `UNREGISTERED_ACCOUNT` is registered and owns every record but
+// `UNKNOWN_RECORD_ID`.
+pub struct MockRecords;
+
+impl RecordProvider<u64> for MockRecords {
+	fn is_registered(account: &u64) -> bool {
+		*account != UNREGISTERED_ACCOUNT
+	}
+
+	fn is_record_owner(_owner: &u64, record_id: RecordId) -> bool {
+		record_id != UNKNOWN_RECORD_ID
+	}
+
+	fn may_receive(_recipient: &u64, _owner: &u64, _record_id: RecordId) -> bool {
+		true
+	}
+
+	fn log_access(
+		_patient: &u64,
+		_actor: &u64,
+		_action: AuditAction<u64>,
+		_record_id: Option<RecordId>,
+		_path: AccessPath,
+	) {
+	}
+}
+
+#[cfg(feature = "runtime-benchmarks")]
+impl pallet_record_sharing::BenchmarkHelper<u64> for MockRecords {
+	fn register(_account: &u64) {}
+
+	fn add_record(_owner: &u64) -> RecordId {
+		1
+	}
 }
 
 // Build genesis storage according to the mock runtime.
 pub fn new_test_ext() -> sp_io::TestExternalities {
-	system::GenesisConfig::default().build_storage::<Test>().unwrap().into()
+	let mut t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	pallet_balances::GenesisConfig::<Test> {
+		balances: (1..=3).map(|account_id| (account_id, INITIAL_BALANCE)).collect(),
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+	t.into()
 }
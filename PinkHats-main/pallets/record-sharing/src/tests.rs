@@ -1,18 +1,34 @@
-use crate::{mock::*, Error};
-use frame_support::{assert_noop, assert_ok, BoundedVec};
+use crate::{
+	migrations::{v1::MigrateToV1, LEGACY_ENVELOPE_VERSION},
+	mock::*,
+	Error, Event, Grant, GrantsByRecipient, SealedEnvelope, SharedRecords, ValidityWindow,
+	WeightInfo, ALGORITHM_X25519_XSALSA20_POLY1305, ENVELOPE_VERSION,
+};
+use codec::Encode;
+use frame_support::{
+	assert_noop, assert_ok, bounded_vec,
+	storage::{storage_prefix, unhashed},
+	traits::{Get, Hooks, OnRuntimeUpgrade, StorageVersion},
+	weights::Weight,
+	Blake2_128Concat, StorageHasher,
+};
+use pallet_record_sharing_runtime_api as api;
 pub type AccountId = u64;
 #[test]
 fn can_share_record() {
 	let (patient_account_id, patient) = generate_account(1);
 	let (doctor_account_id, _doctor) = generate_account(2);
 	new_test_ext().execute_with(|| {
-		let max_len = MockMaxKeyLength::get() as usize;
+		register_encryption_key(doctor_account_id);
+		let max_len = MockMaxSharedRecords::get() as usize;
 		for i in 0..max_len {
 			assert_ok!(RecordSharing::share_record(
 				patient.clone(),
 				doctor_account_id,
-				BoundedVec::with_max_capacity(),
+				sealed_key(),
+				1,
 				i as u32,
+				None,
 			));
 		}
 
@@ -23,14 +39,539 @@ fn can_share_record() {
 			RecordSharing::share_record(
 				patient.clone(),
 				doctor_account_id,
-				BoundedVec::with_max_capacity(),
+				sealed_key(),
+				1,
 				4_u32,
+				None,
 			),
 			Error::<Test>::VectorFull
 		);
 	})
 }
 
+#[test]
+fn cannot_share_with_unregistered_user_or_unknown_record() {
+	let (_patient_account_id, patient) = generate_account(1);
+	let (doctor_account_id, _doctor) = generate_account(2);
+	let (_unregistered_account_id, unregistered) = generate_account(UNREGISTERED_ACCOUNT);
+	new_test_ext().execute_with(|| {
+		register_encryption_key(doctor_account_id);
+		assert_noop!(
+			RecordSharing::share_record(
+				patient.clone(),
+				UNREGISTERED_ACCOUNT,
+				sealed_key(),
+				1,
+				1,
+				None,
+			),
+			Error::<Test>::UserUnregistered
+		);
+		assert_noop!(
+			RecordSharing::share_record(unregistered, doctor_account_id, sealed_key(), 1, 1, None,),
+			Error::<Test>::UserUnregistered
+		);
+		assert_noop!(
+			RecordSharing::share_record(
+				patient,
+				doctor_account_id,
+				sealed_key(),
+				1,
+				UNKNOWN_RECORD_ID,
+				None,
+			),
+			Error::<Test>::WrongRecordId
+		);
+	})
+}
+
+#[test]
+fn can_revoke_shared_record() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (doctor_account_id, _doctor) = generate_account(2);
+	new_test_ext().execute_with(|| {
+		register_encryption_key(doctor_account_id);
+		for record_id in 1..=2 {
+			assert_ok!(RecordSharing::share_record(
+				patient.clone(),
+				doctor_account_id,
+				sealed_key(),
+				1,
+				record_id,
+				None,
+			));
+		}
+
+		assert_ok!(RecordSharing::revoke_share(patient.clone(), doctor_account_id, 1));
+		let shared_records = RecordSharing::records_shared(patient_account_id, doctor_account_id);
+		assert_eq!(
+			shared_records.into_iter().map(|grant| grant.record_id).collect::<Vec<_>>(),
+			vec![2]
+		);
+
+		assert_noop!(
+			RecordSharing::revoke_share(patient.clone(), doctor_account_id, 1),
+			Error::<Test>::SharingNotFound
+		);
+
+		assert_ok!(RecordSharing::revoke_share(patient.clone(), doctor_account_id, 2));
+		assert!(!SharedRecords::<Test>::contains_key(patient_account_id, doctor_account_id));
+	})
+}
+
+#[test]
+fn can_revoke_all_shares_with_recipient() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (doctor_account_id, _doctor) = generate_account(2);
+	let (other_doctor_account_id, _other_doctor) = generate_account(3);
+	new_test_ext().execute_with(|| {
+		register_encryption_key(doctor_account_id);
+		register_encryption_key(other_doctor_account_id);
+		for recipient in [doctor_account_id, other_doctor_account_id] {
+			assert_ok!(RecordSharing::share_record(
+				patient.clone(),
+				recipient,
+				sealed_key(),
+				1,
+				1,
+				None,
+			));
+		}
+
+		assert_noop!(
+			RecordSharing::revoke_all_shares(patient.clone(), doctor_account_id, 0),
+			Error::<Test>::GrantCountTooLow
+		);
+		assert_ok!(RecordSharing::revoke_all_shares(patient.clone(), doctor_account_id, 1));
+		assert!(RecordSharing::records_shared(patient_account_id, doctor_account_id).is_empty());
+		assert_eq!(
+			RecordSharing::records_shared(patient_account_id, other_doctor_account_id).len(),
+			1
+		);
+
+		assert_noop!(
+			RecordSharing::revoke_all_shares(patient, doctor_account_id, 1),
+			Error::<Test>::SharingNotFound
+		);
+	})
+}
+
+#[test]
+fn time_bounded_sharing_expires() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (doctor_account_id, _doctor) = generate_account(2);
+	new_test_ext().execute_with(|| {
+		register_encryption_key(doctor_account_id);
+		System::set_block_number(1);
+		assert_noop!(
+			RecordSharing::share_record(
+				patient.clone(),
+				doctor_account_id,
+				sealed_key(),
+				1,
+				1,
+				Some(ValidityWindow { starts_at: 3, ends_at: 3 }),
+			),
+			Error::<Test>::InvalidValidityWindow
+		);
+		assert_ok!(RecordSharing::share_record(
+			patient.clone(),
+			doctor_account_id,
+			sealed_key(),
+			1,
+			1,
+			Some(ValidityWindow { starts_at: 2, ends_at: 4 }),
+		));
+		assert_ok!(RecordSharing::share_record(
+			patient,
+			doctor_account_id,
+			sealed_key(),
+			1,
+			2,
+			None,
+		));
+
+		// Not valid yet
+		assert_eq!(RecordSharing::active_shares(&patient_account_id, &doctor_account_id).len(), 1);
+		System::set_block_number(2);
+		assert_eq!(RecordSharing::active_shares(&patient_account_id, &doctor_account_id).len(), 2);
+
+		// Expired grants are ignored before they are cleaned up
+		System::set_block_number(4);
+		let active_shares = RecordSharing::active_shares(&patient_account_id, &doctor_account_id);
+		assert_eq!(
+			active_shares.into_iter().map(|grant| grant.record_id).collect::<Vec<_>>(),
+			vec![2]
+		);
+		assert_eq!(RecordSharing::records_shared(patient_account_id, doctor_account_id).len(), 2);
+
+		RecordSharing::on_idle(4, Weight::MAX);
+		assert_eq!(RecordSharing::records_shared(patient_account_id, doctor_account_id).len(), 1);
+		System::assert_last_event(
+			Event::ShareExpired(patient_account_id, doctor_account_id, 1).into(),
+		);
+	})
+}
+
+#[test]
+fn expiries_wait_for_enough_idle_weight() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (doctor_account_id, _doctor) = generate_account(2);
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		register_encryption_key(doctor_account_id);
+		assert_ok!(RecordSharing::share_record(
+			patient,
+			doctor_account_id,
+			sealed_key(),
+			1,
+			1,
+			Some(ValidityWindow { starts_at: 1, ends_at: 2 }),
+		));
+		System::set_block_number(2);
+
+		// Enough to move the cursor past blocks without expiries, but not to remove one
+		let cursor_weight = <Test as frame_system::Config>::DbWeight::get().reads_writes(1, 1);
+		let idle_weight = cursor_weight.saturating_add(<() as WeightInfo>::expire_sharings(0));
+		assert!(RecordSharing::on_idle(2, idle_weight).all_lte(idle_weight));
+		assert_eq!(RecordSharing::records_shared(patient_account_id, doctor_account_id).len(), 1);
+
+		let idle_weight = cursor_weight.saturating_add(<() as WeightInfo>::expire_sharings(1));
+		assert_eq!(RecordSharing::on_idle(2, idle_weight), idle_weight);
+		assert!(RecordSharing::records_shared(patient_account_id, doctor_account_id).is_empty());
+		System::assert_has_event(
+			Event::ShareExpired(patient_account_id, doctor_account_id, 1).into(),
+		);
+	})
+}
+
+#[test]
+fn grants_hold_a_deposit_until_removed() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (doctor_account_id, _doctor) = generate_account(2);
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		register_encryption_key(doctor_account_id);
+		for record_id in [1, 2] {
+			assert_ok!(RecordSharing::share_record(
+				patient.clone(),
+				doctor_account_id,
+				sealed_key(),
+				1,
+				record_id,
+				None,
+			));
+		}
+
+		let grant = RecordSharing::records_shared(patient_account_id, doctor_account_id)[0].clone();
+		assert_eq!(grant.deposit, grant.encoded_size() as u64 * MockDepositPerByte::get());
+		System::assert_has_event(
+			Event::GrantDepositReserved(patient_account_id, grant.deposit).into(),
+		);
+		assert_eq!(Balances::reserved_balance(patient_account_id), 2 * grant.deposit);
+
+		assert_ok!(RecordSharing::revoke_share(patient.clone(), doctor_account_id, 1));
+		System::assert_has_event(
+			Event::GrantDepositReturned(patient_account_id, grant.deposit).into(),
+		);
+		assert_eq!(Balances::reserved_balance(patient_account_id), grant.deposit);
+
+		assert_ok!(RecordSharing::revoke_all_shares(patient, doctor_account_id, 1));
+		assert_eq!(Balances::reserved_balance(patient_account_id), 0);
+		assert_eq!(Balances::free_balance(patient_account_id), INITIAL_BALANCE);
+	})
+}
+
+#[test]
+fn grants_are_served_as_runtime_api_types() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (doctor_account_id, _doctor) = generate_account(2);
+	let (other_doctor_account_id, _other_doctor) = generate_account(3);
+	new_test_ext().execute_with(|| {
+		register_encryption_key(doctor_account_id);
+		register_encryption_key(other_doctor_account_id);
+		assert_ok!(RecordSharing::share_record(
+			patient.clone(),
+			doctor_account_id,
+			sealed_key(),
+			1,
+			1,
+			Some(ValidityWindow { starts_at: 2, ends_at: 4 }),
+		));
+		assert_ok!(RecordSharing::share_record(
+			patient,
+			other_doctor_account_id,
+			sealed_key(),
+			1,
+			2,
+			None,
+		));
+
+		let grant = api::GrantInfo {
+			sender: patient_account_id,
+			recipient: doctor_account_id,
+			record_id: 1,
+			sealed_key: api::SealedKey {
+				version: ENVELOPE_VERSION,
+				algorithm: ALGORITHM_X25519_XSALSA20_POLY1305,
+				ephemeral_public_key: [7; 32],
+				nonce: [9; 24],
+				ciphertext: vec![1, 2, 3],
+			},
+			recipient_key_id: 1,
+			validity: Some((2, 4)),
+		};
+		assert_eq!(
+			RecordSharing::grants_to(&doctor_account_id, 0, u32::MAX).items,
+			vec![grant.clone()]
+		);
+		let mut grants_by = RecordSharing::grants_by(&patient_account_id, 0, u32::MAX).items;
+		grants_by.sort_by_key(|grant| grant.record_id);
+		assert_eq!(grants_by.len(), 2);
+		assert_eq!(grants_by[0], grant);
+		assert_eq!(grants_by[1].recipient, other_doctor_account_id);
+		assert!(RecordSharing::grants_by(&doctor_account_id, 0, u32::MAX).items.is_empty());
+	})
+}
+
+#[test]
+fn grants_are_served_in_pages() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (doctor_account_id, _doctor) = generate_account(2);
+	new_test_ext().execute_with(|| {
+		register_encryption_key(doctor_account_id);
+		for record_id in 1..=3 {
+			assert_ok!(RecordSharing::share_record(
+				patient.clone(),
+				doctor_account_id,
+				sealed_key(),
+				1,
+				record_id,
+				None,
+			));
+		}
+
+		let record_ids = |page: api::Page<api::GrantInfo<AccountId, u64>>| -> Vec<_> {
+			page.items.into_iter().map(|grant| grant.record_id).collect()
+		};
+		let page = RecordSharing::grants_by(&patient_account_id, 1, 1);
+		assert_eq!(page.total, 3);
+		assert_eq!(record_ids(page), vec![2]);
+		assert_eq!(record_ids(RecordSharing::grants_to(&doctor_account_id, 1, 5)), vec![2, 3]);
+		let page = RecordSharing::grants_to(&doctor_account_id, 3, 5);
+		assert_eq!(page.total, 3);
+		assert!(page.items.is_empty());
+	})
+}
+
+#[test]
+fn expired_and_revoked_grants_are_not_served() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (doctor_account_id, _doctor) = generate_account(2);
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		register_encryption_key(doctor_account_id);
+		assert_ok!(RecordSharing::share_record(
+			patient.clone(),
+			doctor_account_id,
+			sealed_key(),
+			1,
+			1,
+			Some(ValidityWindow { starts_at: 1, ends_at: 3 }),
+		));
+		assert_ok!(RecordSharing::share_record(
+			patient.clone(),
+			doctor_account_id,
+			sealed_key(),
+			1,
+			2,
+			None,
+		));
+		assert!(GrantsByRecipient::<Test>::contains_key(doctor_account_id, patient_account_id));
+		assert_eq!(RecordSharing::grants_to(&doctor_account_id, 0, u32::MAX).items.len(), 2);
+
+		// An expired grant is left out even before `on_idle` removes it
+		System::set_block_number(3);
+		let grants_to = RecordSharing::grants_to(&doctor_account_id, 0, u32::MAX).items;
+		assert_eq!(grants_to.iter().map(|grant| grant.record_id).collect::<Vec<_>>(), vec![2]);
+		assert_eq!(RecordSharing::grants_by(&patient_account_id, 0, u32::MAX).items, grants_to);
+
+		// The recipient is no longer indexed once nothing is shared with them
+		assert_ok!(RecordSharing::revoke_share(patient, doctor_account_id, 2));
+		RecordSharing::on_idle(3, Weight::MAX);
+		assert!(!GrantsByRecipient::<Test>::contains_key(doctor_account_id, patient_account_id));
+		assert!(RecordSharing::grants_to(&doctor_account_id, 0, u32::MAX).items.is_empty());
+	})
+}
+
+#[test]
+fn can_rotate_encryption_keys() {
+	let (patient_account_id, patient) = generate_account(1);
+	let (doctor_account_id, doctor) = generate_account(2);
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert!(RecordSharing::current_encryption_key(&doctor_account_id).is_none());
+		// Nothing can be shared before the recipient publishes a key
+		assert_noop!(
+			RecordSharing::share_record(
+				patient.clone(),
+				doctor_account_id,
+				sealed_key(),
+				1,
+				1,
+				None,
+			),
+			Error::<Test>::UnknownEncryptionKey
+		);
+
+		for public_key in [[1; 32], [2; 32], [3; 32]] {
+			assert_ok!(RecordSharing::register_encryption_key(doctor.clone(), public_key));
+		}
+		System::assert_last_event(Event::EncryptionKeyRegistered(doctor_account_id, 3).into());
+
+		// Only the latest keys are kept
+		let keys = RecordSharing::encryption_keys(doctor_account_id);
+		assert_eq!(keys.iter().map(|key| key.key_id).collect::<Vec<_>>(), vec![2, 3]);
+		let current_key = RecordSharing::current_encryption_key(&doctor_account_id)
+			.expect("a key was registered");
+		assert_eq!(current_key.public_key, [3; 32]);
+
+		// Keys must be wrapped for the current key
+		assert_noop!(
+			RecordSharing::share_record(
+				patient.clone(),
+				doctor_account_id,
+				sealed_key(),
+				2,
+				1,
+				None,
+			),
+			Error::<Test>::UnknownEncryptionKey
+		);
+		assert_ok!(RecordSharing::share_record(
+			patient,
+			doctor_account_id,
+			sealed_key(),
+			3,
+			1,
+			None,
+		));
+		assert_eq!(
+			RecordSharing::records_shared(patient_account_id, doctor_account_id)[0]
+				.recipient_key_id,
+			3
+		);
+	})
+}
+
+#[test]
+fn rejects_unknown_envelope_formats() {
+	let (_patient_account_id, patient) = generate_account(1);
+	let (doctor_account_id, _doctor) = generate_account(2);
+	new_test_ext().execute_with(|| {
+		register_encryption_key(doctor_account_id);
+		let unknown_version = SealedEnvelope { version: ENVELOPE_VERSION + 1, ..sealed_key() };
+		assert_noop!(
+			RecordSharing::share_record(
+				patient.clone(),
+				doctor_account_id,
+				unknown_version,
+				1,
+				1,
+				None,
+			),
+			Error::<Test>::UnsupportedEnvelopeVersion
+		);
+		let unknown_algorithm = SealedEnvelope { algorithm: u8::MAX, ..sealed_key() };
+		assert_noop!(
+			RecordSharing::share_record(patient, doctor_account_id, unknown_algorithm, 1, 1, None,),
+			Error::<Test>::UnsupportedAlgorithm
+		);
+	})
+}
+
+#[test]
+fn migration_keeps_baseline_sharings() {
+	let (sender_account_id, _) = generate_account(1);
+	let (doctor_account_id, _) = generate_account(2);
+	let (nurse_account_id, _) = generate_account(3);
+	new_test_ext().execute_with(|| {
+		// Sharings as the first version of the pallet stored them: a key of words next to the
+		// id of each shared record.
+		let shared_records_key = |sender: AccountId, recipient: AccountId| {
+			let mut key = storage_prefix(b"RecordSharing", b"SharedRecords").to_vec();
+			key.extend(Blake2_128Concat::hash(&sender.encode()));
+			key.extend(Blake2_128Concat::hash(&recipient.encode()));
+			key
+		};
+		let too_long_key = vec![0u32; MockMaxCiphertextLength::get() as usize / 4 + 1];
+		unhashed::put_raw(
+			&shared_records_key(sender_account_id, doctor_account_id),
+			&vec![(vec![1u32, 0x0403_0201], 1u32), (too_long_key, 2)].encode(),
+		);
+		unhashed::put_raw(
+			&shared_records_key(sender_account_id, nurse_account_id),
+			&vec![(vec![7u32], 3u32)].encode(),
+		);
+		StorageVersion::new(0).put::<RecordSharing>();
+		System::set_block_number(1);
+
+		MigrateToV1::<Test>::on_runtime_upgrade();
+
+		assert_eq!(StorageVersion::get::<RecordSharing>(), 1);
+		assert!(GrantsByRecipient::<Test>::contains_key(doctor_account_id, sender_account_id));
+		assert_eq!(RecordSharing::grants_to(&nurse_account_id, 0, u32::MAX).items.len(), 1);
+		let legacy_key = |ciphertext: Vec<u8>| SealedEnvelope {
+			version: LEGACY_ENVELOPE_VERSION,
+			algorithm: 0,
+			ephemeral_public_key: [0; 32],
+			nonce: [0; 24],
+			ciphertext: ciphertext.try_into().unwrap(),
+		};
+		// The key that does not fit in an envelope is dropped, and the sender is told.
+		System::assert_has_event(
+			Event::LegacyGrantDropped(sender_account_id, doctor_account_id, 2).into(),
+		);
+		assert_eq!(
+			RecordSharing::records_shared(sender_account_id, doctor_account_id).into_inner(),
+			vec![Grant {
+				encrypted_key: legacy_key(vec![1, 0, 0, 0, 1, 2, 3, 4]),
+				recipient_key_id: 0,
+				record_id: 1,
+				validity: None,
+				deposit: 0,
+			}]
+		);
+		assert_eq!(
+			RecordSharing::records_shared(sender_account_id, nurse_account_id).into_inner(),
+			vec![Grant {
+				encrypted_key: legacy_key(vec![7, 0, 0, 0]),
+				recipient_key_id: 0,
+				record_id: 3,
+				validity: None,
+				deposit: 0,
+			}]
+		);
+	})
+}
+
+fn sealed_key() -> SealedEnvelope<Test> {
+	SealedEnvelope {
+		version: ENVELOPE_VERSION,
+		algorithm: ALGORITHM_X25519_XSALSA20_POLY1305,
+		ephemeral_public_key: [7; 32],
+		nonce: [9; 24],
+		ciphertext: bounded_vec![1, 2, 3],
+	}
+}
+
+fn register_encryption_key(account_id: AccountId) {
+	assert_ok!(RecordSharing::register_encryption_key(
+		RuntimeOrigin::signed(account_id),
+		[account_id as u8; 32]
+	));
+}
+
 fn generate_account(account_id: AccountId) -> (AccountId, RuntimeOrigin) {
 	(account_id, RuntimeOrigin::signed(account_id))
 }
@@ -105,10 +105,12 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	//   `spec_version`, and `authoring_version` are the same between Wasm and native.
 	// This value is set to 100 to notify Polkadot-JS App (https://polkadot.js.org/apps) to use
 	//   the compatible custom types.
-	spec_version: 100,
+	// Bumped to 101 for the medical record and record sharing storage migrations.
+	spec_version: 101,
 	impl_version: 1,
 	apis: RUNTIME_API_VERSIONS,
-	transaction_version: 1,
+	// Bumped to 2: the medical record and record sharing calls changed.
+	transaction_version: 2,
 	state_version: 1,
 };
 
@@ -259,21 +261,143 @@ impl pallet_balances::Config for Runtime {
 
 parameter_types! {
 	pub const MaxRecordContentLength: u32 = 300;
-	pub const SignatureLength: u32 = 150;
-	pub const MaxKeyLength: u32 = 69; // fix later
+	/// As many grants as a sender could give a recipient before grants were bounded on their own,
+	/// so the upgrade keeps all of them.
+	pub const MaxSharedRecords: u32 = 69;
+	/// A sealed record key is a 32 byte key plus the 16 byte authentication tag. Keys shared
+	/// before envelopes existed were up to 69 words, i.e. 276 bytes, and are kept as they are.
+	pub const MaxCiphertextLength: u32 = 276;
 	pub const MaxRecordLength: u32 = 50;
+	pub const MaxExpiriesPerBlock: u32 = 100;
+	pub const MaxKeyHistory: u32 = 10;
+	/// Has to fit into a block along with the rest of the extrinsic.
+	pub const MaxPayloadLength: u32 = 3 * 1024 * 1024;
+	pub const MaxMediaTypeLength: u32 = 64;
+	pub const MaxRecordVersions: u32 = 10;
+	pub const MaxEndorsements: u32 = 8;
+	pub const MaxProfileLength: u32 = 256;
+	pub const MaxTitleLength: u32 = 128;
+	pub const MaxPurposeLength: u32 = 256;
+	pub const MaxAuditEntries: u32 = 100;
+	pub const MaxRequestedRecords: u32 = 16;
+	pub const AccessRequestLifetime: BlockNumber = 7 * DAYS;
+	pub const MaxRequestExpiriesPerBlock: u32 = 64;
+	/// As much as 100 bytes of storage.
+	pub const AccessRequestDeposit: Balance = 100_000_000;
+	pub const MaxOpenRequests: u32 = 4;
+	/// Reserved for every byte a record or a sharing grant takes up in storage.
+	pub const DepositPerByte: Balance = 1_000_000;
 }
 
 impl pallet_medical_record::Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
 	type MaxRecordContentLength = MaxRecordContentLength;
-	type SignatureLength = SignatureLength;
+	type Signature = Signature;
+	type Signer = <Signature as Verify>::Signer;
 	type MaxRecordLength = MaxRecordLength;
+	type LicensingOrigin = frame_system::EnsureRoot<AccountId>;
+	type PermissionsOrigin = frame_system::EnsureRoot<AccountId>;
+	type RecordSharing = RecordSharing;
+	type MaxPayloadLength = MaxPayloadLength;
+	type MaxMediaTypeLength = MaxMediaTypeLength;
+	type MaxRecordVersions = MaxRecordVersions;
+	type MaxEndorsements = MaxEndorsements;
+	type MaxProfileLength = MaxProfileLength;
+	type MaxTitleLength = MaxTitleLength;
+	type MaxPurposeLength = MaxPurposeLength;
+	type MaxAuditEntries = MaxAuditEntries;
+	type MaxRequestedRecords = MaxRequestedRecords;
+	type AccessRequestLifetime = AccessRequestLifetime;
+	type MaxRequestExpiriesPerBlock = MaxRequestExpiriesPerBlock;
+	type AccessRequestDeposit = AccessRequestDeposit;
+	type MaxOpenRequests = MaxOpenRequests;
+	type Currency = Balances;
+	type DepositPerByte = DepositPerByte;
+	type WeightInfo = pallet_medical_record::weights::SubstrateWeight<Runtime>;
+	#[cfg(feature = "runtime-benchmarks")]
+	type BenchmarkHelper = MedicalRecordBenchmarkHelper;
+}
+
+/// Key type of the keys the medical record benchmarks sign with.
+#[cfg(feature = "runtime-benchmarks")]
+const MEDICAL_RECORD_BENCHMARK_KEY: KeyTypeId = KeyTypeId(*b"mrec");
+
+#[cfg(feature = "runtime-benchmarks")]
+pub struct MedicalRecordBenchmarkHelper;
+
+#[cfg(feature = "runtime-benchmarks")]
+impl pallet_medical_record::BenchmarkHelper<Runtime> for MedicalRecordBenchmarkHelper {
+	fn signer() -> AccountId {
+		sp_io::crypto::sr25519_generate(MEDICAL_RECORD_BENCHMARK_KEY, None).0.into()
+	}
+
+	fn sign(signer: &AccountId, message: &[u8]) -> Signature {
+		let public = sp_core::sr25519::Public(signer.clone().into());
+		sp_io::crypto::sr25519_sign(MEDICAL_RECORD_BENCHMARK_KEY, &public, message)
+			.expect("the signer's key is in the keystore")
+			.into()
+	}
+
+	fn sealed_key(
+		recipient: &AccountId,
+	) -> (pallet_record_sharing::EncryptedKey<Runtime>, pallet_record_sharing::KeyId) {
+		RecordSharing::register_encryption_key(RuntimeOrigin::signed(recipient.clone()), [1; 32])
+			.expect("any account can register a key");
+		let sealed_key = pallet_record_sharing::SealedEnvelope {
+			version: pallet_record_sharing::ENVELOPE_VERSION,
+			algorithm: pallet_record_sharing::ALGORITHM_X25519_XSALSA20_POLY1305,
+			ephemeral_public_key: [2; 32],
+			nonce: [3; 24],
+			ciphertext: frame_support::BoundedVec::truncate_from(vec![
+				4;
+				MaxCiphertextLength::get()
+					as usize
+			]),
+		};
+		let key_id = RecordSharing::current_encryption_key(recipient)
+			.expect("the key was just registered")
+			.key_id;
+		(sealed_key, key_id)
+	}
 }
 
 impl pallet_record_sharing::Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
-	type MaxKeyLength = MaxKeyLength;
+	type MaxSharedRecords = MaxSharedRecords;
+	type MaxCiphertextLength = MaxCiphertextLength;
+	type MaxExpiriesPerBlock = MaxExpiriesPerBlock;
+	type Records = MedicalRecord;
+	type MaxKeyHistory = MaxKeyHistory;
+	type Currency = Balances;
+	type DepositPerByte = DepositPerByte;
+	type WeightInfo = pallet_record_sharing::weights::SubstrateWeight<Runtime>;
+	#[cfg(feature = "runtime-benchmarks")]
+	type BenchmarkHelper = RecordSharingBenchmarkHelper;
+}
+
+#[cfg(feature = "runtime-benchmarks")]
+pub struct RecordSharingBenchmarkHelper;
+
+#[cfg(feature = "runtime-benchmarks")]
+impl pallet_record_sharing::BenchmarkHelper<AccountId> for RecordSharingBenchmarkHelper {
+	fn register(account: &AccountId) {
+		MedicalRecord::create_account(
+			RuntimeOrigin::signed(account.clone()),
+			pallet_medical_record::UserType::Patient,
+		)
+		.expect("the account is new");
+	}
+
+	fn add_record(owner: &AccountId) -> pallet_record_sharing::RecordId {
+		MedicalRecord::patient_adds_record(
+			RuntimeOrigin::signed(owner.clone()),
+			pallet_medical_record::RecordCategory::Note,
+			None,
+			Default::default(),
+		)
+		.expect("the patient has room for a record");
+		MedicalRecord::last_record_id(owner)
+	}
 }
 
 parameter_types! {
@@ -346,8 +470,15 @@ pub type Executive = frame_executive::Executive<
 	frame_system::ChainContext<Runtime>,
 	Runtime,
 	AllPalletsWithSystem,
+	Migrations,
 >;
 
+/// Storage migrations to run on the next runtime upgrade.
+type Migrations = (
+	pallet_medical_record::migrations::v1::MigrateToV1<Runtime>,
+	pallet_record_sharing::migrations::v1::MigrateToV1<Runtime>,
+);
+
 #[cfg(feature = "runtime-benchmarks")]
 #[macro_use]
 extern crate frame_benchmarking;
@@ -359,6 +490,8 @@ mod benches {
 		[frame_system, SystemBench::<Runtime>]
 		[pallet_balances, Balances]
 		[pallet_timestamp, Timestamp]
+		[pallet_medical_record, MedicalRecord]
+		[pallet_record_sharing, RecordSharing]
 	);
 }
 
@@ -510,6 +643,68 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl pallet_medical_record_runtime_api::MedicalRecordApi<Block, AccountId, Hash, BlockNumber>
+		for Runtime
+	{
+		fn account_roles(account: AccountId) -> Vec<pallet_medical_record_runtime_api::Role> {
+			MedicalRecord::account_roles(&account)
+		}
+
+		fn records_of(
+			patient: AccountId,
+			offset: u32,
+			limit: u32,
+		) -> pallet_medical_record_runtime_api::Page<
+			pallet_medical_record_runtime_api::RecordInfo<AccountId, Hash, BlockNumber>,
+		> {
+			MedicalRecord::record_infos_page(&patient, offset, limit)
+		}
+
+		fn record(
+			patient: AccountId,
+			id: pallet_medical_record_runtime_api::RecordId,
+		) -> Option<pallet_medical_record_runtime_api::RecordInfo<AccountId, Hash, BlockNumber>> {
+			MedicalRecord::record_info(&patient, id)
+		}
+
+		fn records_matching(
+			patient: AccountId,
+			filter: pallet_medical_record_runtime_api::RecordFilter<BlockNumber>,
+		) -> Vec<pallet_medical_record_runtime_api::RecordInfo<AccountId, Hash, BlockNumber>> {
+			MedicalRecord::record_infos_matching(&patient, filter)
+		}
+
+		fn audit_log(
+			patient: AccountId,
+		) -> Vec<pallet_medical_record_runtime_api::AuditEntry<AccountId, BlockNumber>> {
+			MedicalRecord::audit_infos_of(&patient)
+		}
+	}
+
+	impl pallet_record_sharing_runtime_api::RecordSharingApi<Block, AccountId, BlockNumber>
+		for Runtime
+	{
+		fn grants_by(
+			sender: AccountId,
+			offset: u32,
+			limit: u32,
+		) -> pallet_record_sharing_runtime_api::Page<
+			pallet_record_sharing_runtime_api::GrantInfo<AccountId, BlockNumber>,
+		> {
+			RecordSharing::grants_by(&sender, offset, limit)
+		}
+
+		fn grants_to(
+			recipient: AccountId,
+			offset: u32,
+			limit: u32,
+		) -> pallet_record_sharing_runtime_api::Page<
+			pallet_record_sharing_runtime_api::GrantInfo<AccountId, BlockNumber>,
+		> {
+			RecordSharing::grants_to(&recipient, offset, limit)
+		}
+	}
+
 	#[cfg(feature = "runtime-benchmarks")]
 	impl frame_benchmarking::Benchmark<Block> for Runtime {
 		fn benchmark_metadata(extra: bool) -> (